@@ -0,0 +1,136 @@
+use crate::models::FrameStats;
+use std::fmt;
+
+/// Frame times and draw counts of a run, summarized by `report`.
+#[derive(Default)]
+pub struct Benchmark {
+    /// seconds
+    frame_times: Vec<f32>,
+    drawn: usize,
+    culled: usize,
+    draw_calls: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BenchmarkReport {
+    pub frames: usize,
+    /// all times in milliseconds
+    pub average: f32,
+    pub min: f32,
+    pub max: f32,
+    /// 99% of the frames took less than this
+    pub percentile_99: f32,
+    pub fps: f32,
+    /// per frame
+    pub drawn: f32,
+    pub culled: f32,
+    pub draw_calls: f32,
+}
+
+impl Benchmark {
+    pub fn new() -> Self {
+        Benchmark::default()
+    }
+
+    pub fn record(&mut self, frame_time: f32, stats: FrameStats) {
+        self.frame_times.push(frame_time);
+        self.drawn += stats.drawn;
+        self.culled += stats.culled;
+        self.draw_calls += stats.draw_calls;
+    }
+
+    /// `None` before the first frame
+    pub fn report(&self) -> Option<BenchmarkReport> {
+        if self.frame_times.is_empty() {
+            return None;
+        }
+
+        let mut sorted: Vec<f32> = self.frame_times.iter().map(|time| time * 1000.0).collect();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let frames = sorted.len();
+        let total: f32 = sorted.iter().sum();
+        let average = total / frames as f32;
+        // nearest rank
+        let rank = ((frames as f32 * 0.99).ceil() as usize).max(1);
+
+        Some(BenchmarkReport {
+            frames,
+            average,
+            min: sorted[0],
+            max: sorted[frames - 1],
+            percentile_99: sorted[rank - 1],
+            fps: 1000.0 / average,
+            drawn: self.drawn as f32 / frames as f32,
+            culled: self.culled as f32 / frames as f32,
+            draw_calls: self.draw_calls as f32 / frames as f32,
+        })
+    }
+}
+
+impl fmt::Display for BenchmarkReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "frames:      {}", self.frames)?;
+        writeln!(f, "fps:         {:.1}", self.fps)?;
+        writeln!(
+            f,
+            "frame time:  avg {:.3} ms, min {:.3} ms, max {:.3} ms, p99 {:.3} ms",
+            self.average, self.min, self.max, self.percentile_99
+        )?;
+        write!(
+            f,
+            "per frame:   {:.1} drawn, {:.1} culled, {:.1} draw calls",
+            self.drawn, self.culled, self.draw_calls
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(drawn: usize) -> FrameStats {
+        FrameStats {
+            drawn,
+            culled: 1,
+            draw_calls: 2,
+        }
+    }
+
+    #[test]
+    fn empty_benchmark_has_no_report() {
+        assert_eq!(Benchmark::new().report(), None);
+    }
+
+    #[test]
+    fn report_summarizes_frames() {
+        let mut benchmark = Benchmark::new();
+        benchmark.record(0.010, stats(10));
+        benchmark.record(0.020, stats(20));
+        benchmark.record(0.030, stats(30));
+        benchmark.record(0.020, stats(40));
+
+        let report = benchmark.report().unwrap();
+        assert_eq!(report.frames, 4);
+        assert!((report.average - 20.0).abs() < 0.001);
+        assert!((report.min - 10.0).abs() < 0.001);
+        assert!((report.max - 30.0).abs() < 0.001);
+        assert!((report.fps - 50.0).abs() < 0.01);
+        assert!((report.drawn - 25.0).abs() < 0.001);
+        assert!((report.draw_calls - 2.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn percentile_ignores_the_slowest_one_percent() {
+        let mut benchmark = Benchmark::new();
+        for _ in 0..199 {
+            benchmark.record(0.010, stats(0));
+        }
+        benchmark.record(1.0, stats(0));
+        benchmark.record(1.0, stats(0));
+
+        let report = benchmark.report().unwrap();
+        assert!((report.percentile_99 - 10.0).abs() < 0.001);
+        assert!((report.max - 1000.0).abs() < 0.001);
+    }
+}
@@ -0,0 +1,903 @@
+use crate::character::CharacterController;
+use crate::coordinates::SphereVector;
+use crate::math::{clamp, Matrix4, Quaternion, Ray, Vector3};
+use crate::matrices::MatrixOperation;
+use crate::models::{Camera, DeviceManager};
+use crate::physics::PhysicsWorld;
+
+use glium::glutin::event::{MouseButton, VirtualKeyCode};
+
+/// Orbits around a target point, the position is kept as a `SphereVector` from the target.
+/// Input moves the goal values and the camera follows them with exponential damping.
+/// Positive elevation is above the target here, `SphereVector` measures it the other way.
+pub struct OrbitCamera {
+    target: Vector3,
+    goal_target: Vector3,
+    orbit: SphereVector,
+    goal: SphereVector,
+    pub min_radius: f32,
+    pub max_radius: f32,
+    /// degrees per pixel of mouse movement
+    pub rotate_speed: f32,
+    /// fraction of the radius per wheel line
+    pub zoom_speed: f32,
+    /// fraction of the radius per pixel of mouse movement
+    pub pan_speed: f32,
+    /// how fast the camera catches up with the goal, per second. 0.0 disables the damping
+    pub damping: f32,
+    /// the mouse only rotates while this button is held, always when `None`
+    pub rotate_button: Option<MouseButton>,
+}
+
+#[allow(dead_code)]
+impl OrbitCamera {
+    pub fn new(target: Vector3, radius: f32, elevation: f32, azimuthal: f32) -> Self {
+        let orbit = SphereVector::new(radius, -elevation, azimuthal);
+
+        OrbitCamera {
+            target,
+            goal_target: target,
+            orbit,
+            goal: orbit,
+            min_radius: 2.0,
+            max_radius: 100.0,
+            rotate_speed: 0.3,
+            zoom_speed: 0.1,
+            pan_speed: 0.002,
+            damping: 10.0,
+            rotate_button: Some(MouseButton::Right),
+        }
+    }
+
+    pub fn get_target(&self) -> Vector3 {
+        self.target
+    }
+
+    pub fn get_position(&self) -> Vector3 {
+        self.target + self.orbit.to_cartesian()
+    }
+
+    pub fn get_radius(&self) -> f32 {
+        self.orbit.radius()
+    }
+
+    pub fn get_elevation(&self) -> f32 {
+        -self.orbit.elevation()
+    }
+
+    pub fn get_azimuthal(&self) -> f32 {
+        self.orbit.azimuthal()
+    }
+
+    /// the camera glides to the new target
+    pub fn set_target(&mut self, target: Vector3) {
+        self.goal_target = target;
+    }
+
+    /// moves target and orbit at once, without damping
+    pub fn snap_to(&mut self, target: Vector3) {
+        self.target = target;
+        self.goal_target = target;
+        self.orbit = self.goal;
+    }
+
+    /// degrees, positive vertical goes up
+    pub fn rotate(&mut self, horizontal: f32, vertical: f32) {
+        self.goal = SphereVector::new(
+            self.goal.radius(),
+            self.goal.elevation() - vertical,
+            self.goal.azimuthal() + horizontal,
+        );
+    }
+
+    /// positive lines get closer to the target
+    pub fn zoom(&mut self, lines: f32) {
+        let radius = self.goal.radius() * (1.0 - self.zoom_speed).powf(lines);
+
+        self.goal = SphereVector::new(
+            radius.max(self.min_radius).min(self.max_radius),
+            self.goal.elevation(),
+            self.goal.azimuthal(),
+        );
+    }
+
+    /// moves the target on the view plane, farther cameras pan faster
+    pub fn pan(&mut self, horizontal: f32, vertical: f32) {
+        let forward = (-self.goal.to_cartesian()).normalized();
+        let side = Vector3::new(0.0, 1.0, 0.0).cross(forward).normalized();
+        let up = forward.cross(side);
+        let scale = self.goal.radius() * self.pan_speed;
+
+        self.goal_target = self.goal_target + side * (horizontal * scale) + up * (vertical * scale);
+    }
+
+    /// right button drag rotates, shift drag pans and the wheel zooms
+    pub fn update(&mut self, device_manager: &DeviceManager, camera: &mut Camera, dt: f32) {
+        let mouse_x = device_manager.get_last_mouse_movement_x();
+        let mouse_y = device_manager.get_last_mouse_movement_y();
+
+        if device_manager.is_shift_pressed() {
+            self.pan(mouse_x, mouse_y);
+        } else if self
+            .rotate_button
+            .is_none_or(|button| device_manager.is_mouse_button_pressed(button))
+        {
+            self.rotate(-mouse_x * self.rotate_speed, mouse_y * self.rotate_speed);
+        }
+
+        self.zoom(device_manager.get_last_mouse_wheel());
+        self.step(dt);
+
+        camera.look_at(self.get_position(), self.target);
+    }
+
+    /// moves the current values towards the goal
+    pub fn step(&mut self, dt: f32) {
+        let amount = damping_amount(self.damping, dt);
+
+        self.orbit = self.orbit.lerp(self.goal, amount);
+        self.target = self.target + (self.goal_target - self.target) * amount;
+    }
+}
+
+/// fraction of the way to the goal covered this frame, frame rate independent
+fn damping_amount(damping: f32, dt: f32) -> f32 {
+    if damping > 0.0 {
+        1.0 - (-damping * dt).exp()
+    } else {
+        1.0
+    }
+}
+
+/// shared by the fly and first person cameras
+#[derive(Debug, Clone, Copy)]
+pub struct MovementSettings {
+    /// units per second
+    pub speed: f32,
+    /// speed multiplier while shift is held
+    pub sprint_multiplier: f32,
+    /// units per second squared, how fast the velocity reaches the wanted one
+    pub acceleration: f32,
+    /// degrees per pixel of mouse movement
+    pub mouse_sensibility: f32,
+    /// degrees, positive pitch looks down
+    pub min_pitch: f32,
+    pub max_pitch: f32,
+}
+
+impl Default for MovementSettings {
+    fn default() -> Self {
+        MovementSettings {
+            speed: 8.0,
+            sprint_multiplier: 2.5,
+            acceleration: 40.0,
+            mouse_sensibility: 0.5,
+            min_pitch: -80.0,
+            max_pitch: 80.0,
+        }
+    }
+}
+
+/// yaw turns around y and pitch around the turned x axis, the same order `Instance` rotations use
+#[derive(Debug, Clone, Copy)]
+struct YawPitch {
+    yaw: f32,
+    pitch: f32,
+}
+
+impl YawPitch {
+    /// the inverse of `rotation`, for cameras that start looking somewhere
+    fn from_forward(forward: Vector3) -> Self {
+        let forward = forward.normalized();
+
+        YawPitch {
+            yaw: forward.x.atan2(forward.z).to_degrees(),
+            pitch: clamp(-forward.y, -1.0, 1.0).asin().to_degrees(),
+        }
+    }
+
+    /// pitch clamped to what the settings allow
+    fn within(mut self, settings: &MovementSettings) -> Self {
+        self.pitch = clamp(self.pitch, settings.min_pitch, settings.max_pitch);
+        self
+    }
+
+    fn turn(&mut self, device_manager: &DeviceManager, settings: &MovementSettings) {
+        self.yaw -= device_manager.get_last_mouse_movement_x() * settings.mouse_sensibility;
+        self.pitch = clamp(
+            self.pitch + device_manager.get_last_mouse_movement_y() * settings.mouse_sensibility,
+            settings.min_pitch,
+            settings.max_pitch,
+        );
+    }
+
+    /// yaw takes the shortest way around
+    fn lerp(self, other: YawPitch, amount: f32) -> YawPitch {
+        let yaw_difference = (other.yaw - self.yaw + 180.0).rem_euclid(360.0) - 180.0;
+
+        YawPitch {
+            yaw: self.yaw + yaw_difference * amount,
+            pitch: self.pitch + (other.pitch - self.pitch) * amount,
+        }
+    }
+
+    fn rotation(self) -> Matrix4 {
+        Quaternion::rotate_y(self.yaw) * Quaternion::rotate_x(self.pitch)
+    }
+
+    /// forward without the pitch, for walking
+    fn flat_forward(self) -> Vector3 {
+        Quaternion::rotate_y(self.yaw).get_forward_vector()
+    }
+}
+
+/// keys as the rest of the scene uses them: W/S forward, A/D side, Q/E up and down
+fn movement_keys(device_manager: &DeviceManager) -> (f32, f32, f32) {
+    let axis = |positive: VirtualKeyCode, negative: VirtualKeyCode| {
+        let mut value = 0.0;
+        if device_manager.is_key_pressed(positive) {
+            value += 1.0;
+        }
+        if device_manager.is_key_pressed(negative) {
+            value -= 1.0;
+        }
+        value
+    };
+
+    (
+        axis(VirtualKeyCode::W, VirtualKeyCode::S),
+        axis(VirtualKeyCode::A, VirtualKeyCode::D),
+        axis(VirtualKeyCode::Q, VirtualKeyCode::E),
+    )
+}
+
+/// full speed on any direction, diagonals are not faster
+fn wish_velocity(
+    direction: Vector3,
+    device_manager: &DeviceManager,
+    settings: &MovementSettings,
+) -> Vector3 {
+    if direction.length() == 0.0 {
+        return direction;
+    }
+
+    let speed = if device_manager.is_shift_pressed() {
+        settings.speed * settings.sprint_multiplier
+    } else {
+        settings.speed
+    };
+
+    direction.normalized() * speed
+}
+
+/// moves `velocity` towards `wish` by at most `acceleration * dt`
+fn accelerate(velocity: Vector3, wish: Vector3, acceleration: f32, dt: f32) -> Vector3 {
+    let difference = wish - velocity;
+    let length = difference.length();
+    let max_change = acceleration * dt;
+
+    if length <= max_change {
+        wish
+    } else {
+        velocity + difference * (max_change / length)
+    }
+}
+
+/// Free camera, moves along where it looks and Q/E move it along world up
+pub struct FlyCamera {
+    position: Vector3,
+    look: YawPitch,
+    velocity: Vector3,
+    pub settings: MovementSettings,
+}
+
+#[allow(dead_code)]
+impl FlyCamera {
+    pub fn new(position: Vector3, yaw: f32, pitch: f32) -> Self {
+        FlyCamera {
+            position,
+            look: YawPitch { yaw, pitch },
+            velocity: Vector3::new(0.0, 0.0, 0.0),
+            settings: MovementSettings::default(),
+        }
+    }
+
+    pub fn with_settings(mut self, settings: MovementSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    pub fn get_position(&self) -> Vector3 {
+        self.position
+    }
+
+    pub fn get_yaw(&self) -> f32 {
+        self.look.yaw
+    }
+
+    pub fn get_pitch(&self) -> f32 {
+        self.look.pitch
+    }
+
+    pub fn get_velocity(&self) -> Vector3 {
+        self.velocity
+    }
+
+    /// takes over from wherever another camera was, stopped
+    pub fn look_along(&mut self, position: Vector3, forward: Vector3) {
+        self.position = position;
+        self.look = YawPitch::from_forward(forward).within(&self.settings);
+        self.velocity = Vector3::new(0.0, 0.0, 0.0);
+    }
+
+    pub fn update(&mut self, device_manager: &DeviceManager, camera: &mut Camera, dt: f32) {
+        self.look.turn(device_manager, &self.settings);
+
+        let rotation = self.look.rotation();
+        let (front, side, up) = movement_keys(device_manager);
+        let direction = rotation.get_forward_vector() * front
+            + rotation.get_side_vector() * side
+            + Vector3::new(0.0, up, 0.0);
+
+        let wish = wish_velocity(direction, device_manager, &self.settings);
+        self.velocity = accelerate(self.velocity, wish, self.settings.acceleration, dt);
+        self.position = self.position + self.velocity * dt;
+
+        camera.operations = MatrixOperation::translation(self.position) * rotation;
+    }
+}
+
+/// First person camera, walks on the horizontal plane whatever the pitch is.
+/// It can move on its own or drive a `CharacterController`, with the eyes `eye_height` above its feet
+pub struct FpsCamera {
+    position: Vector3,
+    look: YawPitch,
+    velocity: Vector3,
+    pub eye_height: f32,
+    pub settings: MovementSettings,
+}
+
+#[allow(dead_code)]
+impl FpsCamera {
+    pub fn new(position: Vector3, yaw: f32) -> Self {
+        FpsCamera {
+            position,
+            look: YawPitch { yaw, pitch: 0.0 },
+            velocity: Vector3::new(0.0, 0.0, 0.0),
+            eye_height: 1.6,
+            settings: MovementSettings::default(),
+        }
+    }
+
+    pub fn with_settings(mut self, settings: MovementSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    pub fn with_eye_height(mut self, eye_height: f32) -> Self {
+        self.eye_height = eye_height;
+        self
+    }
+
+    /// eyes position
+    pub fn get_position(&self) -> Vector3 {
+        self.position
+    }
+
+    pub fn get_yaw(&self) -> f32 {
+        self.look.yaw
+    }
+
+    pub fn get_pitch(&self) -> f32 {
+        self.look.pitch
+    }
+
+    pub fn get_velocity(&self) -> Vector3 {
+        self.velocity
+    }
+
+    pub fn look_along(&mut self, position: Vector3, forward: Vector3) {
+        self.position = position;
+        self.look = YawPitch::from_forward(forward).within(&self.settings);
+        self.velocity = Vector3::new(0.0, 0.0, 0.0);
+    }
+
+    /// moves on its own, keeping its height
+    pub fn update(&mut self, device_manager: &DeviceManager, camera: &mut Camera, dt: f32) {
+        self.walk(device_manager, dt);
+        self.position = self.position + self.velocity * dt;
+        self.apply(camera);
+    }
+
+    /// the character does the moving, so walls, steps and gravity apply. Q jumps
+    pub fn update_character(
+        &mut self,
+        device_manager: &DeviceManager,
+        character: &mut CharacterController,
+        physics: &PhysicsWorld,
+        camera: &mut Camera,
+        dt: f32,
+    ) {
+        self.walk(device_manager, dt);
+        character.update(
+            physics,
+            self.velocity,
+            device_manager.is_key_pressed(VirtualKeyCode::Q),
+            dt,
+        );
+
+        self.position = character.position() + Vector3::new(0.0, self.eye_height, 0.0);
+        self.apply(camera);
+    }
+
+    fn walk(&mut self, device_manager: &DeviceManager, dt: f32) {
+        self.look.turn(device_manager, &self.settings);
+
+        let forward = self.look.flat_forward();
+        let side = Vector3::new(0.0, 1.0, 0.0).cross(forward);
+        let (front, sideways, _) = movement_keys(device_manager);
+
+        let wish = wish_velocity(
+            forward * front + side * sideways,
+            device_manager,
+            &self.settings,
+        );
+        self.velocity = accelerate(self.velocity, wish, self.settings.acceleration, dt);
+    }
+
+    fn apply(&self, camera: &mut Camera) {
+        camera.operations = MatrixOperation::translation(self.position) * self.look.rotation();
+    }
+}
+
+/// Third person camera behind a target transform, like `Camera::set_parent` with a front translation
+/// but damped, looking a bit ahead of where the target moves and pulled in when something
+/// blocks the line of sight, so it never ends inside the scene geometry
+pub struct FollowCamera {
+    focus: Vector3,
+    look: YawPitch,
+    distance: f32,
+    last_target: Option<Vector3>,
+    /// extra degrees of pitch on top of the target one, positive looks down
+    pub pitch: f32,
+    /// behind the target
+    pub offset_distance: f32,
+    /// the camera looks at this point above the target
+    pub offset_height: f32,
+    /// per second, 0.0 disables the damping
+    pub position_damping: f32,
+    pub rotation_damping: f32,
+    /// seconds of target movement the camera looks ahead of it
+    pub look_ahead: f32,
+    /// closest the camera gets to the focus when pulled in
+    pub min_distance: f32,
+    /// kept between the camera and whatever blocks the view
+    pub collision_margin: f32,
+}
+
+#[allow(dead_code)]
+impl FollowCamera {
+    pub fn new(offset_distance: f32, offset_height: f32) -> Self {
+        FollowCamera {
+            focus: Vector3::new(0.0, 0.0, 0.0),
+            look: YawPitch {
+                yaw: 0.0,
+                pitch: 0.0,
+            },
+            distance: offset_distance,
+            last_target: None,
+            pitch: 0.0,
+            offset_distance,
+            offset_height,
+            position_damping: 8.0,
+            rotation_damping: 10.0,
+            look_ahead: 0.2,
+            min_distance: 1.0,
+            collision_margin: 0.3,
+        }
+    }
+
+    pub fn get_focus(&self) -> Vector3 {
+        self.focus
+    }
+
+    /// current distance to the focus, shorter than the offset while pulled in
+    pub fn get_distance(&self) -> f32 {
+        self.distance
+    }
+
+    pub fn get_position(&self) -> Vector3 {
+        self.focus - self.look.rotation().get_forward_vector() * self.distance
+    }
+
+    /// jumps behind the target without damping, for the first frame or after teleports
+    pub fn snap_to(&mut self, target: Matrix4) {
+        self.focus = target.get_position() + Vector3::new(0.0, self.offset_height, 0.0);
+        self.look = YawPitch::from_forward(target.get_forward_vector());
+        self.look.pitch += self.pitch;
+        self.last_target = Some(target.get_position());
+    }
+
+    /// `target` is the followed instance transform, the camera keeps behind its forward vector
+    pub fn update(
+        &mut self,
+        target: Matrix4,
+        physics: &PhysicsWorld,
+        camera: &mut Camera,
+        dt: f32,
+    ) {
+        let target_position = target.get_position();
+        let last_target = match self.last_target {
+            Some(last_target) => last_target,
+            None => {
+                self.snap_to(target);
+                target_position
+            }
+        };
+        self.last_target = Some(target_position);
+
+        let velocity = if dt > 0.0 {
+            (target_position - last_target) * (1.0 / dt)
+        } else {
+            Vector3::new(0.0, 0.0, 0.0)
+        };
+        let goal_focus = target_position
+            + Vector3::new(0.0, self.offset_height, 0.0)
+            + velocity * self.look_ahead;
+
+        let mut goal_look = YawPitch::from_forward(target.get_forward_vector());
+        goal_look.pitch += self.pitch;
+
+        let amount = damping_amount(self.position_damping, dt);
+        self.focus = self.focus + (goal_focus - self.focus) * amount;
+        self.look = self
+            .look
+            .lerp(goal_look, damping_amount(self.rotation_damping, dt));
+
+        // pulling in is immediate, going back out is damped so the camera does not pop
+        let allowed = self.allowed_distance(physics);
+        self.distance = if allowed < self.distance {
+            allowed
+        } else {
+            self.distance + (allowed - self.distance) * amount
+        };
+
+        camera.operations =
+            MatrixOperation::translation(self.get_position()) * self.look.rotation();
+    }
+
+    /// how far back the camera can go before hitting something
+    fn allowed_distance(&self, physics: &PhysicsWorld) -> f32 {
+        let back = -self.look.rotation().get_forward_vector();
+        let ray = Ray::new(self.focus, back);
+
+        match physics.ray_cast(&ray, self.offset_distance + self.collision_margin) {
+            Some((_, hit)) => (hit.distance - self.collision_margin)
+                .max(self.min_distance)
+                .min(self.offset_distance),
+            None => self.offset_distance,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::{Collider, RigidBody};
+
+    fn orbit() -> OrbitCamera {
+        OrbitCamera::new(Vector3::new(1.0, 2.0, 3.0), 10.0, 30.0, 0.0)
+    }
+
+    fn settle(camera: &mut OrbitCamera) {
+        for _ in 0..600 {
+            camera.step(1.0 / 60.0);
+        }
+    }
+
+    #[test]
+    fn orbit_camera_position_from_sphere_vector() {
+        let camera = OrbitCamera::new(Vector3::new(1.0, 2.0, 3.0), 10.0, 30.0, 90.0);
+        let position = camera.get_position();
+
+        assert!(((position - camera.get_target()).length() - 10.0).abs() < 0.0001);
+        // positive elevation is above the target
+        assert!((position.y - (2.0 + 5.0)).abs() < 0.0001);
+        assert!((position.x - 1.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn orbit_camera_damps_towards_goal() {
+        let mut camera = orbit();
+        camera.rotate(90.0, 0.0);
+
+        camera.step(1.0 / 60.0);
+        let first = camera.get_azimuthal();
+        assert!(first > 0.0 && first < 90.0);
+
+        settle(&mut camera);
+        assert!((camera.get_azimuthal() - 90.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn orbit_camera_without_damping_snaps() {
+        let mut camera = orbit();
+        camera.damping = 0.0;
+        camera.rotate(45.0, 0.0);
+        camera.step(1.0 / 60.0);
+
+        assert_eq!(camera.get_azimuthal(), 45.0);
+    }
+
+    #[test]
+    fn orbit_camera_elevation_is_clamped() {
+        let mut camera = orbit();
+        camera.rotate(0.0, 500.0);
+        settle(&mut camera);
+
+        assert!((camera.get_elevation() - 78.75).abs() < 0.001);
+    }
+
+    #[test]
+    fn orbit_camera_zoom_stays_within_limits() {
+        let mut camera = orbit();
+        camera.min_radius = 4.0;
+        camera.max_radius = 20.0;
+
+        camera.zoom(1.0);
+        settle(&mut camera);
+        assert!((camera.get_radius() - 9.0).abs() < 0.001);
+
+        camera.zoom(100.0);
+        settle(&mut camera);
+        assert!((camera.get_radius() - 4.0).abs() < 0.001);
+
+        camera.zoom(-100.0);
+        settle(&mut camera);
+        assert!((camera.get_radius() - 20.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn orbit_camera_pan_moves_target_on_view_plane() {
+        let mut camera = OrbitCamera::new(Vector3::new(0.0, 0.0, 0.0), 10.0, 0.0, 0.0);
+        camera.pan(100.0, 0.0);
+        settle(&mut camera);
+
+        let target = camera.get_target();
+        // looking along -x, panning sideways only moves on z
+        assert!(target.x.abs() < 0.0001);
+        assert!(target.y.abs() < 0.0001);
+        assert!((target.z.abs() - 2.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn orbit_camera_writes_look_at_into_camera() {
+        let mut orbit = orbit();
+        orbit.snap_to(Vector3::new(0.0, 0.0, 0.0));
+        let mut camera = Camera::new();
+
+        orbit.update(&DeviceManager::new(), &mut camera, 1.0 / 60.0);
+
+        let forward = camera.operations.get_forward_vector();
+        let expected = (orbit.get_target() - orbit.get_position()).normalized();
+        assert!((forward - expected).length() < 0.0001);
+        assert!((camera.operations.get_position() - orbit.get_position()).length() < 0.0001);
+    }
+
+    #[test]
+    fn yaw_pitch_lerp_takes_the_shortest_way_around() {
+        let from = YawPitch {
+            yaw: 350.0,
+            pitch: 0.0,
+        };
+        let to = YawPitch {
+            yaw: 10.0,
+            pitch: 20.0,
+        };
+
+        let halfway = from.lerp(to, 0.5);
+        assert_eq!(360.0, halfway.yaw);
+        assert_eq!(10.0, halfway.pitch);
+        assert_eq!(-10.0, to.lerp(from, 1.0).yaw);
+    }
+
+    #[test]
+    fn yaw_pitch_round_trip_through_forward() {
+        let look = YawPitch {
+            yaw: 30.0,
+            pitch: 20.0,
+        };
+        let back = YawPitch::from_forward(look.rotation().get_forward_vector());
+
+        assert!((back.yaw - 30.0).abs() < 0.001);
+        assert!((back.pitch - 20.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn positive_pitch_looks_down() {
+        let look = YawPitch {
+            yaw: 0.0,
+            pitch: 30.0,
+        };
+
+        assert!(look.rotation().get_forward_vector().y < 0.0);
+        assert_eq!(look.flat_forward().y, 0.0);
+    }
+
+    #[test]
+    fn pitch_is_clamped_by_the_settings() {
+        let mut camera =
+            FlyCamera::new(Vector3::new(0.0, 0.0, 0.0), 0.0, 0.0).with_settings(MovementSettings {
+                min_pitch: -20.0,
+                max_pitch: 40.0,
+                ..MovementSettings::default()
+            });
+
+        camera.look_along(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, -1.0, 0.1));
+        assert_eq!(camera.get_pitch(), 40.0);
+
+        camera.look_along(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.1));
+        assert_eq!(camera.get_pitch(), -20.0);
+    }
+
+    #[test]
+    fn acceleration_reaches_wish_velocity_over_time() {
+        let wish = Vector3::new(8.0, 0.0, 0.0);
+        let mut velocity = Vector3::new(0.0, 0.0, 0.0);
+
+        velocity = accelerate(velocity, wish, 40.0, 0.1);
+        assert_eq!(velocity, Vector3::new(4.0, 0.0, 0.0));
+
+        velocity = accelerate(velocity, wish, 40.0, 0.1);
+        assert_eq!(velocity, wish);
+
+        // stopping decelerates the same way
+        velocity = accelerate(velocity, Vector3::new(0.0, 0.0, 0.0), 40.0, 0.1);
+        assert_eq!(velocity, Vector3::new(4.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn fly_camera_writes_position_and_rotation() {
+        let mut fly = FlyCamera::new(Vector3::new(1.0, 2.0, 3.0), 90.0, 10.0);
+        let mut camera = Camera::new();
+
+        fly.update(&DeviceManager::new(), &mut camera, 1.0 / 60.0);
+
+        assert_eq!(
+            camera.operations.get_position(),
+            Vector3::new(1.0, 2.0, 3.0)
+        );
+        let forward = camera.operations.get_forward_vector();
+        assert!(forward.x > 0.9);
+        assert!(forward.y < 0.0);
+    }
+
+    #[test]
+    fn fps_camera_stands_on_the_character() {
+        let physics = PhysicsWorld::new();
+        let mut character =
+            CharacterController::new(Vector3::new(0.0, 5.0, 0.0), 0.4, 1.8).with_gravity(0.0);
+        let mut fps = FpsCamera::new(Vector3::new(0.0, 0.0, 0.0), 0.0).with_eye_height(1.7);
+        let mut camera = Camera::new();
+
+        fps.update_character(
+            &DeviceManager::new(),
+            &mut character,
+            &physics,
+            &mut camera,
+            1.0 / 60.0,
+        );
+
+        assert_eq!(fps.get_position(), Vector3::new(0.0, 6.7, 0.0));
+        assert_eq!(camera.operations.get_position(), fps.get_position());
+    }
+
+    fn follow_target(position: Vector3, yaw: f32) -> Matrix4 {
+        MatrixOperation::translation(position) * Quaternion::rotate_y(yaw)
+    }
+
+    #[test]
+    fn follow_camera_stays_behind_the_target() {
+        let mut follow = FollowCamera::new(10.0, 1.0);
+        let mut camera = Camera::new();
+
+        follow.update(
+            follow_target(Vector3::new(0.0, 0.0, 0.0), 0.0),
+            &PhysicsWorld::new(),
+            &mut camera,
+            1.0 / 60.0,
+        );
+
+        assert_eq!(
+            camera.operations.get_position(),
+            Vector3::new(0.0, 1.0, -10.0)
+        );
+        assert_eq!(follow.get_focus(), Vector3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn follow_camera_turns_the_short_way() {
+        let look = YawPitch {
+            yaw: 170.0,
+            pitch: 0.0,
+        }
+        .lerp(
+            YawPitch {
+                yaw: -170.0,
+                pitch: 0.0,
+            },
+            0.5,
+        );
+
+        assert!((look.yaw - 180.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn follow_camera_lags_and_looks_ahead() {
+        let physics = PhysicsWorld::new();
+        let mut follow = FollowCamera::new(10.0, 0.0);
+        let mut camera = Camera::new();
+        let dt = 1.0 / 60.0;
+
+        follow.update(
+            follow_target(Vector3::new(0.0, 0.0, 0.0), 0.0),
+            &physics,
+            &mut camera,
+            dt,
+        );
+        follow.update(
+            follow_target(Vector3::new(0.0, 0.0, 0.1), 0.0),
+            &physics,
+            &mut camera,
+            dt,
+        );
+
+        // the goal is 0.1 plus 1.2 of look ahead, damping keeps the focus well short of it
+        assert!(follow.get_focus().z < 0.5);
+
+        for i in 2..120 {
+            let target = Vector3::new(0.0, 0.0, i as f32 * 0.1);
+            follow.update(follow_target(target, 0.0), &physics, &mut camera, dt);
+        }
+
+        // moving at 6 units per second with 0.2 seconds of look ahead
+        assert!(follow.get_focus().z > 11.9);
+    }
+
+    #[test]
+    fn follow_camera_pulls_in_when_blocked() {
+        let mut physics = PhysicsWorld::new();
+        physics.add_body(
+            String::from("wall"),
+            RigidBody::fixed(Collider::Box {
+                half_extents: Vector3::new(5.0, 5.0, 0.5),
+            })
+            .with_position(Vector3::new(0.0, 0.0, -4.0)),
+        );
+        let mut follow = FollowCamera::new(10.0, 0.0);
+        let mut camera = Camera::new();
+
+        follow.update(
+            follow_target(Vector3::new(0.0, 0.0, 0.0), 0.0),
+            &physics,
+            &mut camera,
+            1.0 / 60.0,
+        );
+
+        // the wall front face is at 3.5, minus the margin
+        assert!((follow.get_distance() - 3.2).abs() < 0.001);
+        assert!(camera.operations.get_position().z > -3.5);
+
+        physics.remove_body("wall");
+        for _ in 0..120 {
+            follow.update(
+                follow_target(Vector3::new(0.0, 0.0, 0.0), 0.0),
+                &physics,
+                &mut camera,
+                1.0 / 60.0,
+            );
+        }
+
+        assert!(follow.get_distance() > 9.9);
+    }
+}
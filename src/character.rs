@@ -0,0 +1,470 @@
+use crate::math::{degree_to_radians, Ray, Vector3};
+use crate::physics::{PhysicsWorld, RayHit};
+
+/// movement is split so no sub step goes further than this fraction of the radius,
+/// thin colliders can not be skipped over in a single frame
+const SUB_STEP_FRACTION: f32 = 0.5;
+const DEPENETRATION_ITERATIONS: usize = 4;
+/// below this the normal is a ceiling, hitting it stops the jump
+const CEILING_NORMAL_Y: f32 = -0.1;
+/// ground this close under the feet while falling counts as landed
+const GROUND_TOLERANCE: f32 = 0.001;
+
+/// Kinematic capsule moved through the colliders of a `PhysicsWorld`, it pushes nothing and nothing pushes it.
+/// The capsule floats `step_height` above the feet, so steps pass under it, and a ray from its bottom
+/// finds the ground the feet stand on. Surfaces steeper than `max_slope` are not ground,
+/// the capsule is pushed out of them horizontally only, so they work as walls.
+/// reference: https://docs.unity3d.com/Manual/class-CharacterController.html
+pub struct CharacterController {
+    pub radius: f32,
+    /// from the feet to the top of the head, the capsule needs `step_height` plus twice the radius
+    pub height: f32,
+    pub step_height: f32,
+    /// degrees from the floor, steeper surfaces are walls
+    pub max_slope: f32,
+    pub jump_speed: f32,
+    pub gravity: f32,
+    /// bottom of the capsule
+    position: Vector3,
+    vertical_velocity: f32,
+    grounded: bool,
+    ground_normal: Option<Vector3>,
+}
+
+#[allow(dead_code)]
+impl CharacterController {
+    pub fn new(position: Vector3, radius: f32, height: f32) -> Self {
+        CharacterController {
+            radius,
+            height,
+            step_height: 0.3,
+            max_slope: 45.0,
+            jump_speed: 5.0,
+            gravity: 9.81,
+            position,
+            vertical_velocity: 0.0,
+            grounded: false,
+            ground_normal: None,
+        }
+    }
+
+    pub fn with_step_height(mut self, step_height: f32) -> Self {
+        self.step_height = step_height;
+        self
+    }
+
+    pub fn with_max_slope(mut self, max_slope: f32) -> Self {
+        self.max_slope = max_slope;
+        self
+    }
+
+    pub fn with_jump_speed(mut self, jump_speed: f32) -> Self {
+        self.jump_speed = jump_speed;
+        self
+    }
+
+    pub fn with_gravity(mut self, gravity: f32) -> Self {
+        self.gravity = gravity;
+        self
+    }
+
+    /// feet position
+    pub fn position(&self) -> Vector3 {
+        self.position
+    }
+
+    /// middle of the capsule, where an instance of the same height is placed
+    pub fn center(&self) -> Vector3 {
+        self.position + Vector3::new(0.0, self.height / 2.0, 0.0)
+    }
+
+    /// teleports without checking collisions
+    pub fn set_position(&mut self, position: Vector3) {
+        self.position = position;
+        self.vertical_velocity = 0.0;
+    }
+
+    pub fn is_grounded(&self) -> bool {
+        self.grounded
+    }
+
+    pub fn ground_normal(&self) -> Option<Vector3> {
+        self.ground_normal
+    }
+
+    pub fn vertical_velocity(&self) -> f32 {
+        self.vertical_velocity
+    }
+
+    /// `movement` is the wanted horizontal velocity in units per second, its vertical part is ignored.
+    /// Jumping only starts from the ground.
+    pub fn update(&mut self, physics: &PhysicsWorld, movement: Vector3, jump: bool, dt: f32) {
+        if jump && self.grounded {
+            self.vertical_velocity = self.jump_speed;
+            self.grounded = false;
+        }
+
+        if !self.grounded {
+            self.vertical_velocity -= self.gravity * dt;
+        }
+
+        let horizontal = Vector3::new(movement.x, 0.0, movement.z) * dt;
+        self.position = self.slide(physics, self.position, horizontal).0;
+
+        if !self.grounded {
+            let (position, normals) = self.slide(
+                physics,
+                self.position,
+                Vector3::new(0.0, self.vertical_velocity * dt, 0.0),
+            );
+            self.position = position;
+
+            if self.vertical_velocity > 0.0
+                && normals.iter().any(|normal| normal.y < CEILING_NORMAL_Y)
+            {
+                self.vertical_velocity = 0.0;
+            }
+        }
+
+        // on the ground the feet follow it up and down by up to `step_height`, in the air they land on it
+        let reach = if self.grounded { self.step_height } else { 0.0 };
+
+        match self.find_ground(physics, reach) {
+            Some(ground) if self.vertical_velocity <= 0.0 => {
+                self.position.y = ground.point.y;
+                self.vertical_velocity = 0.0;
+                self.grounded = true;
+                self.ground_normal = Some(ground.normal);
+            }
+            _ => {
+                self.grounded = false;
+                self.ground_normal = None;
+            }
+        }
+    }
+
+    /// walkable surface under the center of the capsule, at most `reach` below the feet
+    fn find_ground(&self, physics: &PhysicsWorld, reach: f32) -> Option<RayHit> {
+        let (origin, _) = self.segment(self.position);
+        let feet_distance = origin.y - self.position.y;
+
+        physics
+            .ray_cast(
+                &Ray::new(origin, Vector3::new(0.0, -1.0, 0.0)),
+                feet_distance + reach + GROUND_TOLERANCE,
+            )
+            .map(|(_, hit)| hit)
+            .filter(|hit| self.is_walkable(hit.normal))
+    }
+
+    /// moves in sub steps, pushing the capsule out of whatever it overlaps after each one.
+    /// Returns where it ended and the normals it touched on the way
+    fn slide(
+        &self,
+        physics: &PhysicsWorld,
+        start: Vector3,
+        delta: Vector3,
+    ) -> (Vector3, Vec<Vector3>) {
+        let steps = (delta.length() / (self.radius * SUB_STEP_FRACTION))
+            .ceil()
+            .max(1.0);
+        let step = delta * (1.0 / steps);
+
+        let mut position = start;
+        let mut normals = Vec::new();
+
+        for _ in 0..steps as usize {
+            position = self.depenetrate(physics, position + step, &mut normals);
+        }
+
+        (position, normals)
+    }
+
+    fn depenetrate(
+        &self,
+        physics: &PhysicsWorld,
+        position: Vector3,
+        normals: &mut Vec<Vector3>,
+    ) -> Vector3 {
+        let mut position = position;
+
+        for _ in 0..DEPENETRATION_ITERATIONS {
+            let (bottom, top) = self.segment(position);
+            let contacts = physics.capsule_contacts(bottom, top, self.radius);
+            if contacts.is_empty() {
+                break;
+            }
+
+            for contact in contacts {
+                position = position + self.push_out(contact.normal, contact.depth);
+                normals.push(contact.normal);
+            }
+        }
+
+        position
+    }
+
+    fn push_out(&self, normal: Vector3, depth: f32) -> Vector3 {
+        if self.is_walkable(normal) {
+            return Vector3::new(0.0, depth / normal.y, 0.0);
+        }
+
+        let flat = Vector3::new(normal.x, 0.0, normal.z);
+        if normal.y < CEILING_NORMAL_Y || flat.length() < 0.0001 {
+            return normal * depth;
+        }
+
+        let flat = flat.normalized();
+        flat * (depth / flat.dot(normal))
+    }
+
+    fn is_walkable(&self, normal: Vector3) -> bool {
+        normal.y >= degree_to_radians(self.max_slope).cos()
+    }
+
+    /// centers of the bottom and top spheres, the capsule floats `step_height` above the feet
+    fn segment(&self, position: Vector3) -> (Vector3, Vector3) {
+        let bottom = self.step_height + self.radius;
+        let top = (self.height - self.radius).max(bottom);
+
+        (
+            position + Vector3::new(0.0, bottom, 0.0),
+            position + Vector3::new(0.0, top, 0.0),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Quaternion;
+    use crate::matrices::MatrixOperation;
+    use crate::physics::{Collider, RigidBody};
+
+    const DT: f32 = 1.0 / 60.0;
+
+    fn block(name: &str, center: Vector3, half_extents: Vector3, world: &mut PhysicsWorld) {
+        world.add_body(
+            String::from(name),
+            RigidBody::fixed(Collider::Box { half_extents }).with_position(center),
+        );
+    }
+
+    /// top face at y = 0
+    fn flat_world() -> PhysicsWorld {
+        let mut world = PhysicsWorld::new();
+        block(
+            "floor",
+            Vector3::new(0.0, -0.5, 0.0),
+            Vector3::new(50.0, 0.5, 50.0),
+            &mut world,
+        );
+        world
+    }
+
+    fn character(position: Vector3) -> CharacterController {
+        CharacterController::new(position, 0.4, 1.8)
+    }
+
+    fn walk(
+        controller: &mut CharacterController,
+        world: &PhysicsWorld,
+        movement: Vector3,
+        seconds: f32,
+    ) {
+        for _ in 0..(seconds / DT).round() as usize {
+            controller.update(world, movement, false, DT);
+        }
+    }
+
+    fn still() -> Vector3 {
+        Vector3::new(0.0, 0.0, 0.0)
+    }
+
+    #[test]
+    fn falls_and_lands_on_the_ground() {
+        let world = flat_world();
+        let mut controller = character(Vector3::new(0.0, 3.0, 0.0));
+
+        controller.update(&world, still(), false, DT);
+        assert!(!controller.is_grounded());
+
+        walk(&mut controller, &world, still(), 2.0);
+
+        assert!(controller.is_grounded());
+        assert!(controller.position().y.abs() < 0.01);
+        assert_eq!(controller.vertical_velocity(), 0.0);
+        assert_eq!(
+            controller.ground_normal(),
+            Some(Vector3::new(0.0, 1.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn walks_on_flat_ground() {
+        let world = flat_world();
+        let mut controller = character(Vector3::new(0.0, 0.0, 0.0));
+
+        walk(&mut controller, &world, Vector3::new(3.0, 0.0, 0.0), 1.0);
+
+        assert!((controller.position().x - 3.0).abs() < 0.01);
+        assert!(controller.position().y.abs() < 0.01);
+        assert!(controller.is_grounded());
+    }
+
+    #[test]
+    fn stops_at_walls_and_slides_along_them() {
+        let mut world = flat_world();
+        block(
+            "wall",
+            Vector3::new(2.5, 1.5, 0.0),
+            Vector3::new(0.5, 1.5, 10.0),
+            &mut world,
+        );
+        let mut controller = character(Vector3::new(0.0, 0.0, 0.0));
+
+        walk(&mut controller, &world, Vector3::new(3.0, 0.0, 3.0), 1.0);
+
+        // the wall starts at x = 2, the capsule keeps its radius away
+        assert!((controller.position().x - 1.6).abs() < 0.01);
+        assert!((controller.position().z - 3.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn climbs_steps_up_to_the_step_height() {
+        let mut world = flat_world();
+        block(
+            "low step",
+            Vector3::new(3.0, 0.125, 0.0),
+            Vector3::new(1.0, 0.125, 1.0),
+            &mut world,
+        );
+        block(
+            "high step",
+            Vector3::new(3.0, 0.25, 5.0),
+            Vector3::new(1.0, 0.25, 1.0),
+            &mut world,
+        );
+
+        let mut low = character(Vector3::new(0.0, 0.0, 0.0));
+        walk(&mut low, &world, Vector3::new(3.0, 0.0, 0.0), 1.0);
+        assert!((low.position().y - 0.25).abs() < 0.01);
+        assert!((low.position().x - 3.0).abs() < 0.01);
+
+        let mut high = character(Vector3::new(0.0, 0.0, 5.0));
+        walk(&mut high, &world, Vector3::new(3.0, 0.0, 0.0), 1.0);
+        assert!(high.position().y.abs() < 0.01);
+        assert!(high.position().x < 2.0);
+    }
+
+    #[test]
+    fn refuses_slopes_steeper_than_the_limit() {
+        // the top face of the ramp starts on the floor at x = 2 and rises towards +x
+        let ramp = |angle: f32| {
+            let (sin, cos) = degree_to_radians(angle).sin_cos();
+            let center = Vector3::new(2.0 + 0.5 * sin + 5.0 * cos, 5.0 * sin - 0.5 * cos, 0.0);
+
+            let mut world = flat_world();
+            world.add_body(
+                String::from("ramp"),
+                RigidBody::fixed(Collider::Box {
+                    half_extents: Vector3::new(5.0, 0.5, 2.0),
+                })
+                .with_transform(MatrixOperation::translation(center) * Quaternion::rotate_z(angle)),
+            );
+
+            let mut controller = character(Vector3::new(0.0, 0.0, 0.0));
+            walk(&mut controller, &world, Vector3::new(3.0, 0.0, 0.0), 2.0);
+            controller.position()
+        };
+
+        let gentle = ramp(20.0);
+        assert!(gentle.x > 5.0);
+        assert!(gentle.y > 1.0);
+
+        let steep = ramp(60.0);
+        assert!(steep.x < 3.0);
+        assert!(steep.y < 0.5);
+    }
+
+    #[test]
+    fn jumps_only_from_the_ground() {
+        let world = flat_world();
+        let mut controller = character(Vector3::new(0.0, 0.0, 0.0)).with_jump_speed(5.0);
+        controller.update(&world, still(), false, DT);
+
+        controller.update(&world, still(), true, DT);
+        assert!(!controller.is_grounded());
+        assert!(controller.position().y > 0.0);
+
+        // no double jump while in the air
+        let velocity = controller.vertical_velocity();
+        controller.update(&world, still(), true, DT);
+        assert!(controller.vertical_velocity() < velocity);
+
+        walk(&mut controller, &world, still(), 2.0);
+        assert!(controller.is_grounded());
+        assert!(controller.position().y.abs() < 0.01);
+    }
+
+    #[test]
+    fn ceilings_stop_the_jump() {
+        let mut world = flat_world();
+        block(
+            "ceiling",
+            Vector3::new(0.0, 2.5, 0.0),
+            Vector3::new(5.0, 0.5, 5.0),
+            &mut world,
+        );
+        let mut controller = character(Vector3::new(0.0, 0.0, 0.0)).with_jump_speed(10.0);
+        controller.update(&world, still(), false, DT);
+
+        let mut highest: f32 = 0.0;
+        controller.update(&world, still(), true, DT);
+        for _ in 0..60 {
+            controller.update(&world, still(), false, DT);
+            highest = highest.max(controller.position().y);
+        }
+
+        assert!(highest <= 2.0 - 1.8 + 0.01);
+        assert!(controller.is_grounded());
+    }
+
+    #[test]
+    fn snaps_down_small_drops_and_falls_from_ledges() {
+        let mut world = PhysicsWorld::new();
+        block(
+            "upper",
+            Vector3::new(0.0, -0.5, 0.0),
+            Vector3::new(2.0, 0.5, 2.0),
+            &mut world,
+        );
+        block(
+            "step down",
+            Vector3::new(4.0, -0.7, 0.0),
+            Vector3::new(2.0, 0.5, 2.0),
+            &mut world,
+        );
+        block(
+            "pit",
+            Vector3::new(0.0, -10.5, 0.0),
+            Vector3::new(50.0, 0.5, 50.0),
+            &mut world,
+        );
+
+        let mut controller = character(Vector3::new(1.0, 0.0, 0.0));
+        controller.update(&world, still(), false, DT);
+
+        // walking down a 0.2 step keeps the feet on the ground every frame
+        for _ in 0..30 {
+            controller.update(&world, Vector3::new(3.0, 0.0, 0.0), false, DT);
+            assert!(controller.is_grounded());
+        }
+        assert!((controller.position().y + 0.2).abs() < 0.01);
+
+        // the lower block ends at x = 6, past it there is only the pit
+        walk(&mut controller, &world, Vector3::new(3.0, 0.0, 0.0), 1.5);
+        assert!(!controller.is_grounded());
+        assert!(controller.position().y < -0.5);
+    }
+}
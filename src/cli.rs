@@ -0,0 +1,284 @@
+use std::fmt;
+
+/// names accepted by `--camera`
+pub const CAMERA_CONTROLLERS: [&str; 4] = ["follow", "orbit", "fly", "fps"];
+/// how many frames `--benchmark` draws when `--frames` is not given
+pub const BENCHMARK_FRAMES: u32 = 600;
+
+pub const USAGE: &str = "usage: opengl-rust [options]
+
+options:
+    --scene <file>            adds a .ron, .json, .gltf or .glb scene, replacing what has the same name
+    --save-scene <file>       saves the scene as .ron or .json once it is built
+    --export <file>           writes every instance as one .obj, .stl or .ply mesh
+    --config <file>           window and context options (default world.toml)
+    --width <pixels>          window width
+    --height <pixels>         window height
+    --fullscreen              exclusive fullscreen
+    --borderless              borderless window covering the monitor
+    --camera <controller>     starting camera: follow, orbit, fly or fps
+    --headless                hidden window, draws as fast as it can
+    --frames <n>              exits after drawing n frames
+    --screenshot <file.png>   saves the last frame before exiting
+    --screenshot-every <n>    also saves every n frames, numbered after the file name
+    --benchmark               draws unthrottled, prints frame statistics and exits
+    --help                    shows this message";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CliOptions {
+    pub scene: Option<String>,
+    pub save_scene: Option<String>,
+    pub export: Option<String>,
+    pub config: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fullscreen: bool,
+    pub borderless: bool,
+    pub camera: Option<String>,
+    pub headless: bool,
+    pub frames: Option<u32>,
+    pub screenshot: Option<String>,
+    pub screenshot_every: Option<u32>,
+    pub benchmark: bool,
+    pub help: bool,
+}
+
+impl Default for CliOptions {
+    fn default() -> Self {
+        CliOptions {
+            scene: None,
+            save_scene: None,
+            export: None,
+            config: String::from("world.toml"),
+            width: None,
+            height: None,
+            fullscreen: false,
+            borderless: false,
+            camera: None,
+            headless: false,
+            frames: None,
+            screenshot: None,
+            screenshot_every: None,
+            benchmark: false,
+            help: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CliError {
+    UnknownOption(String),
+    MissingValue(String),
+    InvalidValue { option: String, value: String },
+    Conflict(&'static str, &'static str),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CliError::UnknownOption(option) => write!(f, "unknown option {}", option),
+            CliError::MissingValue(option) => write!(f, "{} needs a value", option),
+            CliError::InvalidValue { option, value } => {
+                write!(f, "invalid value {} for {}", value, option)
+            }
+            CliError::Conflict(first, second) => {
+                write!(f, "{} and {} can not be used together", first, second)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+#[allow(dead_code)]
+impl CliOptions {
+    /// `args` without the program name, as in `std::env::args().skip(1)`
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, CliError> {
+        let mut options = CliOptions::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--scene" => options.scene = Some(value(&arg, &mut args)?),
+                "--save-scene" => options.save_scene = Some(value(&arg, &mut args)?),
+                "--export" => options.export = Some(value(&arg, &mut args)?),
+                "--config" => options.config = value(&arg, &mut args)?,
+                "--width" => options.width = Some(positive(&arg, &mut args)?),
+                "--height" => options.height = Some(positive(&arg, &mut args)?),
+                "--fullscreen" => options.fullscreen = true,
+                "--borderless" => options.borderless = true,
+                "--camera" => {
+                    let camera = value(&arg, &mut args)?;
+                    if !CAMERA_CONTROLLERS.contains(&camera.as_str()) {
+                        return Err(CliError::InvalidValue {
+                            option: arg,
+                            value: camera,
+                        });
+                    }
+                    options.camera = Some(camera);
+                }
+                "--headless" => options.headless = true,
+                "--frames" => options.frames = Some(positive(&arg, &mut args)?),
+                "--screenshot" => options.screenshot = Some(value(&arg, &mut args)?),
+                "--screenshot-every" => options.screenshot_every = Some(positive(&arg, &mut args)?),
+                "--benchmark" => options.benchmark = true,
+                "--help" | "-h" => options.help = true,
+                _ => return Err(CliError::UnknownOption(arg)),
+            }
+        }
+
+        if options.fullscreen && options.borderless {
+            return Err(CliError::Conflict("--fullscreen", "--borderless"));
+        }
+        if options.screenshot_every.is_some() && options.screenshot.is_none() {
+            return Err(CliError::MissingValue(String::from("--screenshot")));
+        }
+
+        Ok(options)
+    }
+
+    /// benchmarks always stop, headless runs stop only when asked
+    pub fn frame_limit(&self) -> Option<u32> {
+        match (self.frames, self.benchmark) {
+            (None, true) => Some(BENCHMARK_FRAMES),
+            (frames, _) => frames,
+        }
+    }
+
+    /// waiting between frames only matters when someone is watching
+    pub fn unthrottled(&self) -> bool {
+        self.benchmark || self.headless
+    }
+
+    /// where frame `frame` (counting from 1) is saved, if it is saved at all
+    pub fn screenshot_path(&self, frame: u32, last: bool) -> Option<String> {
+        let path = self.screenshot.as_ref()?;
+
+        match self.screenshot_every {
+            Some(every) if frame.is_multiple_of(every) => Some(numbered_path(path, frame)),
+            Some(_) if last => Some(numbered_path(path, frame)),
+            None if last => Some(path.clone()),
+            _ => None,
+        }
+    }
+}
+
+fn value<I: Iterator<Item = String>>(option: &str, args: &mut I) -> Result<String, CliError> {
+    args.next()
+        .ok_or_else(|| CliError::MissingValue(String::from(option)))
+}
+
+fn positive<I: Iterator<Item = String>>(option: &str, args: &mut I) -> Result<u32, CliError> {
+    let value = value(option, args)?;
+
+    match value.parse::<u32>() {
+        Ok(number) if number > 0 => Ok(number),
+        _ => Err(CliError::InvalidValue {
+            option: String::from(option),
+            value,
+        }),
+    }
+}
+
+/// shot.png and 42 becomes shot-0042.png
+fn numbered_path(path: &str, frame: u32) -> String {
+    match path.rfind('.') {
+        Some(dot) if !path[dot..].contains('/') => {
+            format!("{}-{:04}{}", &path[..dot], frame, &path[dot..])
+        }
+        _ => format!("{}-{:04}", path, frame),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<CliOptions, CliError> {
+        CliOptions::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn no_arguments_use_defaults() {
+        assert_eq!(parse(&[]).unwrap(), CliOptions::default());
+    }
+
+    #[test]
+    fn options_with_values() {
+        let options = parse(&[
+            "--scene",
+            "level.ron",
+            "--export",
+            "level.stl",
+            "--width",
+            "1280",
+            "--height",
+            "720",
+            "--camera",
+            "fly",
+            "--fullscreen",
+        ])
+        .unwrap();
+
+        assert_eq!(options.scene.as_deref(), Some("level.ron"));
+        assert_eq!(options.export.as_deref(), Some("level.stl"));
+        assert_eq!(options.width, Some(1280));
+        assert_eq!(options.height, Some(720));
+        assert_eq!(options.camera.as_deref(), Some("fly"));
+        assert!(options.fullscreen);
+    }
+
+    #[test]
+    fn invalid_arguments_are_errors() {
+        assert_eq!(
+            parse(&["--wide"]),
+            Err(CliError::UnknownOption(String::from("--wide")))
+        );
+        assert_eq!(
+            parse(&["--frames"]),
+            Err(CliError::MissingValue(String::from("--frames")))
+        );
+        assert!(parse(&["--width", "0"]).is_err());
+        assert!(parse(&["--camera", "drone"]).is_err());
+        assert!(parse(&["--fullscreen", "--borderless"]).is_err());
+        assert!(parse(&["--screenshot-every", "10"]).is_err());
+    }
+
+    #[test]
+    fn benchmark_has_a_frame_limit() {
+        assert_eq!(parse(&["--headless"]).unwrap().frame_limit(), None);
+        assert_eq!(
+            parse(&["--benchmark"]).unwrap().frame_limit(),
+            Some(BENCHMARK_FRAMES)
+        );
+        assert_eq!(
+            parse(&["--benchmark", "--frames", "10"])
+                .unwrap()
+                .frame_limit(),
+            Some(10)
+        );
+    }
+
+    #[test]
+    fn screenshot_paths() {
+        let last_only = parse(&["--screenshot", "shot.png"]).unwrap();
+        assert_eq!(last_only.screenshot_path(5, false), None);
+        assert_eq!(
+            last_only.screenshot_path(5, true).as_deref(),
+            Some("shot.png")
+        );
+
+        let every = parse(&["--screenshot", "out/shot.png", "--screenshot-every", "10"]).unwrap();
+        assert_eq!(every.screenshot_path(5, false), None);
+        assert_eq!(
+            every.screenshot_path(20, false).as_deref(),
+            Some("out/shot-0020.png")
+        );
+        assert_eq!(
+            every.screenshot_path(25, true).as_deref(),
+            Some("out/shot-0025.png")
+        );
+
+        assert_eq!(numbered_path("./shots/frame", 3), "./shots/frame-0003");
+    }
+}
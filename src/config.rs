@@ -0,0 +1,201 @@
+use glium::glutin;
+use glium::glutin::event_loop::EventLoopWindowTarget;
+use glium::glutin::monitor::MonitorHandle;
+use glium::glutin::window::{Fullscreen, WindowBuilder};
+use glium::glutin::{Api, ContextBuilder, GlProfile, GlRequest, NotCurrent};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{read_to_string, write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowMode {
+    Windowed,
+    /// a window without borders covering the monitor, switches instantly
+    Borderless,
+    /// takes over the monitor with the video mode closest to the window size
+    Fullscreen,
+}
+
+/// How the window and the OpenGL context are created. Every field has a default,
+/// so a config file only needs the ones it changes:
+///
+/// ```toml
+/// title = "cubes"
+/// width = 1280
+/// height = 720
+/// msaa_samples = 4
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorldConfig {
+    pub title: String,
+    /// logical pixels, ignored while fullscreen
+    pub width: u32,
+    pub height: u32,
+    pub window_mode: WindowMode,
+    pub resizable: bool,
+    /// a hidden window still draws, for screenshots and benchmarks
+    pub visible: bool,
+    pub vsync: bool,
+    /// 0 disables multisampling, usual values are 2, 4 and 8.
+    /// only the window is multisampled, with post processing the fxaa pass does the anti aliasing
+    pub msaa_samples: u16,
+    pub depth_bits: u8,
+    pub srgb: bool,
+    /// major and minor OpenGL version, the latest available when `None`. shaders need at least 3.3
+    pub gl_version: Option<(u8, u8)>,
+}
+
+impl Default for WorldConfig {
+    fn default() -> Self {
+        WorldConfig {
+            title: String::from("Hello OpenGL - focus on game math"),
+            width: 600,
+            height: 600,
+            window_mode: WindowMode::Windowed,
+            resizable: true,
+            visible: true,
+            vsync: false,
+            msaa_samples: 0,
+            depth_bits: 24,
+            srgb: false,
+            gl_version: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(error) => write!(f, "could not access the config file: {}", error),
+            ConfigError::Parse(error) => write!(f, "invalid config file: {}", error),
+            ConfigError::Serialize(error) => write!(f, "could not write the config: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[allow(dead_code)]
+impl WorldConfig {
+    pub fn from_file(path: &str) -> Result<Self, ConfigError> {
+        let content = read_to_string(path).map_err(ConfigError::Io)?;
+        WorldConfig::from_toml(&content)
+    }
+
+    pub fn from_toml(content: &str) -> Result<Self, ConfigError> {
+        toml::from_str(content).map_err(ConfigError::Parse)
+    }
+
+    pub fn to_toml(&self) -> Result<String, ConfigError> {
+        toml::to_string(self).map_err(ConfigError::Serialize)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), ConfigError> {
+        write(path, self.to_toml()?).map_err(ConfigError::Io)
+    }
+
+    pub fn window_builder<T>(&self, event_loop: &EventLoopWindowTarget<T>) -> WindowBuilder {
+        WindowBuilder::new()
+            .with_title(self.title.clone())
+            .with_inner_size(glutin::dpi::LogicalSize::new(
+                self.width as f64,
+                self.height as f64,
+            ))
+            .with_resizable(self.resizable)
+            .with_visible(self.visible)
+            .with_fullscreen(self.fullscreen(event_loop.primary_monitor()))
+    }
+
+    pub fn context_builder(&self) -> ContextBuilder<'static, NotCurrent> {
+        let context_builder = ContextBuilder::new()
+            .with_vsync(self.vsync)
+            .with_multisampling(self.msaa_samples)
+            .with_depth_buffer(self.depth_bits)
+            .with_srgb(self.srgb);
+
+        match self.gl_version {
+            Some(version) => context_builder
+                .with_gl(GlRequest::Specific(Api::OpenGl, version))
+                .with_gl_profile(GlProfile::Core),
+            None => context_builder,
+        }
+    }
+
+    /// what `window_mode` means on `monitor`
+    pub fn fullscreen(&self, monitor: MonitorHandle) -> Option<Fullscreen> {
+        match self.window_mode {
+            WindowMode::Windowed => None,
+            WindowMode::Borderless => Some(Fullscreen::Borderless(monitor)),
+            WindowMode::Fullscreen => {
+                let (width, height) = (self.width, self.height);
+
+                // the same size as the window if the monitor has it, the biggest one otherwise
+                monitor
+                    .video_modes()
+                    .max_by_key(|mode| {
+                        let size = mode.size();
+                        (
+                            size.width == width && size.height == height,
+                            size.width * size.height,
+                            mode.refresh_rate(),
+                        )
+                    })
+                    .map(Fullscreen::Exclusive)
+                    .or(Some(Fullscreen::Borderless(monitor)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_fields_use_defaults() {
+        let config = WorldConfig::from_toml("title = \"cubes\"\nmsaa_samples = 4\n").unwrap();
+
+        assert_eq!(config.title, "cubes");
+        assert_eq!(config.msaa_samples, 4);
+        assert_eq!(config.width, 600);
+        assert_eq!(config.window_mode, WindowMode::Windowed);
+    }
+
+    #[test]
+    fn window_mode_and_gl_version_parse() {
+        let config =
+            WorldConfig::from_toml("window_mode = \"borderless\"\ngl_version = [3, 3]\n").unwrap();
+
+        assert_eq!(config.window_mode, WindowMode::Borderless);
+        assert_eq!(config.gl_version, Some((3, 3)));
+    }
+
+    #[test]
+    fn config_round_trips_through_toml() {
+        let config = WorldConfig {
+            width: 1280,
+            height: 720,
+            vsync: true,
+            window_mode: WindowMode::Fullscreen,
+            ..WorldConfig::default()
+        };
+
+        let parsed = WorldConfig::from_toml(&config.to_toml().unwrap()).unwrap();
+        assert_eq!(parsed, config);
+    }
+
+    #[test]
+    fn invalid_config_is_an_error() {
+        assert!(WorldConfig::from_toml("width = \"wide\"").is_err());
+        assert!(WorldConfig::from_file("does/not/exist.toml").is_err());
+    }
+}
@@ -1,5 +1,8 @@
 use crate::math::{clamp, degree_to_radians, Vector3};
 
+const MIN_RADIUS: f32 = 1.0;
+const MAX_ELEVATION: f32 = 78.75;
+
 #[derive(Debug, Clone, Copy)]
 pub struct SphereVector {
     radius: f32,
@@ -10,15 +13,40 @@ pub struct SphereVector {
 /// reference: https://en.wikipedia.org/wiki/Spherical_coordinate_system
 /// remember that up is y, not z, so the elevation angle is against y, not z.
 /// clamp is necessary to avoid problems on matrix camera transform. Reference: https://paroj.github.io/gltut/Positioning/Tutorial%2007.html
+#[allow(dead_code)]
 impl SphereVector {
     pub fn new(radius: f32, elevation: f32, azimuthal: f32) -> SphereVector {
         SphereVector {
-            radius: radius.max(1.0),
+            radius: radius.max(MIN_RADIUS),
             azimuthal,
-            elevation: clamp(elevation, -78.75, 78.75),
+            elevation: clamp(elevation, -MAX_ELEVATION, MAX_ELEVATION),
         }
     }
 
+    pub fn radius(self) -> f32 {
+        self.radius
+    }
+
+    pub fn elevation(self) -> f32 {
+        self.elevation
+    }
+
+    pub fn azimuthal(self) -> f32 {
+        self.azimuthal
+    }
+
+    /// the azimuth takes the shortest way around, so 350 to 10 goes through 0 and not through 180
+    pub fn lerp(self, other: SphereVector, amount: f32) -> SphereVector {
+        let azimuthal_difference =
+            (other.azimuthal - self.azimuthal + 180.0).rem_euclid(360.0) - 180.0;
+
+        SphereVector::new(
+            self.radius + (other.radius - self.radius) * amount,
+            self.elevation + (other.elevation - self.elevation) * amount,
+            self.azimuthal + azimuthal_difference * amount,
+        )
+    }
+
     pub fn to_cartesian(self) -> Vector3 {
         let elevation = degree_to_radians(self.elevation + 90.0);
         let azimuthal = degree_to_radians(self.azimuthal);
@@ -61,6 +89,21 @@ mod tests {
         assert_eq!(10.0, not_clampeda.radius);
     }
 
+    #[test]
+    fn sphere_vector_lerp() {
+        let from = SphereVector::new(2.0, 10.0, 350.0);
+        let to = SphereVector::new(4.0, 30.0, 10.0);
+
+        let half = from.lerp(to, 0.5);
+        assert_eq!(3.0, half.radius());
+        assert_eq!(20.0, half.elevation());
+        assert_eq!(360.0, half.azimuthal());
+
+        let end = from.lerp(to, 1.0);
+        assert_eq!(4.0, end.radius());
+        assert_eq!(370.0, end.azimuthal());
+    }
+
     #[test]
     fn sphere_vector_to_cartesian() {
         assert_eq!(
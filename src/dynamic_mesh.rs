@@ -0,0 +1,369 @@
+use crate::models::{Prefab, PrefabBuffers};
+use crate::primitives::{check_attributes, MeshVertex, MissingAttributes, Vertex};
+use glium::backend::glutin::Display;
+use glium::index::{IndicesSource, NoIndices, PrimitiveType};
+use glium::vertex::VerticesSource;
+use glium::{IndexBuffer, VertexBuffer};
+use std::any::Any;
+use std::ops::Range;
+use std::rc::Rc;
+
+/// buffers never start smaller than this, so small meshes can grow a while before reallocating
+const MIN_CAPACITY: usize = 64;
+
+fn capacity_for(len: usize) -> usize {
+    len.next_power_of_two().max(MIN_CAPACITY)
+}
+
+/// the new capacity when `len` items do not fit in `capacity`
+fn grown_capacity(len: usize, capacity: usize) -> Option<usize> {
+    if len > capacity {
+        Some(capacity_for(len))
+    } else {
+        None
+    }
+}
+
+/// `count` items from `start`, panics when they go past `len`
+fn checked_range(start: usize, count: usize, len: usize) -> Range<usize> {
+    let end = start + count;
+    assert!(end <= len, "vertices {}..{} out of {}", start, end, len);
+    start..end
+}
+
+/// typed buffers with room to grow, only the first `vertex_count` vertices are drawn
+struct DynamicBuffers<V: MeshVertex> {
+    display: Display,
+    primitive_type: PrimitiveType,
+    vertex: VertexBuffer<V>,
+    vertex_count: usize,
+    index: Option<IndexBuffer<u32>>,
+    index_count: usize,
+    no_indices: NoIndices,
+}
+
+impl<V: MeshVertex> DynamicBuffers<V> {
+    /// orphans the old storage first, the gpu may still be drawing the last frame from it
+    fn write_vertices(&mut self, vertices: &[V]) {
+        if let Some(capacity) = grown_capacity(vertices.len(), self.vertex.len()) {
+            self.vertex = VertexBuffer::empty_dynamic(&self.display, capacity).unwrap();
+        } else {
+            self.vertex.invalidate();
+        }
+        if !vertices.is_empty() {
+            self.vertex
+                .slice(0..vertices.len())
+                .unwrap()
+                .write(vertices);
+        }
+        self.vertex_count = vertices.len();
+    }
+
+    /// without orphaning, the rest of the buffer is still in use
+    fn write_vertex_range(&mut self, start: usize, vertices: &[V]) {
+        self.vertex
+            .slice(start..start + vertices.len())
+            .unwrap()
+            .write(vertices);
+    }
+
+    fn write_indices(&mut self, indices: Option<&[u32]>) {
+        let indices = match indices {
+            Some(indices) => indices,
+            None => {
+                self.index = None;
+                self.index_count = 0;
+                return;
+            }
+        };
+
+        let capacity = self.index.as_ref().map_or(0, |index| index.len());
+        match (&self.index, grown_capacity(indices.len(), capacity)) {
+            (Some(index), None) => index.invalidate(),
+            _ => {
+                self.index = Some(
+                    IndexBuffer::empty_dynamic(
+                        &self.display,
+                        self.primitive_type,
+                        capacity_for(indices.len()),
+                    )
+                    .unwrap(),
+                )
+            }
+        }
+        if !indices.is_empty() {
+            let index = self.index.as_ref().unwrap();
+            index.slice(0..indices.len()).unwrap().write(indices);
+        }
+        self.index_count = indices.len();
+    }
+}
+
+impl<V: MeshVertex> PrefabBuffers for DynamicBuffers<V> {
+    fn vertices(&self) -> VerticesSource<'_> {
+        self.vertex.slice(0..self.vertex_count).unwrap().into()
+    }
+
+    fn indices(&self) -> IndicesSource<'_> {
+        match &self.index {
+            Some(index) => index.slice(0..self.index_count).unwrap().into(),
+            None => (&self.no_indices).into(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.vertex_count == 0 || (self.index.is_some() && self.index_count == 0)
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// A mesh whose vertices and indices can change every frame, for cloth, deformable
+/// terrain, trails and debug geometry. Rewrites orphan the buffers instead of mapping
+/// them persistently, which would need OpenGL 4.4. Buffers double when they run out of room.
+///
+/// `prefab` is drawn, culled and picked like any other prefab, and sees every change
+/// on the next frame. The vertices stay on the cpu for physics and picking.
+pub struct DynamicMesh<V: MeshVertex = Vertex> {
+    prefab: Rc<Prefab>,
+    vertices: Vec<V>,
+    indices: Option<Vec<u32>>,
+}
+
+#[allow(dead_code)]
+impl<V: MeshVertex> DynamicMesh<V> {
+    /// without indices the vertices are drawn in order.
+    /// Fails when the vertex type lacks one of the `SHADER_ATTRIBUTES`
+    pub fn new(
+        display: Display,
+        primitive_type: PrimitiveType,
+        vertices: Vec<V>,
+        indices: Option<Vec<u32>>,
+    ) -> Result<Self, MissingAttributes> {
+        check_attributes::<V>()?;
+        let mut buffers = DynamicBuffers {
+            vertex: VertexBuffer::empty_dynamic(&display, capacity_for(vertices.len())).unwrap(),
+            display,
+            primitive_type,
+            vertex_count: 0,
+            index: None,
+            index_count: 0,
+            no_indices: NoIndices(primitive_type),
+        };
+        buffers.write_vertices(&vertices);
+        buffers.write_indices(indices.as_deref());
+
+        let mesh = DynamicMesh {
+            prefab: Prefab::from_buffers(Box::new(buffers), primitive_type, Vec::new(), &[]),
+            vertices,
+            indices,
+        };
+        mesh.refresh_geometry();
+        Ok(mesh)
+    }
+
+    /// for `Instance::new` or `World::add_prefab`
+    pub fn prefab(&self) -> Rc<Prefab> {
+        self.prefab.clone()
+    }
+
+    pub fn vertices(&self) -> &[V] {
+        &self.vertices
+    }
+
+    pub fn indices(&self) -> Option<&[u32]> {
+        self.indices.as_deref()
+    }
+
+    pub fn len(&self) -> usize {
+        self.vertices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    /// vertices that fit before the buffer has to grow
+    pub fn capacity(&self) -> usize {
+        self.with_buffers(|buffers| buffers.vertex.len())
+    }
+
+    /// replaces every vertex, the usual per frame rewrite
+    pub fn set_vertices(&mut self, vertices: Vec<V>) {
+        self.with_buffers(|buffers| buffers.write_vertices(&vertices));
+        self.vertices = vertices;
+        self.refresh_geometry();
+    }
+
+    /// rewrites the vertices from `start` on, like the rows of terrain that changed.
+    /// Panics when the range goes past the last vertex
+    pub fn update_vertices(&mut self, start: usize, vertices: &[V]) {
+        let range = checked_range(start, vertices.len(), self.vertices.len());
+
+        self.with_buffers(|buffers| buffers.write_vertex_range(start, vertices));
+        self.vertices[range].copy_from_slice(vertices);
+        let converted: Vec<Vertex> = vertices.iter().map(MeshVertex::to_vertex).collect();
+        self.prefab.update_geometry(start, &converted);
+    }
+
+    /// adds vertices at the end, growing the buffer when it is full
+    pub fn push_vertices(&mut self, vertices: &[V]) {
+        let start = self.vertices.len();
+        self.vertices.extend_from_slice(vertices);
+
+        let all = &self.vertices;
+        self.with_buffers(|buffers| {
+            if grown_capacity(all.len(), buffers.vertex.len()).is_some() {
+                buffers.write_vertices(all);
+            } else if !vertices.is_empty() {
+                buffers.write_vertex_range(start, vertices);
+                buffers.vertex_count = all.len();
+            }
+        });
+        self.refresh_geometry();
+    }
+
+    /// indices must stay below the vertex count, triangles that do not are not picked
+    pub fn set_indices(&mut self, indices: Option<Vec<u32>>) {
+        self.with_buffers(|buffers| buffers.write_indices(indices.as_deref()));
+        self.indices = indices;
+        self.refresh_geometry();
+    }
+
+    pub fn clear(&mut self) {
+        self.set_vertices(Vec::new());
+        if self.indices.is_some() {
+            self.set_indices(Some(Vec::new()));
+        }
+    }
+
+    fn with_buffers<R, F: FnOnce(&mut DynamicBuffers<V>) -> R>(&self, action: F) -> R {
+        let mut buffers = self.prefab.buffers_mut();
+        // the prefab was built by `new` with these buffers
+        let buffers = buffers
+            .as_any_mut()
+            .downcast_mut::<DynamicBuffers<V>>()
+            .unwrap();
+        action(buffers)
+    }
+
+    fn refresh_geometry(&self) {
+        let vertices = self.vertices.iter().map(MeshVertex::to_vertex).collect();
+        let indices: Vec<u32> = match &self.indices {
+            Some(indices) => indices.clone(),
+            None => (0..self.vertices.len() as u32).collect(),
+        };
+        self.prefab.set_geometry(vertices, &indices);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Vector3;
+
+    /// stands in for the gpu side, tests only look at the cpu copies
+    struct NoBuffers;
+
+    impl PrefabBuffers for NoBuffers {
+        fn vertices(&self) -> VerticesSource<'_> {
+            unreachable!("empty buffers are never drawn")
+        }
+
+        fn indices(&self) -> IndicesSource<'_> {
+            unreachable!("empty buffers are never drawn")
+        }
+
+        fn is_empty(&self) -> bool {
+            true
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+    }
+
+    fn vertex(x: f32, y: f32, z: f32) -> Vertex {
+        Vertex {
+            position: [x, y, z],
+            color: [1.0, 1.0, 1.0, 1.0],
+            tex_coords: [0.0, 0.0],
+            normal: [0.0, 0.0, 1.0],
+        }
+    }
+
+    fn triangle_prefab() -> Rc<Prefab> {
+        Prefab::from_buffers(
+            Box::new(NoBuffers),
+            PrimitiveType::TrianglesList,
+            vec![
+                vertex(0.0, 0.0, 0.0),
+                vertex(1.0, 0.0, 0.0),
+                vertex(0.0, 1.0, 0.0),
+            ],
+            &[0, 1, 2],
+        )
+    }
+
+    #[test]
+    fn capacity_doubles_when_the_vertices_do_not_fit() {
+        let mut capacity = capacity_for(0);
+        let mut growths = Vec::new();
+
+        for len in 1..=300 {
+            if let Some(grown) = grown_capacity(len, capacity) {
+                growths.push((len, grown));
+                capacity = grown;
+            }
+        }
+
+        assert_eq!(MIN_CAPACITY, capacity_for(0));
+        assert_eq!(vec![(65, 128), (129, 256), (257, 512)], growths);
+        assert_eq!(Some(1024), grown_capacity(1000, 512));
+    }
+
+    #[test]
+    fn updated_ranges_stay_inside_the_vertices() {
+        assert_eq!(2..5, checked_range(2, 3, 5));
+        assert_eq!(5..5, checked_range(5, 0, 5));
+    }
+
+    #[test]
+    #[should_panic(expected = "vertices 3..6 out of 5")]
+    fn updates_past_the_last_vertex_panic() {
+        checked_range(3, 3, 5);
+    }
+
+    #[test]
+    fn update_geometry_moves_the_bounds() {
+        let prefab = triangle_prefab();
+
+        prefab.update_geometry(1, &[vertex(4.0, 0.0, 0.0), vertex(0.0, 2.0, -1.0)]);
+
+        assert_eq!(Vector3::new(4.0, 2.0, 0.0), prefab.get_bounds().max);
+        assert_eq!(Vector3::new(0.0, 0.0, -1.0), prefab.get_bounds().min);
+        assert_eq!([4.0, 0.0, 0.0], prefab.get_vertices()[1].position);
+    }
+
+    #[test]
+    #[should_panic(expected = "vertices 2..4 out of 3")]
+    fn update_geometry_past_the_last_vertex_panics() {
+        let prefab = triangle_prefab();
+
+        prefab.update_geometry(2, &[vertex(1.0, 1.0, 1.0), vertex(2.0, 2.0, 2.0)]);
+    }
+
+    #[test]
+    fn failed_updates_leave_the_geometry_alone() {
+        let prefab = triangle_prefab();
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            prefab.update_geometry(2, &[vertex(9.0, 9.0, 9.0), vertex(9.0, 9.0, 9.0)]);
+        }));
+
+        assert!(result.is_err());
+        assert_eq!([0.0, 1.0, 0.0], prefab.get_vertices()[2].position);
+    }
+}
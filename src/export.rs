@@ -0,0 +1,441 @@
+use crate::math::{clamp, Matrix4, Vector3};
+use crate::mesh::Mesh;
+use crate::models::{Instance, Prefab};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Obj,
+    StlAscii,
+    StlBinary,
+    Ply,
+}
+
+impl ExportFormat {
+    /// from the file extension, `.stl` is written binary, like most tools expect
+    pub fn from_path(path: &str) -> Result<Self, ExportError> {
+        match Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+        {
+            Some("obj") => Ok(ExportFormat::Obj),
+            Some("stl") => Ok(ExportFormat::StlBinary),
+            Some("ply") => Ok(ExportFormat::Ply),
+            _ => Err(ExportError::UnknownFormat(String::from(path))),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ExportError {
+    Io(io::Error),
+    UnknownFormat(String),
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExportError::Io(error) => write!(f, "could not write the mesh: {}", error),
+            ExportError::UnknownFormat(path) => {
+                write!(f, "{} is neither a .obj, .stl nor a .ply file", path)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+/// A named `Mesh` copied from a `Prefab`, optionally moved by a transform.
+#[derive(Debug, Clone)]
+pub struct ExportMesh {
+    pub name: String,
+    pub mesh: Mesh,
+}
+
+#[allow(dead_code)]
+impl ExportMesh {
+    /// in local space
+    pub fn from_prefab(name: &str, prefab: &Prefab) -> Self {
+        ExportMesh {
+            name: String::from(name),
+            mesh: Mesh::from_prefab(prefab),
+        }
+    }
+
+    /// in world space, with the instance transform baked into the vertices
+    pub fn from_instance(name: &str, instance: &Instance) -> Self {
+        ExportMesh::from_prefab(name, instance.get_prefab()).transformed(instance.get_transform())
+    }
+
+    /// see `Mesh::transformed`
+    pub fn transformed(self, matrix: Matrix4) -> Self {
+        ExportMesh {
+            name: self.name,
+            mesh: self.mesh.transformed(matrix),
+        }
+    }
+
+    /// one mesh with the vertices of all of them, in order
+    pub fn merge(name: &str, meshes: &[ExportMesh]) -> Self {
+        let mut merged = Mesh::default();
+        for export in meshes {
+            merged.append(&export.mesh);
+        }
+        ExportMesh {
+            name: String::from(name),
+            mesh: merged,
+        }
+    }
+
+    /// zero normals mean the mesh was built without them
+    pub fn has_normals(&self) -> bool {
+        self.mesh
+            .vertices
+            .iter()
+            .any(|vertex| vertex.normal != [0.0; 3])
+    }
+
+    /// all white vertices are left out of the file
+    pub fn has_colors(&self) -> bool {
+        self.mesh
+            .vertices
+            .iter()
+            .any(|vertex| vertex.color != [1.0, 1.0, 1.0, 1.0])
+    }
+
+    fn position(&self, index: u32) -> Vector3 {
+        let [x, y, z] = self.mesh.vertices[index as usize].position;
+        Vector3::new(x, y, z)
+    }
+
+    /// STL stores one unit normal per triangle
+    fn facet_normal(&self, triangle: [u32; 3]) -> Vector3 {
+        let normal = self.mesh.face_normal(triangle);
+        if normal.length() > 0.0 {
+            normal.normalized()
+        } else {
+            normal
+        }
+    }
+}
+
+/// Every mesh becomes an `o` object. Colors are written after the position, which is
+/// a common extension, and texture coordinates are always written.
+/// reference: http://paulbourke.net/dataformats/obj/
+pub fn write_obj<W: Write>(meshes: &[ExportMesh], writer: &mut W) -> io::Result<()> {
+    writeln!(writer, "# exported by opengl-rust")?;
+    // obj indices are shared by the whole file and start at 1
+    let mut offset = 1;
+
+    for mesh in meshes {
+        let normals = mesh.has_normals();
+        let colors = mesh.has_colors();
+
+        writeln!(writer, "o {}", mesh.name)?;
+        for vertex in &mesh.mesh.vertices {
+            let [x, y, z] = vertex.position;
+            if colors {
+                let [r, g, b, _] = vertex.color;
+                writeln!(writer, "v {} {} {} {} {} {}", x, y, z, r, g, b)?;
+            } else {
+                writeln!(writer, "v {} {} {}", x, y, z)?;
+            }
+        }
+        for vertex in &mesh.mesh.vertices {
+            let [u, v] = vertex.tex_coords;
+            writeln!(writer, "vt {} {}", u, v)?;
+        }
+        if normals {
+            for vertex in &mesh.mesh.vertices {
+                let [x, y, z] = vertex.normal;
+                writeln!(writer, "vn {} {} {}", x, y, z)?;
+            }
+        }
+
+        for triangle in mesh.mesh.triangles() {
+            write!(writer, "f")?;
+            for &index in &triangle {
+                let index = index + offset;
+                if normals {
+                    write!(writer, " {}/{}/{}", index, index, index)?;
+                } else {
+                    write!(writer, " {}/{}", index, index)?;
+                }
+            }
+            writeln!(writer)?;
+        }
+
+        offset += mesh.mesh.vertices.len() as u32;
+    }
+
+    Ok(())
+}
+
+/// reference: https://www.fabbers.com/tech/STL_Format
+pub fn write_stl_ascii<W: Write>(mesh: &ExportMesh, writer: &mut W) -> io::Result<()> {
+    writeln!(writer, "solid {}", mesh.name)?;
+
+    for triangle in mesh.mesh.triangles() {
+        let normal = mesh.facet_normal(triangle);
+        writeln!(
+            writer,
+            "  facet normal {} {} {}",
+            normal.x, normal.y, normal.z
+        )?;
+        writeln!(writer, "    outer loop")?;
+        for &index in &triangle {
+            let position = mesh.position(index);
+            writeln!(
+                writer,
+                "      vertex {} {} {}",
+                position.x, position.y, position.z
+            )?;
+        }
+        writeln!(writer, "    endloop")?;
+        writeln!(writer, "  endfacet")?;
+    }
+
+    writeln!(writer, "endsolid {}", mesh.name)
+}
+
+/// an 80 byte header, the triangle count and 50 bytes per triangle, little endian
+pub fn write_stl_binary<W: Write>(mesh: &ExportMesh, writer: &mut W) -> io::Result<()> {
+    // readers take headers starting with "solid" for ascii files
+    let mut header = [b' '; 80];
+    let title = format!("binary stl {}", mesh.name);
+    let length = title.len().min(80);
+    header[..length].copy_from_slice(&title.as_bytes()[..length]);
+    writer.write_all(&header)?;
+    writer.write_all(&(mesh.mesh.indices.len() as u32 / 3).to_le_bytes())?;
+
+    for triangle in mesh.mesh.triangles() {
+        let mut values = vec![mesh.facet_normal(triangle)];
+        values.extend(triangle.iter().map(|&index| mesh.position(index)));
+        for value in values {
+            for component in value.to_array().iter() {
+                writer.write_all(&component.to_le_bytes())?;
+            }
+        }
+        // the attribute byte count, unused
+        writer.write_all(&0u16.to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// ascii, with normals and 8 bit colors when the mesh has them
+/// reference: http://paulbourke.net/dataformats/ply/
+pub fn write_ply<W: Write>(mesh: &ExportMesh, writer: &mut W) -> io::Result<()> {
+    let normals = mesh.has_normals();
+    let colors = mesh.has_colors();
+
+    writeln!(writer, "ply")?;
+    writeln!(writer, "format ascii 1.0")?;
+    writeln!(writer, "comment exported by opengl-rust")?;
+    writeln!(writer, "comment object {}", mesh.name)?;
+    writeln!(writer, "element vertex {}", mesh.mesh.vertices.len())?;
+    for property in &["x", "y", "z"] {
+        writeln!(writer, "property float {}", property)?;
+    }
+    if normals {
+        for property in &["nx", "ny", "nz"] {
+            writeln!(writer, "property float {}", property)?;
+        }
+    }
+    if colors {
+        for property in &["red", "green", "blue", "alpha"] {
+            writeln!(writer, "property uchar {}", property)?;
+        }
+    }
+    writeln!(writer, "element face {}", mesh.mesh.indices.len() / 3)?;
+    writeln!(writer, "property list uchar uint vertex_indices")?;
+    writeln!(writer, "end_header")?;
+
+    for vertex in &mesh.mesh.vertices {
+        let [x, y, z] = vertex.position;
+        write!(writer, "{} {} {}", x, y, z)?;
+        if normals {
+            let [x, y, z] = vertex.normal;
+            write!(writer, " {} {} {}", x, y, z)?;
+        }
+        if colors {
+            for channel in vertex.color.iter() {
+                write!(
+                    writer,
+                    " {}",
+                    (clamp(*channel, 0.0, 1.0) * 255.0).round() as u8
+                )?;
+            }
+        }
+        writeln!(writer)?;
+    }
+    for [a, b, c] in mesh.mesh.triangles() {
+        writeln!(writer, "3 {} {} {}", a, b, c)?;
+    }
+
+    Ok(())
+}
+
+/// OBJ files keep every mesh as its own object, STL and PLY get them merged into one
+pub fn export_meshes(
+    meshes: &[ExportMesh],
+    path: &str,
+    format: ExportFormat,
+) -> Result<(), ExportError> {
+    let mut writer = BufWriter::new(File::create(path).map_err(ExportError::Io)?);
+    let name = Path::new(path)
+        .file_stem()
+        .map_or_else(String::new, |stem| stem.to_string_lossy().to_string());
+
+    match format {
+        ExportFormat::Obj => write_obj(meshes, &mut writer),
+        ExportFormat::StlAscii => write_stl_ascii(&ExportMesh::merge(&name, meshes), &mut writer),
+        ExportFormat::StlBinary => write_stl_binary(&ExportMesh::merge(&name, meshes), &mut writer),
+        ExportFormat::Ply => write_ply(&ExportMesh::merge(&name, meshes), &mut writer),
+    }
+    .and_then(|_| writer.flush())
+    .map_err(ExportError::Io)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Quaternion;
+    use crate::matrices::MatrixOperation;
+    use crate::obj::parse_obj;
+    use crate::primitives::Vertex;
+
+    fn vertex(position: [f32; 3], color: [f32; 4], normal: [f32; 3]) -> Vertex {
+        Vertex {
+            position,
+            color,
+            tex_coords: [0.0, 0.0],
+            normal,
+        }
+    }
+
+    /// a quad facing +z, red on the right side
+    fn quad() -> ExportMesh {
+        let white = [1.0, 1.0, 1.0, 1.0];
+        let red = [1.0, 0.0, 0.0, 1.0];
+        let normal = [0.0, 0.0, 1.0];
+        ExportMesh {
+            name: String::from("quad"),
+            mesh: Mesh::new(
+                vec![
+                    vertex([0.0, 0.0, 0.0], white, normal),
+                    vertex([1.0, 0.0, 0.0], red, normal),
+                    vertex([1.0, 1.0, 0.0], red, normal),
+                    vertex([0.0, 1.0, 0.0], white, normal),
+                ],
+                vec![0, 1, 2, 0, 2, 3],
+            ),
+        }
+    }
+
+    fn written<F: Fn(&mut Vec<u8>) -> io::Result<()>>(write: F) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn obj_files_can_be_read_back() {
+        let mut moved = quad();
+        moved.name = String::from("moved");
+        let bytes = written(|writer| write_obj(&[quad(), moved.clone()], writer));
+        let data = parse_obj("quad.obj", &String::from_utf8(bytes).unwrap()).unwrap();
+
+        assert_eq!(data.meshes.len(), 2);
+        assert_eq!(data.meshes[1].name, "moved");
+        let mesh = &data.meshes[1];
+        assert_eq!(mesh.indices, vec![0, 1, 2, 0, 2, 3]);
+        assert_eq!(mesh.vertices[1].color, [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(mesh.vertices[2].normal, [0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn obj_leaves_out_missing_normals_and_colors() {
+        let mut plain = quad();
+        for vertex in &mut plain.mesh.vertices {
+            vertex.color = [1.0, 1.0, 1.0, 1.0];
+            vertex.normal = [0.0, 0.0, 0.0];
+        }
+        let text =
+            String::from_utf8(written(|writer| write_obj(&[plain.clone()], writer))).unwrap();
+
+        assert!(text.contains("v 1 1 0\n"));
+        assert!(!text.contains("vn "));
+        assert!(text.contains("f 1/1 2/2 3/3\n"));
+    }
+
+    #[test]
+    fn stl_has_one_facet_per_triangle() {
+        let text = String::from_utf8(written(|writer| write_stl_ascii(&quad(), writer))).unwrap();
+        assert!(text.starts_with("solid quad\n"));
+        assert_eq!(text.matches("facet normal 0 0 1").count(), 2);
+        assert_eq!(text.matches("vertex ").count(), 6);
+        assert!(text.ends_with("endsolid quad\n"));
+
+        let bytes = written(|writer| write_stl_binary(&quad(), writer));
+        assert_eq!(bytes.len(), 80 + 4 + 2 * 50);
+        assert!(!bytes.starts_with(b"solid"));
+        assert_eq!(&bytes[80..84], &2u32.to_le_bytes());
+        // the normal z of the first triangle
+        assert_eq!(&bytes[92..96], &1.0f32.to_le_bytes());
+    }
+
+    #[test]
+    fn ply_header_matches_the_vertex_data() {
+        let text = String::from_utf8(written(|writer| write_ply(&quad(), writer))).unwrap();
+        let (header, body) = text.split_at(text.find("end_header\n").unwrap() + 11);
+
+        assert!(header.contains("element vertex 4\n"));
+        assert!(header.contains("property float nz\n"));
+        assert!(header.contains("property uchar alpha\n"));
+        assert!(header.contains("element face 2\n"));
+        let lines: Vec<&str> = body.lines().collect();
+        assert_eq!(lines[1], "1 0 0 0 0 1 255 0 0 255");
+        assert_eq!(lines[5], "3 0 2 3");
+    }
+
+    #[test]
+    fn transforms_are_baked() {
+        let moved = quad().transformed(
+            MatrixOperation::translation(Vector3::new(0.0, 0.0, 5.0)) * Quaternion::rotate_y(90.0),
+        );
+        assert_eq!(moved.mesh.indices, quad().mesh.indices);
+        let [x, y, z] = moved.mesh.vertices[1].position;
+        assert!((Vector3::new(x, y, z) - Vector3::new(0.0, 0.0, 4.0)).length() < 1e-5);
+        let [x, y, z] = moved.mesh.vertices[0].normal;
+        assert!((Vector3::new(x, y, z) - Vector3::new(1.0, 0.0, 0.0)).length() < 1e-5);
+
+        // a squashed mesh keeps unit normals, a mirrored one keeps its winding
+        let mirrored = quad().transformed(MatrixOperation::scale(Vector3::new(-1.0, 1.0, 0.5)));
+        assert_eq!(mirrored.mesh.indices, vec![0, 2, 1, 0, 3, 2]);
+        assert_eq!(mirrored.mesh.vertices[0].normal, [0.0, 0.0, 1.0]);
+        assert!(mirrored.facet_normal([0, 2, 1]).z > 0.0);
+    }
+
+    #[test]
+    fn merged_meshes_offset_their_indices() {
+        let merged = ExportMesh::merge("both", &[quad(), quad()]);
+        assert_eq!(merged.mesh.vertices.len(), 8);
+        assert_eq!(merged.mesh.triangles().nth(2), Some([4, 5, 6]));
+    }
+
+    #[test]
+    fn formats_come_from_the_extension() {
+        assert_eq!(ExportFormat::from_path("a.obj").unwrap(), ExportFormat::Obj);
+        assert_eq!(
+            ExportFormat::from_path("a.stl").unwrap(),
+            ExportFormat::StlBinary
+        );
+        assert_eq!(ExportFormat::from_path("a.ply").unwrap(), ExportFormat::Ply);
+        assert!(ExportFormat::from_path("a.fbx").is_err());
+    }
+}
@@ -0,0 +1,190 @@
+use crate::math::{clamp, Vector3};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FogMode {
+    Off,
+    Linear,
+    Exponential,
+    ExponentialSquared,
+}
+
+impl FogMode {
+    /// values must match the `fogMode` checks on smooth_color.frag
+    pub fn to_uniform(self) -> i32 {
+        match self {
+            FogMode::Off => 0,
+            FogMode::Linear => 1,
+            FogMode::Exponential => 2,
+            FogMode::ExponentialSquared => 3,
+        }
+    }
+}
+
+/// fog gets thinner above `base`, decaying by exp(-falloff * (height - base)).
+/// A falloff of 0.0 means height has no effect.
+#[derive(Debug, Clone, Copy)]
+pub struct HeightFalloff {
+    pub base: f32,
+    pub falloff: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Fog {
+    pub mode: FogMode,
+    pub color: Vector3,
+    pub start: f32,
+    pub end: f32,
+    pub density: f32,
+    pub height: Option<HeightFalloff>,
+}
+
+/// reference: https://www.khronos.org/registry/OpenGL-Refpages/gl2.1/xhtml/glFog.xml
+/// `visibility` mirrors what smooth_color.frag does, so it can be used to reason about a scene on the cpu.
+#[allow(dead_code)]
+impl Fog {
+    pub fn off() -> Self {
+        Fog {
+            mode: FogMode::Off,
+            color: Vector3::new(0.0, 0.0, 0.0),
+            start: 0.0,
+            end: 0.0,
+            density: 0.0,
+            height: None,
+        }
+    }
+
+    pub fn linear(color: Vector3, start: f32, end: f32) -> Self {
+        Fog {
+            mode: FogMode::Linear,
+            color,
+            start,
+            end,
+            ..Fog::off()
+        }
+    }
+
+    pub fn exponential(color: Vector3, density: f32) -> Self {
+        Fog {
+            mode: FogMode::Exponential,
+            color,
+            density,
+            ..Fog::off()
+        }
+    }
+
+    pub fn exponential_squared(color: Vector3, density: f32) -> Self {
+        Fog {
+            mode: FogMode::ExponentialSquared,
+            color,
+            density,
+            ..Fog::off()
+        }
+    }
+
+    pub fn with_height_falloff(mut self, base: f32, falloff: f32) -> Self {
+        self.height = Some(HeightFalloff { base, falloff });
+        self
+    }
+
+    pub fn clear_color(&self) -> (f32, f32, f32, f32) {
+        (self.color.x, self.color.y, self.color.z, 1.0)
+    }
+
+    pub fn height_base(&self) -> f32 {
+        self.height.map_or(0.0, |height| height.base)
+    }
+
+    pub fn height_falloff(&self) -> f32 {
+        self.height.map_or(0.0, |height| height.falloff)
+    }
+
+    /// 1.0 means the fragment keeps its own color, 0.0 means it is fully replaced by the fog color
+    pub fn visibility(&self, distance: f32, height: f32) -> f32 {
+        let visibility = match self.mode {
+            FogMode::Off => return 1.0,
+            FogMode::Linear => {
+                if self.end <= self.start {
+                    if distance <= self.start {
+                        1.0
+                    } else {
+                        0.0
+                    }
+                } else {
+                    (self.end - distance) / (self.end - self.start)
+                }
+            }
+            FogMode::Exponential => (-self.density * distance).exp(),
+            FogMode::ExponentialSquared => {
+                let amount = self.density * distance;
+                (-amount * amount).exp()
+            }
+        };
+        let visibility = clamp(visibility, 0.0, 1.0);
+
+        let height_factor = (-self.height_falloff() * (height - self.height_base()).max(0.0)).exp();
+
+        1.0 - (1.0 - visibility) * height_factor
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn color() -> Vector3 {
+        Vector3::new(0.5, 0.5, 0.5)
+    }
+
+    #[test]
+    fn fog_off_keeps_everything_visible() {
+        let fog = Fog::off();
+
+        assert_eq!(1.0, fog.visibility(0.0, 0.0));
+        assert_eq!(1.0, fog.visibility(10_000.0, 0.0));
+    }
+
+    #[test]
+    fn fog_linear_visibility() {
+        let fog = Fog::linear(color(), 10.0, 20.0);
+
+        assert_eq!(1.0, fog.visibility(5.0, 0.0));
+        assert_eq!(1.0, fog.visibility(10.0, 0.0));
+        assert_eq!(0.5, fog.visibility(15.0, 0.0));
+        assert_eq!(0.0, fog.visibility(20.0, 0.0));
+        assert_eq!(0.0, fog.visibility(500.0, 0.0));
+    }
+
+    #[test]
+    fn fog_exponential_visibility() {
+        let fog = Fog::exponential(color(), 0.1);
+
+        assert_eq!(1.0, fog.visibility(0.0, 0.0));
+        assert_eq!(0.36787945, fog.visibility(10.0, 0.0));
+    }
+
+    #[test]
+    fn fog_exponential_squared_visibility() {
+        let fog = Fog::exponential_squared(color(), 0.1);
+
+        assert_eq!(1.0, fog.visibility(0.0, 0.0));
+        assert_eq!(0.36787945, fog.visibility(10.0, 0.0));
+        assert_eq!(0.018315613, fog.visibility(20.0, 0.0));
+    }
+
+    #[test]
+    fn fog_height_falloff_thins_fog_above_base() {
+        let fog = Fog::linear(color(), 0.0, 10.0).with_height_falloff(2.0, 1.0);
+
+        assert_eq!(0.0, fog.visibility(10.0, 0.0));
+        assert_eq!(0.0, fog.visibility(10.0, 2.0));
+        assert_eq!(0.63212055, fog.visibility(10.0, 3.0));
+        assert!(fog.visibility(10.0, 50.0) > 0.999);
+    }
+
+    #[test]
+    fn fog_clear_color_matches_fog_color() {
+        let fog = Fog::exponential(Vector3::new(0.2, 0.3, 0.4), 0.01);
+
+        assert_eq!((0.2, 0.3, 0.4, 1.0), fog.clear_color());
+    }
+}
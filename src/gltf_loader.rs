@@ -0,0 +1,732 @@
+use crate::lights::Light;
+use crate::materials::{BlendMode, Material};
+use crate::math::{Matrix4, Quaternion, Vector3};
+use crate::mesh::Mesh;
+use crate::models::{triangles_of, Prefab, Projection, Viewport, World, Z_FAR};
+use crate::primitives::Vertex;
+use crate::scene::{
+    CameraDescription, InstanceDescription, LightDescription, Scene, SceneError, Transform,
+};
+use glium::index::PrimitiveType;
+use glium::texture::RawImage2d;
+use glium::Texture2d;
+use gltf::khr_lights_punctual::Kind;
+use gltf::mesh::Mode;
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+
+/// One primitive of a glTF mesh, registered as a prefab.
+#[derive(Debug, Clone)]
+pub struct GltfMesh {
+    pub name: String,
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+}
+
+/// rgba pixels, the first row is the top one like glTF texture coordinates expect
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfMaterial {
+    pub name: String,
+    /// the base color factor
+    pub color: [f32; 4],
+    pub blend_mode: BlendMode,
+    /// an index into `GltfData::images`, the base color texture
+    pub image: Option<usize>,
+}
+
+/// Everything read from a glTF file, before anything is sent to the gpu.
+/// The scene refers to the meshes and materials by name.
+#[derive(Debug, Clone)]
+pub struct GltfData {
+    pub meshes: Vec<GltfMesh>,
+    pub materials: Vec<GltfMaterial>,
+    pub images: Vec<GltfImage>,
+    pub scene: Scene,
+}
+
+#[derive(Debug)]
+pub enum GltfError {
+    Import { path: String, error: gltf::Error },
+    Mesh { mesh: String, message: String },
+    Image { index: usize, message: String },
+    Scene(SceneError),
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GltfError::Import { path, error } => write!(f, "{}: {}", path, error),
+            GltfError::Mesh { mesh, message } => write!(f, "mesh \"{}\": {}", mesh, message),
+            GltfError::Image { index, message } => write!(f, "image {}: {}", index, message),
+            GltfError::Scene(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for GltfError {}
+
+/// the vertices and triangle list indices of a primitive
+type Geometry = (Vec<Vertex>, Vec<u32>);
+/// the prefab and material names of a primitive
+type PrimitiveNames = (String, Option<String>);
+
+/// for nodes without a mesh, prefab names from meshes always end with the primitive index
+fn empty_prefab(name: &str) -> String {
+    format!("{}/empty", name)
+}
+
+/// `base`, or `base.index` when a name is repeated, as glTF allows
+fn unique_name(used: &mut HashSet<String>, base: String, index: usize) -> String {
+    let name = if used.contains(&base) {
+        format!("{}.{}", base, index)
+    } else {
+        base
+    };
+    used.insert(name.clone());
+    name
+}
+
+/// glTF matrices are column major, ours are row major
+fn to_matrix(columns: [[f32; 4]; 4]) -> Matrix4 {
+    let mut data = [0.0; 16];
+    for (column, values) in columns.iter().enumerate() {
+        for (row, value) in values.iter().enumerate() {
+            data[row * 4 + column] = *value;
+        }
+    }
+    Matrix4::from(data)
+}
+
+/// what glTF asks for when a primitive has no normals, every triangle gets its own
+/// corners with the triangle's normal
+fn flat_normals(vertices: Vec<Vertex>, indices: Vec<u32>) -> Geometry {
+    let mut mesh = Mesh::new(vertices, indices);
+    mesh.flat_normals();
+    (mesh.vertices, mesh.indices)
+}
+
+/// strips and fans become lists, other modes have no triangles
+fn triangle_list(mode: Mode, indices: Vec<u32>) -> Option<Vec<u32>> {
+    let primitive_type = match mode {
+        Mode::Triangles => PrimitiveType::TrianglesList,
+        Mode::TriangleStrip => PrimitiveType::TriangleStrip,
+        Mode::TriangleFan => PrimitiveType::TriangleFan,
+        _ => return None,
+    };
+
+    Some(
+        triangles_of(primitive_type, &indices)
+            .into_iter()
+            .flatten()
+            .map(|index| index as u32)
+            .collect(),
+    )
+}
+
+fn read_primitive(
+    mesh_name: &str,
+    primitive: &gltf::Primitive,
+    buffers: &[gltf::buffer::Data],
+) -> Result<Option<Geometry>, GltfError> {
+    let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+
+    let positions: Vec<[f32; 3]> = match reader.read_positions() {
+        Some(positions) => positions.collect(),
+        None => {
+            return Err(GltfError::Mesh {
+                mesh: String::from(mesh_name),
+                message: format!("primitive {} has no positions", primitive.index()),
+            })
+        }
+    };
+    let indices = match reader.read_indices() {
+        Some(indices) => indices.into_u32().collect(),
+        None => (0..positions.len() as u32).collect(),
+    };
+    let indices = match triangle_list(primitive.mode(), indices) {
+        Some(indices) => indices,
+        None => return Ok(None),
+    };
+    if let Some(index) = indices
+        .iter()
+        .find(|&&index| index as usize >= positions.len())
+    {
+        return Err(GltfError::Mesh {
+            mesh: String::from(mesh_name),
+            message: format!(
+                "primitive {} uses vertex {} of {}",
+                primitive.index(),
+                index,
+                positions.len()
+            ),
+        });
+    }
+
+    let mut vertices: Vec<Vertex> = positions
+        .into_iter()
+        .map(|position| Vertex {
+            position,
+            color: [1.0, 1.0, 1.0, 1.0],
+            tex_coords: [0.0, 0.0],
+            normal: [0.0, 0.0, 0.0],
+        })
+        .collect();
+
+    if let Some(colors) = reader.read_colors(0) {
+        for (vertex, color) in vertices.iter_mut().zip(colors.into_rgba_f32()) {
+            vertex.color = color;
+        }
+    }
+    if let Some(tex_coords) = reader.read_tex_coords(0) {
+        for (vertex, tex_coords) in vertices.iter_mut().zip(tex_coords.into_f32()) {
+            vertex.tex_coords = tex_coords;
+        }
+    }
+    match reader.read_normals() {
+        Some(normals) => {
+            for (vertex, normal) in vertices.iter_mut().zip(normals) {
+                vertex.normal = normal;
+            }
+            Ok(Some((vertices, indices)))
+        }
+        None => Ok(Some(flat_normals(vertices, indices))),
+    }
+}
+
+fn read_image(index: usize, image: gltf::image::Data) -> Result<GltfImage, GltfError> {
+    use gltf::image::Format;
+
+    let pixels = match image.format {
+        Format::R8G8B8A8 => image.pixels,
+        Format::R8G8B8 => image
+            .pixels
+            .chunks_exact(3)
+            .flat_map(|pixel| vec![pixel[0], pixel[1], pixel[2], 255])
+            .collect(),
+        Format::B8G8R8A8 => image
+            .pixels
+            .chunks_exact(4)
+            .flat_map(|pixel| vec![pixel[2], pixel[1], pixel[0], pixel[3]])
+            .collect(),
+        Format::B8G8R8 => image
+            .pixels
+            .chunks_exact(3)
+            .flat_map(|pixel| vec![pixel[2], pixel[1], pixel[0], 255])
+            .collect(),
+        // single channel images are grayscale
+        Format::R8 => image
+            .pixels
+            .iter()
+            .flat_map(|&value| vec![value, value, value, 255])
+            .collect(),
+        Format::R8G8 => image
+            .pixels
+            .chunks_exact(2)
+            .flat_map(|pixel| vec![pixel[0], pixel[0], pixel[0], pixel[1]])
+            .collect(),
+        format => {
+            return Err(GltfError::Image {
+                index,
+                message: format!("{:?} images are not supported, only 8 bits", format),
+            })
+        }
+    };
+
+    Ok(GltfImage {
+        width: image.width,
+        height: image.height,
+        pixels,
+    })
+}
+
+/// walks the node tree, every node becomes an instance parented to the node above it
+struct NodeReader<'a> {
+    name: &'a str,
+    /// prefab names of each primitive, by mesh index, `None` for skipped primitives
+    prefabs: &'a [Vec<Option<PrimitiveNames>>],
+    used_names: HashSet<String>,
+    /// set once a node without geometry needs the empty prefab
+    uses_empty: bool,
+    scene: Scene,
+}
+
+impl<'a> NodeReader<'a> {
+    fn node(&mut self, node: gltf::Node, parent: Option<&str>, parent_world: Matrix4) {
+        let local = to_matrix(node.transform().matrix());
+        let world = parent_world * local;
+        let base = match node.name() {
+            Some(name) => format!("{}/{}", self.name, name),
+            None => format!("{}/node{}", self.name, node.index()),
+        };
+        let node_name = unique_name(&mut self.used_names, base, node.index());
+
+        if let Some(camera) = node.camera() {
+            self.camera(&node_name, camera, world);
+        }
+        if let Some(light) = node.light() {
+            self.light(&node_name, light, world);
+        }
+
+        let primitives: Vec<&PrimitiveNames> = match node.mesh() {
+            Some(mesh) => self.prefabs[mesh.index()].iter().flatten().collect(),
+            None => Vec::new(),
+        };
+        let (prefab, material) = match primitives.first() {
+            Some((prefab, material)) => (prefab.clone(), material.clone()),
+            None => {
+                self.uses_empty = true;
+                (empty_prefab(self.name), None)
+            }
+        };
+
+        // the first primitive is the node, the others are its children
+        self.scene.instances.push(InstanceDescription {
+            name: node_name.clone(),
+            prefab,
+            material,
+            color: [1.0, 1.0, 1.0, 1.0],
+            transform: Transform::from_matrix(local),
+            parent: parent.map(String::from),
+        });
+        for (i, (prefab, material)) in primitives.iter().enumerate().skip(1) {
+            self.scene.instances.push(InstanceDescription {
+                name: format!("{}/{}", node_name, i),
+                prefab: prefab.clone(),
+                material: material.clone(),
+                color: [1.0, 1.0, 1.0, 1.0],
+                transform: Transform::default(),
+                parent: Some(node_name.clone()),
+            });
+        }
+
+        for child in node.children() {
+            self.node(child, Some(&node_name), world);
+        }
+    }
+
+    /// glTF cameras look down -z, ours look down their forward vector
+    fn camera(&mut self, name: &str, camera: gltf::Camera, world: Matrix4) {
+        let (projection, z_near, z_far) = match camera.projection() {
+            gltf::camera::Projection::Perspective(perspective) => (
+                Projection::Perspective {
+                    fov: perspective.yfov().to_degrees(),
+                },
+                perspective.znear(),
+                perspective.zfar().unwrap_or(Z_FAR),
+            ),
+            gltf::camera::Projection::Orthographic(orthographic) => (
+                Projection::Orthographic {
+                    height: orthographic.ymag() * 2.0,
+                },
+                orthographic.znear(),
+                orthographic.zfar(),
+            ),
+        };
+
+        self.scene.cameras.push(CameraDescription {
+            name: String::from(name),
+            transform: Transform::from_matrix(world * Quaternion::rotate_y(180.0)),
+            look_at: None,
+            projection,
+            z_near,
+            z_far,
+            viewport: Viewport::full(),
+            order: 0,
+            // the main camera stays in charge until one of these is turned on
+            active: false,
+            render_target: None,
+        });
+    }
+
+    /// punctual lights shine down -z, and have no range limit when it is missing
+    fn light(&mut self, name: &str, light: gltf::khr_lights_punctual::Light, world: Matrix4) {
+        let [r, g, b] = light.color();
+        let color = Vector3::new(r, g, b);
+        let position = world.get_position();
+        let direction = world.transform_vector(Vector3::new(0.0, 0.0, -1.0));
+        let range = light.range().unwrap_or(f32::MAX);
+
+        let light = match light.kind() {
+            Kind::Directional => Light::directional(direction, color, light.intensity()),
+            Kind::Point => Light::point(position, range, color, light.intensity()),
+            Kind::Spot {
+                outer_cone_angle, ..
+            } => Light::spot(
+                position,
+                direction,
+                range,
+                outer_cone_angle.to_degrees(),
+                color,
+                light.intensity(),
+            ),
+        };
+
+        self.scene.lights.push(LightDescription {
+            name: String::from(name),
+            light,
+        });
+    }
+}
+
+/// Reads a .gltf or .glb file with embedded or external buffers and images.
+/// Every name is prefixed with "name/": primitives become prefabs named "name/mesh/0",
+/// materials keep their name, and nodes become instances parented like the node tree.
+/// Meshes with several primitives add the others as children "name/node/1".
+/// Nodes without a mesh use the "name/empty" prefab, which has no geometry.
+/// reference: https://registry.khronos.org/glTF/specs/2.0/glTF-2.0.html
+pub fn read_gltf(path: &str, name: &str) -> Result<GltfData, GltfError> {
+    let (document, buffers, images) = gltf::import(path).map_err(|error| GltfError::Import {
+        path: String::from(path),
+        error,
+    })?;
+
+    let images = images
+        .into_iter()
+        .enumerate()
+        .map(|(index, image)| read_image(index, image))
+        .collect::<Result<Vec<GltfImage>, GltfError>>()?;
+
+    let mut used_names = HashSet::new();
+    let mut materials = Vec::new();
+    for material in document.materials() {
+        // the default material has no index, and is left as our default material
+        let index = match material.index() {
+            Some(index) => index,
+            None => continue,
+        };
+        let base = match material.name() {
+            Some(material_name) => format!("{}/{}", name, material_name),
+            None => format!("{}/material{}", name, index),
+        };
+        let pbr = material.pbr_metallic_roughness();
+        materials.push(GltfMaterial {
+            name: unique_name(&mut used_names, base, index),
+            color: pbr.base_color_factor(),
+            // masks are drawn opaque, there is no alpha test
+            blend_mode: match material.alpha_mode() {
+                gltf::material::AlphaMode::Blend => BlendMode::Alpha,
+                _ => BlendMode::Opaque,
+            },
+            image: pbr
+                .base_color_texture()
+                .map(|info| info.texture().source().index()),
+        });
+    }
+
+    let mut used_names = HashSet::new();
+    let mut meshes = Vec::new();
+    let mut prefabs = Vec::new();
+    for mesh in document.meshes() {
+        let base = match mesh.name() {
+            Some(mesh_name) => format!("{}/{}", name, mesh_name),
+            None => format!("{}/mesh{}", name, mesh.index()),
+        };
+        let mesh_name = unique_name(&mut used_names, base, mesh.index());
+
+        let mut primitives = Vec::new();
+        for primitive in mesh.primitives() {
+            match read_primitive(&mesh_name, &primitive, &buffers)? {
+                Some((vertices, indices)) => {
+                    let prefab = format!("{}/{}", mesh_name, primitive.index());
+                    let material = primitive
+                        .material()
+                        .index()
+                        .map(|index| materials[index].name.clone());
+                    meshes.push(GltfMesh {
+                        name: prefab.clone(),
+                        vertices,
+                        indices,
+                    });
+                    primitives.push(Some((prefab, material)));
+                }
+                None => primitives.push(None),
+            }
+        }
+        prefabs.push(primitives);
+    }
+
+    let mut reader = NodeReader {
+        name,
+        prefabs: &prefabs,
+        used_names: HashSet::new(),
+        uses_empty: false,
+        scene: Scene::default(),
+    };
+    if let Some(scene) = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+    {
+        for node in scene.nodes() {
+            reader.node(node, None, Matrix4::identity());
+        }
+    }
+    if reader.uses_empty {
+        meshes.push(GltfMesh {
+            name: empty_prefab(name),
+            vertices: Vec::new(),
+            indices: Vec::new(),
+        });
+    }
+
+    Ok(GltfData {
+        meshes,
+        materials,
+        images,
+        scene: reader.scene,
+    })
+}
+
+/// Reads a glTF file and adds it to `world`, replacing what has the same names.
+/// Nothing is added when the file has an error
+pub fn load_gltf(world: &mut World<'static>, path: &str, name: &str) -> Result<(), GltfError> {
+    let data = read_gltf(path, name)?;
+
+    // checked before anything is added, so a scene error leaves the world as it was
+    let mut known = world.scene_names();
+    known
+        .prefabs
+        .extend(data.meshes.iter().map(|mesh| mesh.name.clone()));
+    known
+        .materials
+        .extend(data.materials.iter().map(|material| material.name.clone()));
+    data.scene.validate(&known).map_err(GltfError::Scene)?;
+    data.scene.world_transforms().map_err(GltfError::Scene)?;
+
+    // glTF images start at the top, like the texture coordinates, so nothing is flipped
+    let textures: Vec<Arc<Texture2d>> = data
+        .images
+        .into_iter()
+        .map(|image| {
+            let image = RawImage2d::from_raw_rgba(image.pixels, (image.width, image.height));
+            Arc::new(Texture2d::new(&world.display, image).unwrap())
+        })
+        .collect();
+
+    for mesh in data.meshes {
+        let prefab = Prefab::build_u32(world.display.clone(), mesh.vertices, mesh.indices);
+        world.add_prefab(mesh.name, prefab);
+    }
+
+    for material in data.materials {
+        let registered = match material.image {
+            Some(image) => {
+                Material::textured(material.color, material.blend_mode, textures[image].clone())
+            }
+            None => Material::new(material.color, material.blend_mode),
+        };
+        world.add_material(material.name, registered);
+    }
+
+    world.load_scene(&data.scene).map_err(GltfError::Scene)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lights::LightKind;
+
+    const SAMPLES: [&str; 3] = [
+        "assets/gltf/sample_embedded.gltf",
+        "assets/gltf/sample_external.gltf",
+        "assets/gltf/sample.glb",
+    ];
+
+    fn assert_close(a: Vector3, b: Vector3) {
+        assert!((a - b).length() < 1e-4, "{:?} != {:?}", a, b);
+    }
+
+    fn instance<'a>(data: &'a GltfData, name: &str) -> &'a InstanceDescription {
+        data.scene
+            .instances
+            .iter()
+            .find(|instance| instance.name == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn reads_meshes_and_materials() {
+        let data = read_gltf(SAMPLES[0], "sample").unwrap();
+
+        // the nodes without a mesh share the last, empty one
+        assert_eq!(data.meshes.len(), 3);
+        assert_eq!(data.meshes[2].name, "sample/empty");
+        assert!(data.meshes[2].vertices.is_empty());
+        let quad = &data.meshes[0];
+        assert_eq!(quad.name, "sample/box/0");
+        assert_eq!(quad.vertices.len(), 4);
+        assert_eq!(quad.indices, vec![0, 1, 2, 0, 2, 3]);
+        assert_eq!(quad.vertices[0].tex_coords, [0.0, 1.0]);
+        assert_eq!(quad.vertices[0].normal, [0.0, 0.0, 1.0]);
+
+        // no indices and no normals on the second primitive
+        let triangle = &data.meshes[1];
+        assert_eq!(triangle.indices, vec![0, 1, 2]);
+        assert_eq!(triangle.vertices[1].color, [0.0, 1.0, 0.0, 1.0]);
+        assert_eq!(triangle.vertices[2].normal, [0.0, 0.0, 1.0]);
+
+        assert_eq!(
+            data.materials,
+            vec![
+                GltfMaterial {
+                    name: String::from("sample/painted"),
+                    color: [1.0, 0.5, 0.5, 1.0],
+                    blend_mode: BlendMode::Opaque,
+                    image: Some(0),
+                },
+                GltfMaterial {
+                    name: String::from("sample/glass"),
+                    color: [0.5, 0.5, 1.0, 0.5],
+                    blend_mode: BlendMode::Alpha,
+                    image: None,
+                },
+            ]
+        );
+
+        // red, green on the top row, blue, white below
+        let image = &data.images[0];
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(&image.pixels[..8], &[255, 0, 0, 255, 0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn missing_normals_are_flat() {
+        let corner = |x: f32, y: f32, z: f32| Vertex {
+            position: [x, y, z],
+            color: [1.0, 1.0, 1.0, 1.0],
+            tex_coords: [0.0, 0.0],
+            normal: [0.0, 0.0, 0.0],
+        };
+        // a roof, folded along the shared edge from 0 to 1
+        let vertices = vec![
+            corner(0.0, 1.0, 0.0),
+            corner(0.0, 1.0, 1.0),
+            corner(-1.0, 0.0, 0.0),
+            corner(1.0, 0.0, 0.0),
+        ];
+
+        let (vertices, indices) = flat_normals(vertices, vec![0, 2, 1, 0, 1, 3]);
+
+        assert_eq!(indices, vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(vertices[0].position, vertices[3].position);
+        for (i, vertex) in vertices.iter().enumerate() {
+            let [x, y, z] = vertex.normal;
+            let expected = if i < 3 { -1.0 } else { 1.0 };
+            assert_close(
+                Vector3::new(x, y, z),
+                Vector3::new(expected, 1.0, 0.0).normalized(),
+            );
+        }
+    }
+
+    #[test]
+    fn node_tree_becomes_instances() {
+        let data = read_gltf(SAMPLES[0], "sample").unwrap();
+        // the five nodes and the second primitive of the box
+        assert_eq!(data.scene.instances.len(), 6);
+
+        let base = instance(&data, "sample/base");
+        assert_eq!(base.prefab, "sample/empty");
+        assert_eq!(base.parent, None);
+        assert_close(base.transform.translation, Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(instance(&data, "sample/lamp").prefab, "sample/empty");
+
+        let quad = instance(&data, "sample/box");
+        assert_eq!(quad.prefab, "sample/box/0");
+        assert_eq!(quad.material.as_deref(), Some("sample/painted"));
+        assert_eq!(quad.parent.as_deref(), Some("sample/base"));
+        assert_close(quad.transform.translation, Vector3::new(0.0, 2.0, 0.0));
+        assert_close(quad.transform.scale, Vector3::new(2.0, 2.0, 2.0));
+
+        let triangle = instance(&data, "sample/box/1");
+        assert_eq!(triangle.prefab, "sample/box/1");
+        assert_eq!(triangle.material.as_deref(), Some("sample/glass"));
+        assert_eq!(triangle.parent.as_deref(), Some("sample/box"));
+        assert_eq!(triangle.transform, Transform::default());
+
+        let transforms = data.scene.world_transforms().unwrap();
+        assert_close(
+            transforms["sample/box/1"].transform_point(Vector3::new(1.0, 0.0, 0.0)),
+            Vector3::new(3.0, 2.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn cameras_and_lights() {
+        let data = read_gltf(SAMPLES[0], "sample").unwrap();
+
+        let camera = &data.scene.cameras[0];
+        assert_eq!(camera.name, "sample/camera");
+        assert!(!camera.active);
+        assert_eq!(camera.z_far, 100.0);
+        match camera.projection {
+            Projection::Perspective { fov } => assert!((fov - 45.0).abs() < 1e-3),
+            projection => panic!("expected a perspective camera, found {:?}", projection),
+        }
+        let matrix = camera.transform.to_matrix();
+        assert_close(matrix.get_position(), Vector3::new(0.0, 1.0, 5.0));
+        assert_close(matrix.get_forward_vector(), Vector3::new(0.0, 0.0, -1.0));
+
+        assert_eq!(data.scene.lights.len(), 2);
+        let sun = &data.scene.lights[0];
+        assert_eq!(sun.name, "sample/sun");
+        assert_eq!(sun.light.intensity, 2.0);
+        match sun.light.kind {
+            LightKind::Directional { direction } => {
+                assert_close(direction, Vector3::new(0.0, -1.0, 0.0))
+            }
+            kind => panic!("expected a directional light, found {:?}", kind),
+        }
+        assert_eq!(
+            data.scene.lights[1].light.kind,
+            LightKind::Point {
+                position: Vector3::new(0.0, 3.0, 0.0),
+                range: 10.0
+            }
+        );
+    }
+
+    #[test]
+    fn embedded_external_and_binary_files_match() {
+        let embedded = read_gltf(SAMPLES[0], "sample").unwrap();
+
+        for path in &SAMPLES[1..] {
+            let data = read_gltf(path, "sample").unwrap();
+            assert_eq!(data.scene, embedded.scene, "{}", path);
+            assert_eq!(data.materials, embedded.materials, "{}", path);
+            assert_eq!(data.images, embedded.images, "{}", path);
+            assert_eq!(data.meshes.len(), embedded.meshes.len(), "{}", path);
+            for (mesh, expected) in data.meshes.iter().zip(&embedded.meshes) {
+                assert_eq!(mesh.indices, expected.indices, "{}", path);
+            }
+        }
+    }
+
+    #[test]
+    fn strips_and_fans_become_lists() {
+        assert_eq!(
+            triangle_list(Mode::TriangleStrip, vec![0, 1, 2, 3]),
+            Some(vec![0, 1, 2, 1, 3, 2])
+        );
+        assert_eq!(
+            triangle_list(Mode::TriangleFan, vec![0, 1, 2, 3]),
+            Some(vec![0, 1, 2, 0, 2, 3])
+        );
+        assert_eq!(triangle_list(Mode::Lines, vec![0, 1]), None);
+    }
+
+    #[test]
+    fn missing_files_are_errors() {
+        match read_gltf("assets/gltf/missing.gltf", "missing") {
+            Err(GltfError::Import { path, .. }) => assert_eq!(path, "assets/gltf/missing.gltf"),
+            _ => panic!("expected an import error"),
+        }
+    }
+}
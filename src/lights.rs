@@ -0,0 +1,69 @@
+use crate::math::Vector3;
+use serde::{Deserialize, Serialize};
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum LightKind {
+    /// like the sun, the same direction everywhere
+    Directional { direction: Vector3 },
+    /// no light past `range`
+    Point { position: Vector3, range: f32 },
+    /// a point light limited to a cone, `angle` in degrees from the center to the edge
+    Spot {
+        position: Vector3,
+        direction: Vector3,
+        range: f32,
+        angle: f32,
+    },
+}
+
+/// Light sources placed in the `World`. The shaders only use vertex colors for now,
+/// lights are kept so scenes can describe them.
+/// reference: https://learnopengl.com/Lighting/Light-casters
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Light {
+    pub kind: LightKind,
+    pub color: Vector3,
+    pub intensity: f32,
+}
+
+#[allow(dead_code)]
+impl Light {
+    pub fn directional(direction: Vector3, color: Vector3, intensity: f32) -> Self {
+        Light {
+            kind: LightKind::Directional {
+                direction: direction.normalized(),
+            },
+            color,
+            intensity,
+        }
+    }
+
+    pub fn point(position: Vector3, range: f32, color: Vector3, intensity: f32) -> Self {
+        Light {
+            kind: LightKind::Point { position, range },
+            color,
+            intensity,
+        }
+    }
+
+    pub fn spot(
+        position: Vector3,
+        direction: Vector3,
+        range: f32,
+        angle: f32,
+        color: Vector3,
+        intensity: f32,
+    ) -> Self {
+        Light {
+            kind: LightKind::Spot {
+                position,
+                direction: direction.normalized(),
+                range,
+                angle,
+            },
+            color,
+            intensity,
+        }
+    }
+}
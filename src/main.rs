@@ -1,46 +1,218 @@
 #[macro_use]
 extern crate glium;
 
+mod benchmark;
+mod cameras;
+mod character;
+mod cli;
+mod config;
 mod coordinates;
+mod dynamic_mesh;
+mod export;
+mod fog;
+mod gltf_loader;
+mod lights;
+mod materials;
 mod math;
 mod matrices;
+mod mesh;
 mod models;
+mod obj;
+mod physics;
+mod post_processing;
 mod primitives;
+mod render_target;
+mod scene;
 mod shaders;
+mod shapes;
+mod spatial;
 
+use benchmark::Benchmark;
+use cameras::{FlyCamera, FollowCamera, FpsCamera, MovementSettings, OrbitCamera};
+use character::CharacterController;
+use cli::{CliOptions, USAGE};
+use config::{WindowMode, WorldConfig};
 use coordinates::SphereVector;
+use fog::Fog;
 use glium::glutin;
-use glium::glutin::event::VirtualKeyCode;
+use glium::glutin::event::{ElementState, MouseButton, VirtualKeyCode};
 use glium::glutin::event_loop::{ControlFlow, EventLoop};
-use math::{clamp, Vector3};
-use models::{Camera, Instance, World};
-use primitives::Primitive;
+use glium::glutin::platform::desktop::EventLoopExtRunReturn;
+use gltf_loader::load_gltf;
+use materials::{BlendMode, Material};
+use math::{clamp, Quaternion, Vector3};
+use matrices::MatrixOperation;
+use models::{Camera, FrameStats, Instance, PickResult, Viewport, World, MAIN_CAMERA};
+use physics::{Collider, RigidBody};
+use post_processing::PostProcessing;
+use render_target::RenderTarget;
+use std::path::Path;
 
 const MOUSE_SENSIBILITY: f32 = 0.5;
-const KEY_MOVEMENT: f32 = 0.25;
+/// units per second
+const MOVEMENT_SPEED: f32 = 8.0;
+const JUMP_SPEED: f32 = 5.0;
+/// seconds between draws, the event loop waits at least this long
+const FRAME_TIME: f32 = 1.0 / 60.0;
+
+fn title_text(fps: f32, stats: FrameStats, picked: &Option<PickResult>) -> String {
+    match picked {
+        Some(hit) => format!(
+            "{:.0} fps, {}, picked {} at ({:.1}, {:.1}, {:.1})",
+            fps, stats, hit.name, hit.point.x, hit.point.y, hit.point.z
+        ),
+        None => format!("{:.0} fps, {}", fps, stats),
+    }
+}
+
+/// TAB goes through them in order
+#[derive(Clone, Copy, PartialEq)]
+enum View {
+    Follow,
+    Orbit,
+    Fly,
+    FirstPerson,
+}
+
+impl View {
+    fn next(self) -> View {
+        match self {
+            View::Follow => View::Orbit,
+            View::Orbit => View::Fly,
+            View::Fly => View::FirstPerson,
+            View::FirstPerson => View::Follow,
+        }
+    }
+
+    /// names from `cli::CAMERA_CONTROLLERS`
+    fn from_name(name: &str) -> Option<View> {
+        match name {
+            "follow" => Some(View::Follow),
+            "orbit" => Some(View::Orbit),
+            "fly" => Some(View::Fly),
+            "fps" => Some(View::FirstPerson),
+            _ => None,
+        }
+    }
+}
 
 fn main() {
-    let event_loop = EventLoop::new();
+    let options = match CliOptions::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}\n\n{}", error, USAGE);
+            std::process::exit(2);
+        }
+    };
+    if options.help {
+        println!("{}", USAGE);
+        return;
+    }
+
+    // the defaults are used when the config file does not exist, the command line wins over both
+    let mut config = match WorldConfig::from_file(&options.config) {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("{}, using the default config", error);
+            WorldConfig::default()
+        }
+    };
+    config.width = options.width.unwrap_or(config.width);
+    config.height = options.height.unwrap_or(config.height);
+    if options.fullscreen {
+        config.window_mode = WindowMode::Fullscreen;
+    } else if options.borderless {
+        config.window_mode = WindowMode::Borderless;
+    }
+    if options.headless {
+        config.visible = false;
+    }
+
+    let mut event_loop = EventLoop::new();
     let camera = Camera::new();
-    let mut world = World::new(&event_loop, camera);
+    let mut world = World::with_config(&event_loop, camera, config);
+
+    // O shows a top down view of the whole floor on the top right corner
+    let mut overview = Camera::new()
+        .with_orthographic(80.0)
+        .with_clip_planes(1.0, 200.0)
+        .with_viewport(Viewport::new(0.7, 0.0, 0.3, 0.3))
+        .with_order(1);
+    overview.operations =
+        MatrixOperation::translation(Vector3::new(30.0, 100.0, 30.0)) * Quaternion::rotate_x(90.0);
+    overview.active = false;
+    world.add_camera(String::from("overview"), overview);
+
+    // a security camera over the cubes, shown on a monitor in front of the player block
+    let monitor_target = RenderTarget::new(&world.display, 512, 512);
+    let monitor_material = Material::textured(
+        [1.0, 1.0, 1.0, 1.0],
+        BlendMode::Opaque,
+        monitor_target.texture(),
+    );
+    world.add_render_target(String::from("monitor"), monitor_target);
+    world.add_material(String::from("monitor"), monitor_material.clone());
+
+    let mut security_camera = Camera::new().with_render_target("monitor");
+    security_camera.look_at(
+        Vector3::new(-10.0, 20.0, -10.0),
+        Vector3::new(20.0, 0.0, 20.0),
+    );
+    world.add_camera(String::from("security"), security_camera);
+
+    let fog_color = Vector3::new(0.6, 0.7, 0.8);
+    world.set_fog(Fog::exponential(fog_color, 0.02).with_height_falloff(0.0, 0.1));
+
+    // keys 1 to 5 toggle each pass, gamma starts disabled
+    world.set_post_processing(PostProcessing::with_default_passes(&world.display));
 
     // ITEMS TO DRAW
-    let cube_prefab = Primitive::cube(world.display.clone());
+    let cube_prefab = world.get_prefab("cube").unwrap();
+    let glass_material = Material::new([0.6, 0.8, 1.0, 0.4], BlendMode::Alpha);
+    let glow_material = Material::new([1.0, 0.6, 0.2, 0.8], BlendMode::Additive);
+    world.add_material(String::from("glass"), glass_material.clone());
+    world.add_material(String::from("glow"), glow_material.clone());
 
     let mut cube_instance = Instance::new(cube_prefab.clone());
     cube_instance.set_scale(Vector3::new(1.5, 1.5, 1.5));
     cube_instance.set_translation(Vector3::new(0.0, 0.75, -5.0));
     world.add_instance(String::from("instance1"), cube_instance);
+    // the player block is a capsule as tall as the block, with its feet on the floor
+    let mut player = CharacterController::new(Vector3::new(0.0, 0.0, -5.0), 0.5, 1.5)
+        .with_step_height(0.4)
+        .with_jump_speed(JUMP_SPEED);
 
     for i in 0..100 {
+        // some cubes start in the air and fall on the floor
+        let falling = i % 9 == 0;
+
         let mut cube_instance = Instance::new(cube_prefab.clone());
         cube_instance.set_rotate_y(i as f32 * 37.0);
+        if falling {
+            cube_instance.set_rotate_x(30.0);
+        }
         cube_instance.set_translation(Vector3::new(
             (i % 10) as f32 * 6.0,
-            0.5,
+            if falling { 6.0 } else { 0.5 },
             (i / 10) as f32 * 6.0,
         ));
-        world.add_instance(String::from(format!("cube_{}", i)), cube_instance);
+        match i % 7 {
+            0 => cube_instance.set_material(glass_material.clone()),
+            3 => cube_instance.set_material(glow_material.clone()),
+            _ => (),
+        };
+
+        let name = format!("cube_{}", i);
+        let collider = Collider::from_bounds(cube_prefab.get_bounds());
+        world.add_instance(name.clone(), cube_instance);
+        world.add_rigid_body(
+            name,
+            if falling {
+                RigidBody::dynamic(collider, 1.0)
+            } else {
+                RigidBody::fixed(collider)
+            },
+        );
     }
 
     world.add_instance(
@@ -53,112 +225,328 @@ fn main() {
         Instance::new(cube_prefab.clone()),
     );
 
-    let mut floor = Instance::new(cube_prefab.clone());
-    floor.set_rotate_x(180.0);
-    floor.set_scale(Vector3::new(80.0, 0.1, 80.0));
-    floor.set_translation(Vector3::new(30.0, -0.05, 30.0));
+    let mut monitor = Instance::new(world.get_prefab("quad").unwrap());
+    monitor.set_material(monitor_material);
+    monitor.set_scale(Vector3::new(4.0, 4.0, 1.0));
+    monitor.set_rotate_y(180.0);
+    monitor.set_translation(Vector3::new(-6.0, 3.0, -2.0));
+    world.add_instance(String::from("monitor"), monitor);
+
+    let mut floor = Instance::new(world.get_prefab("plane").unwrap());
+    floor.set_scale(Vector3::new(80.0, 1.0, 80.0));
+    floor.set_translation(Vector3::new(30.0, 0.0, 30.0));
     world.add_instance(String::from("floor"), floor);
+    // the plane has no thickness, so the collider is a thin slab around it
+    world.add_rigid_body(
+        String::from("floor"),
+        RigidBody::fixed(Collider::Box {
+            half_extents: Vector3::new(0.5, 0.005, 0.5),
+        }),
+    );
+
+    // scene files add to the built in scene, "instance1" stays the player block
+    if let Some(path) = &options.scene {
+        let path = Path::new(path);
+        // glTF names are prefixed with the file name, like "robot/arm"
+        let result = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("gltf") | Some("glb") => {
+                let name = path.file_stem().unwrap().to_string_lossy();
+                load_gltf(&mut world, &path.display().to_string(), &name)
+                    .map_err(|error| error.to_string())
+            }
+            _ => world
+                .load_scene_file(&path.display().to_string())
+                .map_err(|error| error.to_string()),
+        };
+        if let Err(error) = result {
+            eprintln!("{}: {}", path.display(), error);
+            std::process::exit(1);
+        }
+    }
+    if let Some(path) = &options.save_scene {
+        match world.save_scene(path) {
+            Ok(()) => println!("saved {}", path),
+            Err(error) => eprintln!("could not save {}: {}", path, error),
+        }
+    }
+    if let Some(path) = &options.export {
+        match world.export(path) {
+            Ok(()) => println!("exported {}", path),
+            Err(error) => eprintln!("could not export {}: {}", path, error),
+        }
+    }
 
     // DRAW STEP
     let mut step = 0;
-    let mut camera_vertical_rotation = 0.0;
-
-    world.set_update(move |device_manager, instances, camera| {
-        let mut front_movement = 0.0;
-        let mut side_movement = 0.0;
-        let mut up_movement = 0.0;
-        let rotate_horizontal = -device_manager.get_last_mouse_movement_x() * MOUSE_SENSIBILITY;
-        let rotate_vertical = device_manager.get_last_mouse_movement_y() * MOUSE_SENSIBILITY;
-
-        for key in device_manager.iter_keys() {
-            match key {
-                VirtualKeyCode::W => front_movement = KEY_MOVEMENT,
-                VirtualKeyCode::S => front_movement = -KEY_MOVEMENT,
-                VirtualKeyCode::A => side_movement = KEY_MOVEMENT,
-                VirtualKeyCode::D => side_movement = -KEY_MOVEMENT,
-                VirtualKeyCode::Q => up_movement = KEY_MOVEMENT,
-                VirtualKeyCode::E => up_movement = -KEY_MOVEMENT,
-                _ => (),
+    // TAB switches between following the player block, orbiting around where it was,
+    // flying freely and looking through the player block eyes
+    let mut follow = FollowCamera::new(10.0, 0.0);
+    let mut orbit = OrbitCamera::new(player.center(), 12.0, 25.0, -90.0);
+    let mut fly = FlyCamera::new(player.center(), 0.0, 0.0);
+    let mut first_person = FpsCamera::new(player.center(), 0.0)
+        .with_eye_height(1.3)
+        .with_settings(MovementSettings {
+            speed: MOVEMENT_SPEED,
+            mouse_sensibility: MOUSE_SENSIBILITY,
+            min_pitch: -20.0,
+            max_pitch: 40.0,
+            ..MovementSettings::default()
+        });
+    let mut view = options
+        .camera
+        .as_deref()
+        .and_then(View::from_name)
+        .unwrap_or(View::Follow);
+    // the controller being switched to starts from where the camera is
+    let mut entering_view = true;
+
+    world.set_update(
+        move |device_manager, instances, physics, cameras, frame_time| {
+            if device_manager.was_key_pressed(VirtualKeyCode::O) {
+                let overview = cameras.get_mut("overview").unwrap();
+                overview.active = !overview.active;
+            }
+
+            let camera = cameras.get_mut(MAIN_CAMERA).unwrap();
+            let mut front_movement = 0.0;
+            let mut side_movement = 0.0;
+            let mut jump = false;
+
+            if device_manager.was_key_pressed(VirtualKeyCode::Tab) {
+                view = view.next();
+                entering_view = true;
+            }
+
+            if entering_view {
+                entering_view = false;
+                match view {
+                    View::Orbit => orbit.snap_to(player.center()),
+                    View::Fly => fly.look_along(
+                        camera.operations.get_position(),
+                        camera.operations.get_forward_vector(),
+                    ),
+                    View::FirstPerson => {
+                        let forward = instances.get("instance1").unwrap().get_forward_vector();
+                        first_person.look_along(player.center(), forward);
+                    }
+                    View::Follow => {
+                        follow.snap_to(instances.get("instance1").unwrap().get_transform())
+                    }
+                }
+            }
+
+            // only the follow view turns the player block with the mouse
+            let (rotate_horizontal, rotate_vertical) = if view == View::Follow {
+                (
+                    -device_manager.get_last_mouse_movement_x() * MOUSE_SENSIBILITY,
+                    device_manager.get_last_mouse_movement_y() * MOUSE_SENSIBILITY,
+                )
+            } else {
+                (0.0, 0.0)
             };
-        }
 
-        // MOVING BLOCK ON SPHERICAL COORDINATES
-        step = step + 1;
-        step = step % 360;
-
-        instances
-            .entry(String::from("instance1"))
-            .and_modify(|instance| {
-                instance.set_rotate_y(rotate_horizontal);
-                instance.add_front_translation(front_movement);
-                instance.add_side_translation(side_movement);
-                instance.add_up_translation(up_movement);
-            });
-
-        let parent = instances.get("instance1").unwrap().clone();
-
-        camera_vertical_rotation += rotate_vertical;
-        camera_vertical_rotation = clamp(camera_vertical_rotation, -20.0, 40.0);
-
-        let mut camera_instance = parent.clone();
-        camera_instance.set_rotate_x(camera_vertical_rotation);
-        camera_instance.add_front_translation(-10.0);
-        camera.set_parent(&camera_instance);
-
-        instances
-            .entry(String::from("instance2"))
-            .and_modify(|instance| {
-                instance.reset_transform();
-                instance.set_scale(Vector3::new(0.5, 0.5, 0.5));
-                instance.set_parent(&parent);
-                instance.set_translation(
-                    SphereVector::new(1.5, -20.0, (step * 5) as f32).to_cartesian(),
-                );
-            });
-
-        instances
-            .entry(String::from("instance3"))
-            .and_modify(|instance| {
-                instance.reset_transform();
-                instance.set_scale(Vector3::new(0.5, 0.5, 0.5));
-                instance.set_translation(Vector3::new(0.0, 0.0, -5.0));
-                instance.set_translation(
-                    SphereVector::new(2.0, -45.0, step as f32 + 90.0).to_cartesian(),
-                );
-            });
-    });
+            // the fly camera takes the keys, the player block stays where it is
+            for key in device_manager.iter_keys().filter(|_| view != View::Fly) {
+                match key {
+                    VirtualKeyCode::W => front_movement = MOVEMENT_SPEED,
+                    VirtualKeyCode::S => front_movement = -MOVEMENT_SPEED,
+                    VirtualKeyCode::A => side_movement = MOVEMENT_SPEED,
+                    VirtualKeyCode::D => side_movement = -MOVEMENT_SPEED,
+                    VirtualKeyCode::Q => jump = true,
+                    _ => (),
+                };
+            }
+
+            // MOVING BLOCK ON SPHERICAL COORDINATES
+            step = step + 1;
+            step = step % 360;
+
+            // the player block walks through the character controller, so it collides instead of going through things
+            instances
+                .entry(String::from("instance1"))
+                .and_modify(|instance| {
+                    if view == View::FirstPerson {
+                        first_person.update_character(
+                            device_manager,
+                            &mut player,
+                            physics,
+                            camera,
+                            frame_time,
+                        );
+
+                        instance.reset_transform();
+                        instance.set_scale(Vector3::new(1.5, 1.5, 1.5));
+                        instance.set_rotate_y(first_person.get_yaw());
+                    } else {
+                        instance.set_rotate_y(rotate_horizontal);
+
+                        let movement = instance.get_forward_vector() * front_movement
+                            + instance.get_side_vector() * side_movement;
+                        player.update(physics, movement, jump, frame_time);
+                    }
+
+                    instance.set_position(player.center());
+                });
+
+            let parent = instances.get("instance1").unwrap().clone();
+
+            follow.pitch = clamp(follow.pitch + rotate_vertical, -20.0, 40.0);
+
+            match view {
+                View::Follow => follow.update(parent.get_transform(), physics, camera, frame_time),
+                View::Orbit => orbit.update(device_manager, camera, frame_time),
+                View::Fly => fly.update(device_manager, camera, frame_time),
+                // already placed while moving the player block
+                View::FirstPerson => (),
+            }
+
+            instances
+                .entry(String::from("instance2"))
+                .and_modify(|instance| {
+                    instance.reset_transform();
+                    instance.set_scale(Vector3::new(0.5, 0.5, 0.5));
+                    instance.set_parent(&parent);
+                    instance.set_translation(
+                        SphereVector::new(1.5, -20.0, (step * 5) as f32).to_cartesian(),
+                    );
+                });
+
+            instances
+                .entry(String::from("instance3"))
+                .and_modify(|instance| {
+                    instance.reset_transform();
+                    instance.set_scale(Vector3::new(0.5, 0.5, 0.5));
+                    instance.set_translation(Vector3::new(0.0, 0.0, -5.0));
+                    instance.set_translation(
+                        SphereVector::new(2.0, -45.0, step as f32 + 90.0).to_cartesian(),
+                    );
+                });
+        },
+    );
 
     // WIN EVENT LOOP
     let mut next_frame_time = std::time::Instant::now();
+    // the title shows the frame rate and culling once a second, and the last picked instance
+    let mut title_time = std::time::Instant::now();
+    let mut title_frames = 0;
+    let mut fps = 0.0;
+    let mut picked: Option<PickResult> = None;
+    let mut cursor_position = (0.0, 0.0);
+    let frame_limit = options.frame_limit();
+    let mut frame = 0;
+    let mut benchmark = Benchmark::new();
 
-    event_loop.run(move |event, _, control_flow| {
-        if next_frame_time.elapsed() > std::time::Duration::from_nanos(16_666_667) {
-            next_frame_time = std::time::Instant::now();
-            world.draw_update();
-        }
+    // run_return gives the event loop back, the context is rebuilt outside of it
+    loop {
+        event_loop.run_return(|event, _, control_flow| {
+            if world.needs_rebuild() {
+                *control_flow = ControlFlow::Exit;
+                return;
+            }
 
-        match event {
-            glutin::event::Event::WindowEvent { event, .. } => match event {
-                glutin::event::WindowEvent::CloseRequested => {
-                    *control_flow = ControlFlow::Exit;
-                    return;
-                }
-                glutin::event::WindowEvent::Resized(new_size) => {
-                    world.change_perspective_ratio(new_size.width as f32 / new_size.height as f32)
+            if options.unthrottled()
+                || next_frame_time.elapsed() > std::time::Duration::from_secs_f32(FRAME_TIME)
+            {
+                let frame_time = next_frame_time.elapsed().as_secs_f32();
+                next_frame_time = std::time::Instant::now();
+                world.draw_update();
+
+                frame += 1;
+                benchmark.record(frame_time, world.frame_stats());
+
+                title_frames += 1;
+                let elapsed = title_time.elapsed().as_secs_f32();
+                if elapsed >= 1.0 {
+                    fps = title_frames as f32 / elapsed;
+                    world.set_title_suffix(&title_text(fps, world.frame_stats(), &picked));
+                    title_time = std::time::Instant::now();
+                    title_frames = 0;
                 }
-                glutin::event::WindowEvent::KeyboardInput { input, .. } => {
-                    world.update_key_manager(&input)
+
+                let last_frame = frame_limit == Some(frame);
+                if let Some(path) = options.screenshot_path(frame, last_frame) {
+                    match world.screenshot(&path) {
+                        Ok(()) => println!("saved {}", path),
+                        Err(error) => eprintln!("could not save {}: {}", path, error),
+                    }
                 }
-                _ => (),
-            },
-            glutin::event::Event::DeviceEvent { event, .. } => match event {
-                glutin::event::DeviceEvent::MouseMotion { delta } => {
-                    world.update_mouse_motion(delta)
+                if last_frame {
+                    *control_flow = ControlFlow::Exit;
+                    return;
                 }
-                glutin::event::DeviceEvent::MouseWheel { delta } => world.update_mouse_wheel(delta),
+            }
+
+            match event {
+                glutin::event::Event::WindowEvent { event, .. } => match event {
+                    glutin::event::WindowEvent::CloseRequested => {
+                        *control_flow = ControlFlow::Exit;
+                        return;
+                    }
+                    glutin::event::WindowEvent::KeyboardInput { input, .. } => {
+                        world.update_key_manager(&input);
+
+                        if input.state == ElementState::Pressed {
+                            match input.virtual_keycode {
+                                Some(VirtualKeyCode::F11) => world.toggle_fullscreen(),
+                                Some(VirtualKeyCode::F10) => {
+                                    let vsync = !world.config().vsync;
+                                    world.set_vsync(vsync);
+                                }
+                                _ => (),
+                            }
+
+                            let pass = match input.virtual_keycode {
+                                Some(VirtualKeyCode::Key1) => Some("bloom"),
+                                Some(VirtualKeyCode::Key2) => Some("tone_mapping"),
+                                Some(VirtualKeyCode::Key3) => Some("gamma"),
+                                Some(VirtualKeyCode::Key4) => Some("fxaa"),
+                                Some(VirtualKeyCode::Key5) => Some("vignette"),
+                                _ => None,
+                            };
+
+                            if let (Some(pass), Some(post_processing)) =
+                                (pass, world.post_processing_mut())
+                            {
+                                post_processing.toggle(pass);
+                            }
+                        }
+                    }
+                    glutin::event::WindowEvent::CursorMoved { position, .. } => {
+                        cursor_position = (position.x as f32, position.y as f32)
+                    }
+                    glutin::event::WindowEvent::MouseInput { state, button, .. } => {
+                        world.update_mouse_buttons(button, state);
+
+                        if state == ElementState::Pressed && button == MouseButton::Left {
+                            picked = world.pick(cursor_position.0, cursor_position.1);
+                            world.set_title_suffix(&title_text(fps, world.frame_stats(), &picked));
+                        }
+                    }
+                    _ => (),
+                },
+                glutin::event::Event::DeviceEvent { event, .. } => match event {
+                    glutin::event::DeviceEvent::MouseMotion { delta } => {
+                        world.update_mouse_motion(delta)
+                    }
+                    glutin::event::DeviceEvent::MouseWheel { delta } => {
+                        world.update_mouse_wheel(delta)
+                    }
+                    _ => (),
+                },
                 _ => (),
-            },
-            _ => (),
+            }
+        });
+
+        if world.needs_rebuild() {
+            world.rebuild(&event_loop);
+        } else {
+            break;
         }
-    });
+    }
+
+    if options.benchmark {
+        if let Some(report) = benchmark.report() {
+            println!("{}", report);
+        }
+    }
 }
@@ -0,0 +1,92 @@
+use glium::backend::glutin::Display;
+use glium::draw_parameters::{Blend, BlendingFunction, LinearBlendingFactor};
+use glium::texture::RawImage2d;
+use glium::Texture2d;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum BlendMode {
+    Opaque,
+    Alpha,
+    Additive,
+    /// expects the color to be already multiplied by its alpha
+    Premultiplied,
+}
+
+/// reference: https://www.khronos.org/opengl/wiki/Blending
+impl BlendMode {
+    pub fn is_transparent(self) -> bool {
+        self != BlendMode::Opaque
+    }
+
+    pub fn to_blend(self) -> Blend {
+        let (source, destination) = match self {
+            BlendMode::Opaque => return Blend::default(),
+            BlendMode::Alpha => return Blend::alpha_blending(),
+            BlendMode::Additive => (LinearBlendingFactor::SourceAlpha, LinearBlendingFactor::One),
+            BlendMode::Premultiplied => (
+                LinearBlendingFactor::One,
+                LinearBlendingFactor::OneMinusSourceAlpha,
+            ),
+        };
+
+        Blend {
+            color: BlendingFunction::Addition {
+                source,
+                destination,
+            },
+            alpha: BlendingFunction::Addition {
+                source,
+                destination,
+            },
+            constant_value: (0.0, 0.0, 0.0, 0.0),
+        }
+    }
+}
+
+/// `color` multiplies the vertex colors, so the same prefab can be drawn see-through.
+/// The texture, when there is one, multiplies them too
+pub struct Material {
+    pub color: [f32; 4],
+    pub blend_mode: BlendMode,
+    pub texture: Option<Arc<Texture2d>>,
+}
+
+/// any format the image crate reads, flipped so the first row is the bottom one like OpenGL expects
+pub fn load_texture(display: &Display, path: &str) -> image::ImageResult<Arc<Texture2d>> {
+    let image = image::open(path)?.to_rgba8();
+    let dimensions = image.dimensions();
+    let image = RawImage2d::from_raw_rgba_reversed(&image.into_raw(), dimensions);
+
+    Ok(Arc::new(Texture2d::new(display, image).unwrap()))
+}
+
+#[allow(dead_code)]
+impl Material {
+    pub fn new(color: [f32; 4], blend_mode: BlendMode) -> Arc<Self> {
+        Arc::new(Material {
+            color,
+            blend_mode,
+            texture: None,
+        })
+    }
+
+    /// the texture may be a `RenderTarget` one, drawn earlier on the same frame
+    pub fn textured(color: [f32; 4], blend_mode: BlendMode, texture: Arc<Texture2d>) -> Arc<Self> {
+        Arc::new(Material {
+            color,
+            blend_mode,
+            texture: Some(texture),
+        })
+    }
+
+    pub fn opaque() -> Arc<Self> {
+        Material::new([1.0, 1.0, 1.0, 1.0], BlendMode::Opaque)
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        self.blend_mode.is_transparent()
+    }
+}
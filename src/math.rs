@@ -1,4 +1,5 @@
 use glium::uniforms::{AsUniformValue, UniformValue};
+use serde::{Deserialize, Serialize};
 use std::ops;
 
 #[derive(Debug, Clone, Copy)]
@@ -83,6 +84,87 @@ impl Matrix4 {
     pub fn get_position(self) -> Vector3 {
         Vector3::new(self.data[3], self.data[7], self.data[11])
     }
+
+    pub fn get_row(self, row: usize) -> [f32; 4] {
+        [
+            self.data[row * 4],
+            self.data[row * 4 + 1],
+            self.data[row * 4 + 2],
+            self.data[row * 4 + 3],
+        ]
+    }
+
+    /// applies the whole affine transform (w = 1.0)
+    pub fn transform_point(self, point: Vector3) -> Vector3 {
+        self.transform_vector(point) + self.get_position()
+    }
+
+    /// applies only rotation and scale (w = 0.0)
+    pub fn transform_vector(self, vector: Vector3) -> Vector3 {
+        Vector3::new(
+            self.data[0] * vector.x + self.data[1] * vector.y + self.data[2] * vector.z,
+            self.data[4] * vector.x + self.data[5] * vector.y + self.data[6] * vector.z,
+            self.data[8] * vector.x + self.data[9] * vector.y + self.data[10] * vector.z,
+        )
+    }
+
+    /// applies a projective transform and divides by w, like the path from clip to device coordinates
+    #[rustfmt::skip]
+    pub fn project_point(self, point: Vector3) -> Vector3 {
+        let [x, y, z, w] = [
+            self.data[0] * point.x + self.data[1] * point.y + self.data[2] * point.z + self.data[3],
+            self.data[4] * point.x + self.data[5] * point.y + self.data[6] * point.z + self.data[7],
+            self.data[8] * point.x + self.data[9] * point.y + self.data[10] * point.z + self.data[11],
+            self.data[12] * point.x + self.data[13] * point.y + self.data[14] * point.z + self.data[15],
+        ];
+
+        Vector3::new(x / w, y / w, z / w)
+    }
+
+    /// cofactor expansion, None for singular matrices
+    /// reference: MESA implementation of gluInvertMatrix
+    #[rustfmt::skip]
+    pub fn inverse(self) -> Option<Matrix4> {
+        let m = self.data;
+        let mut inv = [0.0; 16];
+
+        inv[0] = m[5] * m[10] * m[15] - m[5] * m[11] * m[14] - m[9] * m[6] * m[15] + m[9] * m[7] * m[14] + m[13] * m[6] * m[11] - m[13] * m[7] * m[10];
+        inv[4] = -m[4] * m[10] * m[15] + m[4] * m[11] * m[14] + m[8] * m[6] * m[15] - m[8] * m[7] * m[14] - m[12] * m[6] * m[11] + m[12] * m[7] * m[10];
+        inv[8] = m[4] * m[9] * m[15] - m[4] * m[11] * m[13] - m[8] * m[5] * m[15] + m[8] * m[7] * m[13] + m[12] * m[5] * m[11] - m[12] * m[7] * m[9];
+        inv[12] = -m[4] * m[9] * m[14] + m[4] * m[10] * m[13] + m[8] * m[5] * m[14] - m[8] * m[6] * m[13] - m[12] * m[5] * m[10] + m[12] * m[6] * m[9];
+        inv[1] = -m[1] * m[10] * m[15] + m[1] * m[11] * m[14] + m[9] * m[2] * m[15] - m[9] * m[3] * m[14] - m[13] * m[2] * m[11] + m[13] * m[3] * m[10];
+        inv[5] = m[0] * m[10] * m[15] - m[0] * m[11] * m[14] - m[8] * m[2] * m[15] + m[8] * m[3] * m[14] + m[12] * m[2] * m[11] - m[12] * m[3] * m[10];
+        inv[9] = -m[0] * m[9] * m[15] + m[0] * m[11] * m[13] + m[8] * m[1] * m[15] - m[8] * m[3] * m[13] - m[12] * m[1] * m[11] + m[12] * m[3] * m[9];
+        inv[13] = m[0] * m[9] * m[14] - m[0] * m[10] * m[13] - m[8] * m[1] * m[14] + m[8] * m[2] * m[13] + m[12] * m[1] * m[10] - m[12] * m[2] * m[9];
+        inv[2] = m[1] * m[6] * m[15] - m[1] * m[7] * m[14] - m[5] * m[2] * m[15] + m[5] * m[3] * m[14] + m[13] * m[2] * m[7] - m[13] * m[3] * m[6];
+        inv[6] = -m[0] * m[6] * m[15] + m[0] * m[7] * m[14] + m[4] * m[2] * m[15] - m[4] * m[3] * m[14] - m[12] * m[2] * m[7] + m[12] * m[3] * m[6];
+        inv[10] = m[0] * m[5] * m[15] - m[0] * m[7] * m[13] - m[4] * m[1] * m[15] + m[4] * m[3] * m[13] + m[12] * m[1] * m[7] - m[12] * m[3] * m[5];
+        inv[14] = -m[0] * m[5] * m[14] + m[0] * m[6] * m[13] + m[4] * m[1] * m[14] - m[4] * m[2] * m[13] - m[12] * m[1] * m[6] + m[12] * m[2] * m[5];
+        inv[3] = -m[1] * m[6] * m[11] + m[1] * m[7] * m[10] + m[5] * m[2] * m[11] - m[5] * m[3] * m[10] - m[9] * m[2] * m[7] + m[9] * m[3] * m[6];
+        inv[7] = m[0] * m[6] * m[11] - m[0] * m[7] * m[10] - m[4] * m[2] * m[11] + m[4] * m[3] * m[10] + m[8] * m[2] * m[7] - m[8] * m[3] * m[6];
+        inv[11] = -m[0] * m[5] * m[11] + m[0] * m[7] * m[9] + m[4] * m[1] * m[11] - m[4] * m[3] * m[9] - m[8] * m[1] * m[7] + m[8] * m[3] * m[5];
+        inv[15] = m[0] * m[5] * m[10] - m[0] * m[6] * m[9] - m[4] * m[1] * m[10] + m[4] * m[2] * m[9] + m[8] * m[1] * m[6] - m[8] * m[2] * m[5];
+
+        let determinant = m[0] * inv[0] + m[1] * inv[4] + m[2] * inv[8] + m[3] * inv[12];
+        if determinant.abs() < f32::EPSILON {
+            return None;
+        }
+
+        let inverse_determinant = 1.0 / determinant;
+        for value in inv.iter_mut() {
+            *value *= inverse_determinant;
+        }
+
+        Some(Matrix4::from(inv))
+    }
+
+    /// the biggest scale among the three axis, useful to grow bounding spheres
+    pub fn get_max_scale(self) -> f32 {
+        self.get_side_vector()
+            .length()
+            .max(self.get_up_vector().length())
+            .max(self.get_forward_vector().length())
+    }
 }
 
 impl PartialEq for Matrix4 {
@@ -91,7 +173,7 @@ impl PartialEq for Matrix4 {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Vector3 {
     pub x: f32,
     pub y: f32,
@@ -114,6 +196,14 @@ impl Vector3 {
         result
     }
 
+    pub fn dot(self, other: Vector3) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn length(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
     pub fn cross(self, other: Vector3) -> Self {
         Vector3::new(
             self.y * other.z - self.z * other.y,
@@ -122,6 +212,22 @@ impl Vector3 {
         )
     }
 
+    pub fn min(self, other: Vector3) -> Self {
+        Vector3::new(
+            self.x.min(other.x),
+            self.y.min(other.y),
+            self.z.min(other.z),
+        )
+    }
+
+    pub fn max(self, other: Vector3) -> Self {
+        Vector3::new(
+            self.x.max(other.x),
+            self.y.max(other.y),
+            self.z.max(other.z),
+        )
+    }
+
     pub fn to_array(self) -> [f32; 3] {
         [self.x, self.y, self.z]
     }
@@ -149,6 +255,14 @@ impl ops::Sub<Vector3> for Vector3 {
     }
 }
 
+impl ops::Neg for Vector3 {
+    type Output = Vector3;
+
+    fn neg(self) -> Self::Output {
+        Vector3::new(-self.x, -self.y, -self.z)
+    }
+}
+
 impl ops::Mul<f32> for Vector3 {
     type Output = Vector3;
 
@@ -164,12 +278,20 @@ impl AsUniformValue for Vector3 {
     }
 }
 
+#[derive(Debug, Clone, Copy)]
 pub struct Quaternion {
     data: [f32; 4],
 }
 
 /// reference: https://paroj.github.io/gltut/Positioning/Tut08%20Quaternions.html
+#[allow(dead_code)]
 impl Quaternion {
+    pub fn identity() -> Self {
+        Quaternion {
+            data: [0.0, 0.0, 0.0, 1.0],
+        }
+    }
+
     pub fn new(vector: Vector3, angle: f32) -> Self {
         let vector = vector.normalized();
         let angle = degree_to_radians(angle);
@@ -208,6 +330,108 @@ impl Quaternion {
     pub fn rotate_z(angle: f32) -> Matrix4 {
         Quaternion::new(Vector3::new(0.0, 0.0, 1.0), angle).to_matrix()
     }
+
+    /// expects a pure rotation on the upper 3x3, scale must be removed first.
+    /// reference: https://www.euclideanspace.com/maths/geometry/rotations/conversions/matrixToQuaternion/
+    pub fn from_rotation_matrix(matrix: Matrix4) -> Self {
+        let m = |row: usize, column: usize| matrix.data[row * 4 + column];
+        let trace = m(0, 0) + m(1, 1) + m(2, 2);
+
+        let data = if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            [
+                (m(2, 1) - m(1, 2)) / s,
+                (m(0, 2) - m(2, 0)) / s,
+                (m(1, 0) - m(0, 1)) / s,
+                0.25 * s,
+            ]
+        } else if m(0, 0) > m(1, 1) && m(0, 0) > m(2, 2) {
+            let s = (1.0 + m(0, 0) - m(1, 1) - m(2, 2)).sqrt() * 2.0;
+            [
+                0.25 * s,
+                (m(0, 1) + m(1, 0)) / s,
+                (m(0, 2) + m(2, 0)) / s,
+                (m(2, 1) - m(1, 2)) / s,
+            ]
+        } else if m(1, 1) > m(2, 2) {
+            let s = (1.0 + m(1, 1) - m(0, 0) - m(2, 2)).sqrt() * 2.0;
+            [
+                (m(0, 1) + m(1, 0)) / s,
+                0.25 * s,
+                (m(1, 2) + m(2, 1)) / s,
+                (m(0, 2) - m(2, 0)) / s,
+            ]
+        } else {
+            let s = (1.0 + m(2, 2) - m(0, 0) - m(1, 1)).sqrt() * 2.0;
+            [
+                (m(0, 2) + m(2, 0)) / s,
+                (m(1, 2) + m(2, 1)) / s,
+                0.25 * s,
+                (m(1, 0) - m(0, 1)) / s,
+            ]
+        };
+
+        Quaternion { data }.normalized()
+    }
+
+    pub fn normalized(self) -> Self {
+        let length = self
+            .data
+            .iter()
+            .map(|value| value * value)
+            .sum::<f32>()
+            .sqrt();
+
+        if length == 0.0 {
+            return Quaternion::identity();
+        }
+
+        Quaternion {
+            data: [
+                self.data[0] / length,
+                self.data[1] / length,
+                self.data[2] / length,
+                self.data[3] / length,
+            ],
+        }
+    }
+
+    /// the inverse rotation, for unit quaternions
+    pub fn conjugate(self) -> Self {
+        Quaternion {
+            data: [-self.data[0], -self.data[1], -self.data[2], self.data[3]],
+        }
+    }
+
+    pub fn rotate_vector(self, vector: Vector3) -> Vector3 {
+        let axis = Vector3::new(self.data[0], self.data[1], self.data[2]);
+        let twice_cross = axis.cross(vector) * 2.0;
+
+        vector + twice_cross * self.data[3] + axis.cross(twice_cross)
+    }
+
+    /// advances the rotation by a world space angular velocity, in radians per second.
+    /// reference: https://www.euclideanspace.com/physics/kinematics/angularvelocity/QuaternionDifferentiation2.pdf
+    pub fn integrate(self, angular_velocity: Vector3, dt: f32) -> Self {
+        let spin = Quaternion {
+            data: [
+                angular_velocity.x,
+                angular_velocity.y,
+                angular_velocity.z,
+                0.0,
+            ],
+        } * self;
+
+        Quaternion {
+            data: [
+                self.data[0] + 0.5 * dt * spin.data[0],
+                self.data[1] + 0.5 * dt * spin.data[1],
+                self.data[2] + 0.5 * dt * spin.data[2],
+                self.data[3] + 0.5 * dt * spin.data[3],
+            ],
+        }
+        .normalized()
+    }
 }
 
 impl ops::Mul<Quaternion> for Quaternion {
@@ -237,6 +461,559 @@ impl ops::Mul<Quaternion> for Quaternion {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vector3,
+    pub max: Vector3,
+}
+
+#[allow(dead_code)]
+impl Aabb {
+    pub fn new(min: Vector3, max: Vector3) -> Self {
+        Aabb { min, max }
+    }
+
+    pub fn from_points(points: &[Vector3]) -> Self {
+        let first = points
+            .first()
+            .copied()
+            .unwrap_or(Vector3::new(0.0, 0.0, 0.0));
+
+        points.iter().fold(Aabb::new(first, first), |aabb, point| {
+            Aabb::new(aabb.min.min(*point), aabb.max.max(*point))
+        })
+    }
+
+    pub fn center(&self) -> Vector3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn half_extents(&self) -> Vector3 {
+        (self.max - self.min) * 0.5
+    }
+
+    pub fn union(&self, other: &Aabb) -> Self {
+        Aabb::new(self.min.min(other.min), self.max.max(other.max))
+    }
+
+    pub fn expanded(&self, margin: f32) -> Self {
+        let margin = Vector3::new(margin, margin, margin);
+        Aabb::new(self.min - margin, self.max + margin)
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        let size = self.max - self.min;
+        2.0 * (size.x * size.y + size.y * size.z + size.z * size.x)
+    }
+
+    pub fn contains(&self, other: &Aabb) -> bool {
+        self.min.x <= other.min.x
+            && self.min.y <= other.min.y
+            && self.min.z <= other.min.z
+            && self.max.x >= other.max.x
+            && self.max.y >= other.max.y
+            && self.max.z >= other.max.z
+    }
+
+    pub fn intersects_aabb(&self, other: &Aabb) -> bool {
+        self.min.x <= other.max.x
+            && self.max.x >= other.min.x
+            && self.min.y <= other.max.y
+            && self.max.y >= other.min.y
+            && self.min.z <= other.max.z
+            && self.max.z >= other.min.z
+    }
+
+    pub fn closest_point(&self, point: Vector3) -> Vector3 {
+        point.max(self.min).min(self.max)
+    }
+
+    pub fn contains_point(&self, point: Vector3) -> bool {
+        self.closest_point(point) == point
+    }
+
+    /// reference: Graphics Gems, "Transforming Axis-Aligned Bounding Boxes" (James Arvo)
+    #[rustfmt::skip]
+    pub fn transform(&self, matrix: Matrix4) -> Self {
+        let center = matrix.transform_point(self.center());
+        let extents = self.half_extents();
+        let [m0, m1, m2, _] = matrix.get_row(0);
+        let [m4, m5, m6, _] = matrix.get_row(1);
+        let [m8, m9, m10, _] = matrix.get_row(2);
+
+        let extents = Vector3::new(
+            m0.abs() * extents.x + m1.abs() * extents.y + m2.abs() * extents.z,
+            m4.abs() * extents.x + m5.abs() * extents.y + m6.abs() * extents.z,
+            m8.abs() * extents.x + m9.abs() * extents.y + m10.abs() * extents.z,
+        );
+
+        Aabb::new(center - extents, center + extents)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sphere {
+    pub center: Vector3,
+    pub radius: f32,
+}
+
+#[allow(dead_code)]
+impl Sphere {
+    pub fn new(center: Vector3, radius: f32) -> Self {
+        Sphere { center, radius }
+    }
+
+    /// not the minimal sphere, but centered on the bounding box, which is good enough for culling
+    pub fn from_points(points: &[Vector3]) -> Self {
+        let center = Aabb::from_points(points).center();
+        let radius = points.iter().fold(0.0, |radius: f32, point| {
+            radius.max((*point - center).length())
+        });
+
+        Sphere::new(center, radius)
+    }
+
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        let closest = aabb.closest_point(self.center);
+        (closest - self.center).length() <= self.radius
+    }
+
+    pub fn intersects_sphere(&self, other: &Sphere) -> bool {
+        (other.center - self.center).length() <= self.radius + other.radius
+    }
+
+    pub fn contains_point(&self, point: Vector3) -> bool {
+        (point - self.center).length() <= self.radius
+    }
+
+    /// points inside the sphere are their own closest point
+    pub fn closest_point(&self, point: Vector3) -> Vector3 {
+        let offset = point - self.center;
+        let distance = offset.length();
+
+        if distance <= self.radius {
+            point
+        } else {
+            self.center + offset * (self.radius / distance)
+        }
+    }
+
+    pub fn transform(&self, matrix: Matrix4) -> Self {
+        Sphere::new(
+            matrix.transform_point(self.center),
+            self.radius * matrix.get_max_scale(),
+        )
+    }
+}
+
+/// `direction` is always normalized, so distances along the ray are in world units
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    pub origin: Vector3,
+    pub direction: Vector3,
+}
+
+#[allow(dead_code)]
+impl Ray {
+    pub fn new(origin: Vector3, direction: Vector3) -> Self {
+        Ray {
+            origin,
+            direction: direction.normalized(),
+        }
+    }
+
+    pub fn point_at(&self, distance: f32) -> Vector3 {
+        self.origin + self.direction * distance
+    }
+
+    /// slab method, returns the entry distance (0.0 when the ray starts inside the box)
+    /// reference: https://tavianator.com/2011/ray_box.html
+    pub fn intersect_aabb(&self, aabb: &Aabb) -> Option<f32> {
+        let mut near = 0.0_f32;
+        let mut far = f32::INFINITY;
+
+        for axis in 0..3 {
+            let origin = self.origin.to_array()[axis];
+            let direction = self.direction.to_array()[axis];
+            let min = aabb.min.to_array()[axis];
+            let max = aabb.max.to_array()[axis];
+
+            if direction.abs() < f32::EPSILON {
+                if origin < min || origin > max {
+                    return None;
+                }
+            } else {
+                let inverse = 1.0 / direction;
+                let t1 = (min - origin) * inverse;
+                let t2 = (max - origin) * inverse;
+
+                near = near.max(t1.min(t2));
+                far = far.min(t1.max(t2));
+            }
+        }
+
+        if near <= far {
+            Some(near)
+        } else {
+            None
+        }
+    }
+
+    pub fn closest_point(&self, point: Vector3) -> Vector3 {
+        self.point_at((point - self.origin).dot(self.direction).max(0.0))
+    }
+
+    /// planes are hit from both sides
+    pub fn intersect_plane(&self, plane: &Plane) -> Option<f32> {
+        let denominator = plane.normal.dot(self.direction);
+        if denominator.abs() < f32::EPSILON {
+            return None;
+        }
+
+        let distance = -plane.signed_distance(self.origin) / denominator;
+        if distance >= 0.0 {
+            Some(distance)
+        } else {
+            None
+        }
+    }
+
+    /// returns 0.0 when the ray starts inside the sphere
+    /// reference: Real-Time Collision Detection (Christer Ericson), 5.3.2
+    pub fn intersect_sphere(&self, sphere: &Sphere) -> Option<f32> {
+        let m = self.origin - sphere.center;
+        let b = m.dot(self.direction);
+        let c = m.dot(m) - sphere.radius * sphere.radius;
+
+        // outside and pointing away
+        if c > 0.0 && b > 0.0 {
+            return None;
+        }
+
+        let discriminant = b * b - c;
+        if discriminant < 0.0 {
+            return None;
+        }
+
+        Some((-b - discriminant.sqrt()).max(0.0))
+    }
+
+    /// slab test on the box own axes
+    pub fn intersect_obb(&self, obb: &Obb) -> Option<f32> {
+        let offset = self.origin - obb.center;
+        let local = Ray {
+            origin: Vector3::new(
+                offset.dot(obb.axes[0]),
+                offset.dot(obb.axes[1]),
+                offset.dot(obb.axes[2]),
+            ),
+            direction: Vector3::new(
+                self.direction.dot(obb.axes[0]),
+                self.direction.dot(obb.axes[1]),
+                self.direction.dot(obb.axes[2]),
+            ),
+        };
+
+        local.intersect_aabb(&Aabb::new(-obb.half_extents, obb.half_extents))
+    }
+
+    /// Möller–Trumbore, both faces are hit
+    /// reference: https://www.scratchapixel.com/lessons/3d-basic-rendering/ray-tracing-rendering-a-triangle/moller-trumbore-ray-triangle-intersection
+    pub fn intersect_triangle(&self, triangle: &Triangle) -> Option<f32> {
+        let Triangle { a, b, c } = *triangle;
+        let edge1 = b - a;
+        let edge2 = c - a;
+        let p = self.direction.cross(edge2);
+        let determinant = edge1.dot(p);
+
+        if determinant.abs() < f32::EPSILON {
+            return None;
+        }
+
+        let inverse_determinant = 1.0 / determinant;
+        let t = self.origin - a;
+        let u = t.dot(p) * inverse_determinant;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let q = t.cross(edge1);
+        let v = self.direction.dot(q) * inverse_determinant;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let distance = edge2.dot(q) * inverse_determinant;
+        if distance >= 0.0 {
+            Some(distance)
+        } else {
+            None
+        }
+    }
+}
+
+/// points with normal.dot(point) + distance >= 0.0 are in front of the plane
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Plane {
+    pub normal: Vector3,
+    pub distance: f32,
+}
+
+#[allow(dead_code)]
+impl Plane {
+    pub fn new(normal: Vector3, distance: f32) -> Self {
+        Plane { normal, distance }
+    }
+
+    /// builds the plane ax + by + cz + d = 0 with a normalized normal
+    pub fn from_coefficients(a: f32, b: f32, c: f32, d: f32) -> Self {
+        let normal = Vector3::new(a, b, c);
+        let length = normal.length();
+
+        Plane::new(normal * (1.0 / length), d / length)
+    }
+
+    /// the plane through `point` facing `normal`
+    pub fn from_point_normal(point: Vector3, normal: Vector3) -> Self {
+        let normal = normal.normalized();
+        Plane::new(normal, -normal.dot(point))
+    }
+
+    pub fn signed_distance(&self, point: Vector3) -> f32 {
+        self.normal.dot(point) + self.distance
+    }
+
+    pub fn closest_point(&self, point: Vector3) -> Vector3 {
+        point - self.normal * self.signed_distance(point)
+    }
+}
+
+/// counter-clockwise `a`, `b`, `c` face the direction of `normal`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Triangle {
+    pub a: Vector3,
+    pub b: Vector3,
+    pub c: Vector3,
+}
+
+#[allow(dead_code)]
+impl Triangle {
+    pub fn new(a: Vector3, b: Vector3, c: Vector3) -> Self {
+        Triangle { a, b, c }
+    }
+
+    pub fn normal(&self) -> Vector3 {
+        (self.b - self.a).cross(self.c - self.a).normalized()
+    }
+
+    pub fn area(&self) -> f32 {
+        (self.b - self.a).cross(self.c - self.a).length() * 0.5
+    }
+
+    /// checks the voronoi regions of vertices and edges before projecting on the face
+    /// reference: Real-Time Collision Detection (Christer Ericson), 5.1.5
+    pub fn closest_point(&self, point: Vector3) -> Vector3 {
+        let Triangle { a, b, c } = *self;
+        let ab = b - a;
+        let ac = c - a;
+
+        let ap = point - a;
+        let d1 = ab.dot(ap);
+        let d2 = ac.dot(ap);
+        if d1 <= 0.0 && d2 <= 0.0 {
+            return a;
+        }
+
+        let bp = point - b;
+        let d3 = ab.dot(bp);
+        let d4 = ac.dot(bp);
+        if d3 >= 0.0 && d4 <= d3 {
+            return b;
+        }
+
+        let vc = d1 * d4 - d3 * d2;
+        if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+            return a + ab * (d1 / (d1 - d3));
+        }
+
+        let cp = point - c;
+        let d5 = ab.dot(cp);
+        let d6 = ac.dot(cp);
+        if d6 >= 0.0 && d5 <= d6 {
+            return c;
+        }
+
+        let vb = d5 * d2 - d1 * d6;
+        if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+            return a + ac * (d2 / (d2 - d6));
+        }
+
+        let va = d3 * d6 - d5 * d4;
+        if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+            return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+        }
+
+        let denominator = 1.0 / (va + vb + vc);
+        a + ab * (vb * denominator) + ac * (vc * denominator)
+    }
+}
+
+/// oriented bounding box, `axes` are orthonormal and `half_extents` are measured along them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Obb {
+    pub center: Vector3,
+    pub half_extents: Vector3,
+    pub axes: [Vector3; 3],
+}
+
+#[allow(dead_code)]
+impl Obb {
+    pub fn new(center: Vector3, half_extents: Vector3, axes: [Vector3; 3]) -> Self {
+        Obb {
+            center,
+            half_extents,
+            axes,
+        }
+    }
+
+    /// the local box of a prefab placed by an instance transform, scale goes to the extents
+    pub fn from_aabb(aabb: &Aabb, matrix: Matrix4) -> Self {
+        let side = matrix.get_side_vector();
+        let up = matrix.get_up_vector();
+        let forward = matrix.get_forward_vector();
+        let extents = aabb.half_extents();
+
+        Obb::new(
+            matrix.transform_point(aabb.center()),
+            Vector3::new(
+                extents.x * side.length(),
+                extents.y * up.length(),
+                extents.z * forward.length(),
+            ),
+            [side.normalized(), up.normalized(), forward.normalized()],
+        )
+    }
+
+    pub fn contains_point(&self, point: Vector3) -> bool {
+        let offset = point - self.center;
+        let extents = self.half_extents.to_array();
+
+        (0..3).all(|axis| offset.dot(self.axes[axis]).abs() <= extents[axis])
+    }
+
+    /// reference: Real-Time Collision Detection (Christer Ericson), 5.1.4
+    pub fn closest_point(&self, point: Vector3) -> Vector3 {
+        let offset = point - self.center;
+        let extents = self.half_extents.to_array();
+
+        (0..3).fold(self.center, |closest, axis| {
+            let distance = clamp(offset.dot(self.axes[axis]), -extents[axis], extents[axis]);
+            closest + self.axes[axis] * distance
+        })
+    }
+
+    pub fn intersects_sphere(&self, sphere: &Sphere) -> bool {
+        sphere.contains_point(self.closest_point(sphere.center))
+    }
+
+    pub fn corners(&self) -> [Vector3; 8] {
+        let x = self.axes[0] * self.half_extents.x;
+        let y = self.axes[1] * self.half_extents.y;
+        let z = self.axes[2] * self.half_extents.z;
+        let c = self.center;
+
+        [
+            c - x - y - z,
+            c + x - y - z,
+            c - x + y - z,
+            c + x + y - z,
+            c - x - y + z,
+            c + x - y + z,
+            c - x + y + z,
+            c + x + y + z,
+        ]
+    }
+
+    pub fn to_aabb(self) -> Aabb {
+        Aabb::from_points(&self.corners())
+    }
+}
+
+/// planes point inwards, ordered as left, right, bottom, top, near and far
+#[derive(Debug, Clone, Copy)]
+pub struct Frustum {
+    pub planes: [Plane; 6],
+}
+
+/// reference: http://www.cs.otago.ac.nz/postgrads/alexis/planeExtraction.pdf (Gribb & Hartmann)
+#[allow(dead_code)]
+impl Frustum {
+    pub fn from_matrix(view_projection: Matrix4) -> Self {
+        let row0 = view_projection.get_row(0);
+        let row1 = view_projection.get_row(1);
+        let row2 = view_projection.get_row(2);
+        let row3 = view_projection.get_row(3);
+
+        let plane = |row: [f32; 4], sign: f32| {
+            Plane::from_coefficients(
+                row3[0] + sign * row[0],
+                row3[1] + sign * row[1],
+                row3[2] + sign * row[2],
+                row3[3] + sign * row[3],
+            )
+        };
+
+        Frustum {
+            planes: [
+                plane(row0, 1.0),
+                plane(row0, -1.0),
+                plane(row1, 1.0),
+                plane(row1, -1.0),
+                plane(row2, 1.0),
+                plane(row2, -1.0),
+            ],
+        }
+    }
+
+    pub fn contains_point(&self, point: Vector3) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.signed_distance(point) >= 0.0)
+    }
+
+    pub fn intersects_sphere(&self, sphere: &Sphere) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.signed_distance(sphere.center) >= -sphere.radius)
+    }
+
+    /// conservative: boxes near the frustum corners may be reported as visible
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            // the corner most in the direction of the plane normal
+            let positive = Vector3::new(
+                if plane.normal.x >= 0.0 {
+                    aabb.max.x
+                } else {
+                    aabb.min.x
+                },
+                if plane.normal.y >= 0.0 {
+                    aabb.max.y
+                } else {
+                    aabb.min.y
+                },
+                if plane.normal.z >= 0.0 {
+                    aabb.max.z
+                } else {
+                    aabb.min.z
+                },
+            );
+
+            plane.signed_distance(positive) >= 0.0
+        })
+    }
+}
+
 pub fn clamp(value: f32, min: f32, max: f32) -> f32 {
     f32::min(f32::max(value, min), max)
 }
@@ -249,6 +1026,7 @@ pub fn degree_to_radians(angle: f32) -> f32 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::matrices::MatrixOperation;
 
     #[test]
     #[rustfmt::skip]
@@ -289,6 +1067,21 @@ mod tests {
         assert_eq!(a.to_array(), [3.0, 1.0, 2.0]);
     }
 
+    #[test]
+    fn vector3_dot() {
+        let a = Vector3::new(2.0, 3.0, 4.0);
+        let b = Vector3::new(5.0, -6.0, 7.0);
+
+        assert_eq!(a.dot(b), 20.0);
+    }
+
+    #[test]
+    fn vector3_length() {
+        let a = Vector3::new(2.0, -3.0, 6.0);
+
+        assert_eq!(a.length(), 7.0);
+    }
+
     #[test]
     fn vector3_cross() {
         let a = Vector3::new(2.0, 3.0, 4.0);
@@ -328,6 +1121,617 @@ mod tests {
         assert_eq!(a * 2.0, Vector3::new(14.0, -6.0, 0.0));
     }
 
+    #[test]
+    fn matrix4_transform_point_and_vector() {
+        let matrix = MatrixOperation::translation(Vector3::new(1.0, 2.0, 3.0))
+            * MatrixOperation::scale(Vector3::new(2.0, 2.0, 2.0));
+
+        assert_eq!(
+            matrix.transform_point(Vector3::new(1.0, 0.0, -1.0)),
+            Vector3::new(3.0, 2.0, 1.0)
+        );
+        assert_eq!(
+            matrix.transform_vector(Vector3::new(1.0, 0.0, -1.0)),
+            Vector3::new(2.0, 0.0, -2.0)
+        );
+    }
+
+    #[test]
+    fn matrix4_max_scale() {
+        let matrix =
+            Quaternion::rotate_y(30.0) * MatrixOperation::scale(Vector3::new(1.0, 4.0, 2.0));
+
+        assert_eq!(matrix.get_max_scale(), 4.0);
+    }
+
+    #[test]
+    fn vector3_min_max() {
+        let a = Vector3::new(1.0, 5.0, -3.0);
+        let b = Vector3::new(2.0, -5.0, -4.0);
+
+        assert_eq!(a.min(b), Vector3::new(1.0, -5.0, -4.0));
+        assert_eq!(a.max(b), Vector3::new(2.0, 5.0, -3.0));
+    }
+
+    #[test]
+    fn aabb_from_points() {
+        let aabb = Aabb::from_points(&[
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(-1.0, 2.0, 0.5),
+            Vector3::new(0.0, -3.0, 4.0),
+        ]);
+
+        assert_eq!(aabb.min, Vector3::new(-1.0, -3.0, 0.0));
+        assert_eq!(aabb.max, Vector3::new(1.0, 2.0, 4.0));
+        assert_eq!(aabb.center(), Vector3::new(0.0, -0.5, 2.0));
+        assert_eq!(aabb.half_extents(), Vector3::new(1.0, 2.5, 2.0));
+    }
+
+    #[test]
+    fn aabb_transform() {
+        let aabb = Aabb::new(Vector3::new(-1.0, -1.0, -1.0), Vector3::new(1.0, 1.0, 1.0));
+        let matrix = MatrixOperation::translation(Vector3::new(10.0, 0.0, 0.0))
+            * MatrixOperation::scale(Vector3::new(2.0, 1.0, 1.0));
+
+        let transformed = aabb.transform(matrix);
+
+        assert_eq!(transformed.min, Vector3::new(8.0, -1.0, -1.0));
+        assert_eq!(transformed.max, Vector3::new(12.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn aabb_transform_rotated_grows() {
+        let aabb = Aabb::new(Vector3::new(-1.0, -1.0, -1.0), Vector3::new(1.0, 1.0, 1.0));
+
+        let transformed = aabb.transform(Quaternion::rotate_y(45.0));
+
+        assert!((transformed.max.x - 2.0_f32.sqrt()).abs() < 0.0001);
+        assert!((transformed.max.z - 2.0_f32.sqrt()).abs() < 0.0001);
+        assert!((transformed.max.y - 1.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn sphere_from_points() {
+        let sphere = Sphere::from_points(&[
+            Vector3::new(-1.0, 0.0, 0.0),
+            Vector3::new(3.0, 0.0, 0.0),
+            Vector3::new(1.0, 1.0, 0.0),
+        ]);
+
+        assert_eq!(sphere.center, Vector3::new(1.0, 0.5, 0.0));
+        assert_eq!(sphere.radius, 2.0615528);
+    }
+
+    #[test]
+    fn sphere_transform() {
+        let sphere = Sphere::new(Vector3::new(1.0, 0.0, 0.0), 1.0);
+        let matrix = MatrixOperation::translation(Vector3::new(0.0, 5.0, 0.0))
+            * MatrixOperation::scale(Vector3::new(3.0, 1.0, 1.0));
+
+        let transformed = sphere.transform(matrix);
+
+        assert_eq!(transformed.center, Vector3::new(3.0, 5.0, 0.0));
+        assert_eq!(transformed.radius, 3.0);
+    }
+
+    #[test]
+    fn plane_signed_distance() {
+        let plane = Plane::from_coefficients(0.0, 2.0, 0.0, -4.0);
+
+        assert_eq!(plane.normal, Vector3::new(0.0, 1.0, 0.0));
+        assert_eq!(plane.signed_distance(Vector3::new(5.0, 3.0, 1.0)), 1.0);
+        assert_eq!(plane.signed_distance(Vector3::new(5.0, 0.0, 1.0)), -2.0);
+    }
+
+    fn test_frustum() -> Frustum {
+        // camera at the origin looking down -z, as on the default camera matrix
+        Frustum::from_matrix(MatrixOperation::perspective(1.0, 90.0, 1.0, 100.0))
+    }
+
+    #[test]
+    fn frustum_contains_point() {
+        let frustum = test_frustum();
+
+        assert!(frustum.contains_point(Vector3::new(0.0, 0.0, -10.0)));
+        assert!(frustum.contains_point(Vector3::new(9.0, -9.0, -10.0)));
+        assert!(!frustum.contains_point(Vector3::new(11.0, 0.0, -10.0)));
+        assert!(!frustum.contains_point(Vector3::new(0.0, 0.0, 10.0)));
+        assert!(!frustum.contains_point(Vector3::new(0.0, 0.0, -0.5)));
+        assert!(!frustum.contains_point(Vector3::new(0.0, 0.0, -101.0)));
+    }
+
+    #[test]
+    fn frustum_intersects_sphere() {
+        let frustum = test_frustum();
+
+        assert!(frustum.intersects_sphere(&Sphere::new(Vector3::new(0.0, 0.0, -50.0), 1.0)));
+        assert!(frustum.intersects_sphere(&Sphere::new(Vector3::new(11.0, 0.0, -10.0), 2.0)));
+        assert!(!frustum.intersects_sphere(&Sphere::new(Vector3::new(0.0, 0.0, 5.0), 2.0)));
+        assert!(!frustum.intersects_sphere(&Sphere::new(Vector3::new(0.0, 0.0, -110.0), 5.0)));
+    }
+
+    #[test]
+    fn frustum_intersects_aabb() {
+        let frustum = test_frustum();
+
+        let inside = Aabb::new(Vector3::new(-1.0, -1.0, -6.0), Vector3::new(1.0, 1.0, -4.0));
+        let crossing = Aabb::new(Vector3::new(5.0, -1.0, -6.0), Vector3::new(15.0, 1.0, -4.0));
+        let behind = Aabb::new(Vector3::new(-1.0, -1.0, 4.0), Vector3::new(1.0, 1.0, 6.0));
+        let left = Aabb::new(
+            Vector3::new(-30.0, -1.0, -6.0),
+            Vector3::new(-20.0, 1.0, -4.0),
+        );
+
+        assert!(frustum.intersects_aabb(&inside));
+        assert!(frustum.intersects_aabb(&crossing));
+        assert!(!frustum.intersects_aabb(&behind));
+        assert!(!frustum.intersects_aabb(&left));
+    }
+
+    #[test]
+    fn aabb_union_and_contains() {
+        let a = Aabb::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+        let b = Aabb::new(Vector3::new(2.0, -1.0, 0.5), Vector3::new(3.0, 0.5, 0.7));
+
+        let union = a.union(&b);
+
+        assert_eq!(union.min, Vector3::new(0.0, -1.0, 0.0));
+        assert_eq!(union.max, Vector3::new(3.0, 1.0, 1.0));
+        assert!(union.contains(&a));
+        assert!(union.contains(&b));
+        assert!(!a.contains(&union));
+    }
+
+    #[test]
+    fn aabb_surface_area_and_expanded() {
+        let aabb = Aabb::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 2.0, 3.0));
+
+        assert_eq!(aabb.surface_area(), 22.0);
+        assert_eq!(aabb.expanded(1.0).min, Vector3::new(-1.0, -1.0, -1.0));
+        assert_eq!(aabb.expanded(1.0).max, Vector3::new(2.0, 3.0, 4.0));
+    }
+
+    #[test]
+    fn aabb_intersects_aabb() {
+        let a = Aabb::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+        let touching = Aabb::new(Vector3::new(1.0, 0.0, 0.0), Vector3::new(2.0, 1.0, 1.0));
+        let apart = Aabb::new(Vector3::new(0.0, 1.5, 0.0), Vector3::new(1.0, 2.0, 1.0));
+
+        assert!(a.intersects_aabb(&touching));
+        assert!(!a.intersects_aabb(&apart));
+    }
+
+    #[test]
+    fn sphere_intersects_aabb() {
+        let aabb = Aabb::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+
+        assert!(Sphere::new(Vector3::new(0.5, 0.5, 0.5), 0.1).intersects_aabb(&aabb));
+        assert!(Sphere::new(Vector3::new(2.0, 0.5, 0.5), 1.0).intersects_aabb(&aabb));
+        assert!(!Sphere::new(Vector3::new(2.0, 2.0, 2.0), 1.0).intersects_aabb(&aabb));
+    }
+
+    #[test]
+    fn ray_intersect_aabb() {
+        let aabb = Aabb::new(Vector3::new(-1.0, -1.0, -1.0), Vector3::new(1.0, 1.0, 1.0));
+
+        let hit = Ray::new(Vector3::new(-5.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        let inside = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        let away = Ray::new(Vector3::new(-5.0, 0.0, 0.0), Vector3::new(-1.0, 0.0, 0.0));
+        let parallel = Ray::new(Vector3::new(-5.0, 2.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+
+        assert_eq!(hit.intersect_aabb(&aabb), Some(4.0));
+        assert_eq!(inside.intersect_aabb(&aabb), Some(0.0));
+        assert_eq!(away.intersect_aabb(&aabb), None);
+        assert_eq!(parallel.intersect_aabb(&aabb), None);
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn matrix4_inverse() {
+        let matrix = MatrixOperation::translation(Vector3::new(1.0, 2.0, 3.0))
+            * Quaternion::rotate_y(30.0)
+            * MatrixOperation::scale(Vector3::new(2.0, 2.0, 2.0));
+
+        let identity = matrix * matrix.inverse().unwrap();
+
+        for (value, expected) in identity.data.iter().zip(Matrix4::identity().data.iter()) {
+            assert!((value - expected).abs() < 0.00001);
+        }
+    }
+
+    #[test]
+    fn matrix4_inverse_singular() {
+        let matrix = MatrixOperation::scale(Vector3::new(1.0, 0.0, 1.0));
+
+        assert_eq!(matrix.inverse(), None);
+    }
+
+    #[test]
+    fn matrix4_project_point() {
+        let perspective = MatrixOperation::perspective(1.0, 90.0, 1.0, 100.0);
+
+        let near = perspective.project_point(Vector3::new(1.0, 0.0, -1.0));
+        let far = perspective.project_point(Vector3::new(0.0, -100.0, -100.0));
+
+        assert_eq!(near, Vector3::new(1.0, 0.0, -0.9999999));
+        assert_eq!(far, Vector3::new(0.0, -1.0, 1.0));
+    }
+
+    #[test]
+    fn matrix4_project_point_orthographic() {
+        let orthographic = MatrixOperation::orthographic(2.0, 10.0, 1.0, 101.0);
+
+        let near = orthographic.project_point(Vector3::new(10.0, 5.0, -1.0));
+        let far = orthographic.project_point(Vector3::new(-10.0, 0.0, -101.0));
+
+        assert_eq!(near, Vector3::new(1.0, 1.0, -1.0));
+        assert_eq!(far, Vector3::new(-1.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn ray_intersect_triangle() {
+        let triangle = Triangle::new(
+            Vector3::new(-1.0, -1.0, 0.0),
+            Vector3::new(1.0, -1.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+        );
+
+        let front = Ray::new(Vector3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0));
+        let back = Ray::new(Vector3::new(0.0, 0.0, -2.0), Vector3::new(0.0, 0.0, 1.0));
+        let beside = Ray::new(Vector3::new(2.0, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0));
+        let away = Ray::new(Vector3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 0.0, 1.0));
+        let parallel = Ray::new(Vector3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 1.0, 0.0));
+
+        assert_eq!(front.intersect_triangle(&triangle), Some(5.0));
+        assert_eq!(back.intersect_triangle(&triangle), Some(2.0));
+        assert_eq!(beside.intersect_triangle(&triangle), None);
+        assert_eq!(away.intersect_triangle(&triangle), None);
+        assert_eq!(parallel.intersect_triangle(&triangle), None);
+    }
+
+    #[test]
+    fn ray_intersect_triangle_edges_and_vertices() {
+        let triangle = Triangle::new(
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(2.0, 0.0, 0.0),
+            Vector3::new(0.0, 2.0, 0.0),
+        );
+        let down = Vector3::new(0.0, 0.0, -1.0);
+
+        assert_eq!(
+            Ray::new(Vector3::new(1.0, 0.0, 1.0), down).intersect_triangle(&triangle),
+            Some(1.0)
+        );
+        assert_eq!(
+            Ray::new(Vector3::new(0.0, 0.0, 1.0), down).intersect_triangle(&triangle),
+            Some(1.0)
+        );
+        assert_eq!(
+            Ray::new(Vector3::new(1.5, 1.5, 1.0), down).intersect_triangle(&triangle),
+            None
+        );
+    }
+
+    #[test]
+    fn vector3_neg() {
+        assert_eq!(-Vector3::new(1.0, -2.0, 0.0), Vector3::new(-1.0, 2.0, 0.0));
+    }
+
+    #[test]
+    fn ray_normalizes_direction() {
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 3.0, 4.0));
+
+        assert_eq!(ray.direction, Vector3::new(0.0, 0.6, 0.8));
+        assert_eq!(ray.point_at(5.0), Vector3::new(0.0, 3.0, 4.0));
+    }
+
+    #[test]
+    fn ray_closest_point() {
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+
+        assert_eq!(
+            ray.closest_point(Vector3::new(5.0, 3.0, 0.0)),
+            Vector3::new(5.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            ray.closest_point(Vector3::new(-5.0, 3.0, 0.0)),
+            Vector3::new(0.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn ray_intersect_plane() {
+        let floor =
+            Plane::from_point_normal(Vector3::new(0.0, 1.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+
+        let down = Ray::new(Vector3::new(3.0, 5.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let from_below = Ray::new(Vector3::new(0.0, -1.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        let up = Ray::new(Vector3::new(3.0, 5.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        let parallel = Ray::new(Vector3::new(3.0, 5.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        let diagonal = Ray::new(Vector3::new(0.0, 2.0, 0.0), Vector3::new(1.0, -1.0, 0.0));
+
+        assert_eq!(down.intersect_plane(&floor), Some(4.0));
+        assert_eq!(from_below.intersect_plane(&floor), Some(2.0));
+        assert_eq!(up.intersect_plane(&floor), None);
+        assert_eq!(parallel.intersect_plane(&floor), None);
+        assert_eq!(
+            diagonal.intersect_plane(&floor),
+            Some(std::f32::consts::SQRT_2)
+        );
+    }
+
+    #[test]
+    fn ray_intersect_sphere() {
+        let sphere = Sphere::new(Vector3::new(0.0, 0.0, -10.0), 2.0);
+
+        let hit = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        let inside = Ray::new(Vector3::new(0.0, 0.0, -10.0), Vector3::new(1.0, 0.0, 0.0));
+        let behind = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        let miss = Ray::new(Vector3::new(3.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        let tangent = Ray::new(Vector3::new(2.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+
+        assert_eq!(hit.intersect_sphere(&sphere), Some(8.0));
+        assert_eq!(inside.intersect_sphere(&sphere), Some(0.0));
+        assert_eq!(behind.intersect_sphere(&sphere), None);
+        assert_eq!(miss.intersect_sphere(&sphere), None);
+        assert_eq!(tangent.intersect_sphere(&sphere), Some(10.0));
+    }
+
+    #[test]
+    fn ray_intersect_aabb_diagonal() {
+        let aabb = Aabb::new(Vector3::new(1.0, 1.0, 1.0), Vector3::new(2.0, 2.0, 2.0));
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+
+        let distance = ray.intersect_aabb(&aabb).unwrap();
+        assert!((distance - 3.0_f32.sqrt()).abs() < 0.0001);
+    }
+
+    #[test]
+    fn ray_intersect_obb() {
+        let obb = Obb::from_aabb(
+            &Aabb::new(Vector3::new(-0.5, -0.5, -0.5), Vector3::new(0.5, 0.5, 0.5)),
+            MatrixOperation::translation(Vector3::new(10.0, 0.0, 0.0))
+                * Quaternion::rotate_y(45.0)
+                * MatrixOperation::scale(Vector3::new(2.0, 2.0, 2.0)),
+        );
+
+        let hit = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        let miss = Ray::new(Vector3::new(0.0, 1.5, 0.0), Vector3::new(1.0, 0.0, 0.0));
+
+        // the rotated box shows a corner, sqrt(2) away from the center
+        let distance = hit.intersect_obb(&obb).unwrap();
+        assert!((distance - (10.0 - 2.0_f32.sqrt())).abs() < 0.0001);
+        assert_eq!(miss.intersect_obb(&obb), None);
+    }
+
+    #[test]
+    fn sphere_intersects_sphere() {
+        let a = Sphere::new(Vector3::new(0.0, 0.0, 0.0), 1.0);
+        let touching = Sphere::new(Vector3::new(3.0, 0.0, 0.0), 2.0);
+        let apart = Sphere::new(Vector3::new(0.0, 3.1, 0.0), 2.0);
+
+        assert!(a.intersects_sphere(&touching));
+        assert!(touching.intersects_sphere(&a));
+        assert!(!a.intersects_sphere(&apart));
+    }
+
+    #[test]
+    fn sphere_closest_point() {
+        let sphere = Sphere::new(Vector3::new(1.0, 0.0, 0.0), 2.0);
+
+        assert_eq!(
+            sphere.closest_point(Vector3::new(1.0, 10.0, 0.0)),
+            Vector3::new(1.0, 2.0, 0.0)
+        );
+        assert_eq!(
+            sphere.closest_point(Vector3::new(1.5, 0.0, 0.0)),
+            Vector3::new(1.5, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn aabb_closest_point_and_contains_point() {
+        let aabb = Aabb::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+
+        assert_eq!(
+            aabb.closest_point(Vector3::new(2.0, 0.5, -1.0)),
+            Vector3::new(1.0, 0.5, 0.0)
+        );
+        assert!(aabb.contains_point(Vector3::new(0.5, 0.5, 1.0)));
+        assert!(!aabb.contains_point(Vector3::new(0.5, 1.5, 0.5)));
+    }
+
+    #[test]
+    fn aabb_intersects_aabb_nested_and_separated_by_one_axis() {
+        let outer = Aabb::new(Vector3::new(-5.0, -5.0, -5.0), Vector3::new(5.0, 5.0, 5.0));
+        let inner = Aabb::new(Vector3::new(-1.0, -1.0, -1.0), Vector3::new(1.0, 1.0, 1.0));
+        let separated_z = Aabb::new(Vector3::new(-1.0, -1.0, 6.0), Vector3::new(1.0, 1.0, 7.0));
+
+        assert!(outer.intersects_aabb(&inner));
+        assert!(inner.intersects_aabb(&outer));
+        assert!(!outer.intersects_aabb(&separated_z));
+    }
+
+    #[test]
+    fn sphere_intersects_aabb_near_corner() {
+        let aabb = Aabb::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+
+        // close to the corner on every axis, but more than 1.0 away from it
+        let sphere = Sphere::new(Vector3::new(1.7, 1.7, 1.7), 1.0);
+
+        assert!(!sphere.intersects_aabb(&aabb));
+        assert!(Sphere::new(Vector3::new(1.5, 1.5, 1.5), 1.0).intersects_aabb(&aabb));
+    }
+
+    #[test]
+    fn plane_closest_point() {
+        let plane =
+            Plane::from_point_normal(Vector3::new(0.0, 0.0, 2.0), Vector3::new(0.0, 0.0, 3.0));
+
+        assert_eq!(plane.distance, -2.0);
+        assert_eq!(
+            plane.closest_point(Vector3::new(4.0, 5.0, -1.0)),
+            Vector3::new(4.0, 5.0, 2.0)
+        );
+    }
+
+    #[test]
+    fn triangle_normal_and_area() {
+        let triangle = Triangle::new(
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(2.0, 0.0, 0.0),
+            Vector3::new(0.0, 2.0, 0.0),
+        );
+
+        assert_eq!(triangle.normal(), Vector3::new(0.0, 0.0, 1.0));
+        assert_eq!(triangle.area(), 2.0);
+    }
+
+    #[test]
+    fn triangle_closest_point_regions() {
+        let triangle = Triangle::new(
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(2.0, 0.0, 0.0),
+            Vector3::new(0.0, 2.0, 0.0),
+        );
+
+        // face
+        assert_eq!(
+            triangle.closest_point(Vector3::new(0.5, 0.5, 3.0)),
+            Vector3::new(0.5, 0.5, 0.0)
+        );
+        // vertices
+        assert_eq!(
+            triangle.closest_point(Vector3::new(-1.0, -1.0, 0.0)),
+            Vector3::new(0.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            triangle.closest_point(Vector3::new(3.0, -1.0, 0.0)),
+            Vector3::new(2.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            triangle.closest_point(Vector3::new(-1.0, 3.0, 1.0)),
+            Vector3::new(0.0, 2.0, 0.0)
+        );
+        // edges
+        assert_eq!(
+            triangle.closest_point(Vector3::new(1.0, -1.0, 0.0)),
+            Vector3::new(1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            triangle.closest_point(Vector3::new(-1.0, 1.0, 0.0)),
+            Vector3::new(0.0, 1.0, 0.0)
+        );
+        assert_eq!(
+            triangle.closest_point(Vector3::new(2.0, 2.0, 0.0)),
+            Vector3::new(1.0, 1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn obb_from_aabb() {
+        let obb = Obb::from_aabb(
+            &Aabb::new(Vector3::new(-0.5, -0.5, -0.5), Vector3::new(0.5, 0.5, 0.5)),
+            MatrixOperation::translation(Vector3::new(1.0, 2.0, 3.0))
+                * MatrixOperation::scale(Vector3::new(2.0, 4.0, 6.0)),
+        );
+
+        assert_eq!(obb.center, Vector3::new(1.0, 2.0, 3.0));
+        assert_eq!(obb.half_extents, Vector3::new(1.0, 2.0, 3.0));
+        assert_eq!(obb.axes[0], Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(
+            obb.to_aabb(),
+            Aabb::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(2.0, 4.0, 6.0))
+        );
+    }
+
+    #[test]
+    fn obb_closest_point_and_contains_point() {
+        let diagonal = Vector3::new(1.0, 0.0, 1.0).normalized();
+        let obb = Obb::new(
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(1.0, 1.0, 1.0),
+            [
+                diagonal,
+                Vector3::new(0.0, 1.0, 0.0),
+                Vector3::new(-1.0, 0.0, 1.0).normalized(),
+            ],
+        );
+
+        assert!(obb.contains_point(Vector3::new(1.2, 0.0, 0.0)));
+        assert!(!obb.contains_point(Vector3::new(1.0, 0.0, 1.0)));
+
+        let closest = obb.closest_point(Vector3::new(3.0, 0.0, 3.0));
+        assert!((closest - diagonal).length() < 0.0001);
+    }
+
+    #[test]
+    fn obb_intersects_sphere() {
+        let obb = Obb::from_aabb(
+            &Aabb::new(Vector3::new(-1.0, -1.0, -1.0), Vector3::new(1.0, 1.0, 1.0)),
+            Quaternion::rotate_z(45.0),
+        );
+
+        // the rotated corner reaches sqrt(2) on the x axis
+        assert!(obb.intersects_sphere(&Sphere::new(Vector3::new(2.0, 0.0, 0.0), 0.6)));
+        assert!(!obb.intersects_sphere(&Sphere::new(Vector3::new(2.0, 0.0, 0.0), 0.5)));
+    }
+
+    #[test]
+    fn quaternion_rotate_vector() {
+        let rotation = Quaternion::new(Vector3::new(0.0, 0.0, 1.0), 90.0);
+        let rotated = rotation.rotate_vector(Vector3::new(1.0, 0.0, 0.0));
+
+        assert!((rotated - Vector3::new(0.0, 1.0, 0.0)).length() < 0.0001);
+
+        let back = rotation.conjugate().rotate_vector(rotated);
+        assert!((back - Vector3::new(1.0, 0.0, 0.0)).length() < 0.0001);
+    }
+
+    #[test]
+    fn quaternion_rotate_vector_matches_matrix() {
+        let rotation = Quaternion::new(Vector3::new(1.0, 2.0, -0.5), 73.0);
+        let vector = Vector3::new(0.3, -2.0, 4.0);
+
+        let from_quaternion = rotation.rotate_vector(vector);
+        let from_matrix = rotation.to_matrix().transform_vector(vector);
+
+        assert!((from_quaternion - from_matrix).length() < 0.0001);
+    }
+
+    #[test]
+    fn quaternion_from_rotation_matrix_round_trip() {
+        let rotations = [
+            Quaternion::new(Vector3::new(0.0, 1.0, 0.0), 30.0),
+            Quaternion::new(Vector3::new(1.0, 0.0, 0.0), 179.0),
+            Quaternion::new(Vector3::new(0.0, 1.0, 0.0), 181.0),
+            Quaternion::new(Vector3::new(1.0, 1.0, 1.0), 200.0),
+        ];
+        let vector = Vector3::new(1.0, 2.0, 3.0);
+
+        for rotation in rotations.iter() {
+            let round_trip = Quaternion::from_rotation_matrix(rotation.to_matrix());
+
+            assert!(
+                (round_trip.rotate_vector(vector) - rotation.rotate_vector(vector)).length()
+                    < 0.0001
+            );
+        }
+    }
+
+    #[test]
+    fn quaternion_integrate_angular_velocity() {
+        let mut rotation = Quaternion::identity();
+        let quarter_turn_per_second = Vector3::new(0.0, std::f32::consts::FRAC_PI_2, 0.0);
+
+        for _ in 0..1000 {
+            rotation = rotation.integrate(quarter_turn_per_second, 0.001);
+        }
+
+        let rotated = rotation.rotate_vector(Vector3::new(1.0, 0.0, 0.0));
+        assert!((rotated - Vector3::new(0.0, 0.0, -1.0)).length() < 0.001);
+    }
+
     #[test]
     fn clamp_values() {
         assert_eq!(5.0, clamp(3.0, 5.0, 10.0));
@@ -20,6 +20,22 @@ impl MatrixOperation {
         ])
     }
 
+    /// `height` is how many world units fit from the bottom to the top of the view
+    pub fn orthographic(
+        display_ratio: f32,
+        height: f32,
+        z_near: f32,
+        z_far: f32,
+    ) -> Matrix4 {
+        let width = height * display_ratio;
+        Matrix4::from([
+            2.0 / width, 0.0, 0.0, 0.0,
+            0.0, 2.0 / height, 0.0, 0.0,
+            0.0, 0.0, 2.0 / (z_near - z_far), (z_far + z_near) / (z_near - z_far),
+            0.0, 0.0, 0.0, 1.0
+        ])
+    }
+
     pub fn translation(input: Vector3) -> Matrix4 {
         Matrix4::from([
             1.0, 0.0, 0.0, input.x,
@@ -0,0 +1,1027 @@
+use crate::math::{Matrix4, Vector3};
+use crate::models::{Prefab, PrefabIndices};
+use crate::primitives::{TangentVertex, Vertex};
+use glium::backend::glutin::Display;
+use glium::index::PrimitiveType;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap};
+use std::ops;
+use std::rc::Rc;
+
+/// normals, texture coordinates, colors and tangents closer than this weld together
+const ATTRIBUTE_TOLERANCE: f32 = 1e-3;
+/// how much more it costs to move a border than a surface, so open meshes keep their outline
+const BORDER_WEIGHT: f64 = 1000.0;
+/// collapses that turn a triangle further than this from its old normal, as a cosine, are skipped
+const MIN_FLIP_COSINE: f32 = 0.5;
+
+fn to_vector(value: [f32; 3]) -> Vector3 {
+    Vector3::new(value[0], value[1], value[2])
+}
+
+/// exact bits as a hash key, with -0.0 the same as 0.0
+fn key(value: &[f32]) -> Vec<u32> {
+    value.iter().map(|&x| (x + 0.0).to_bits()).collect()
+}
+
+fn vertex_key(vertex: &Vertex) -> Vec<u32> {
+    let mut values = vertex.position.to_vec();
+    values.extend_from_slice(&vertex.color);
+    values.extend_from_slice(&vertex.tex_coords);
+    values.extend_from_slice(&vertex.normal);
+    key(&values)
+}
+
+fn close(a: &[f32], b: &[f32], tolerance: f32) -> bool {
+    a.iter().zip(b).all(|(a, b)| (a - b).abs() <= tolerance)
+}
+
+/// Triangle list kept on the cpu, for building geometry before it is uploaded.
+/// Triangles wind counter clockwise seen from the side the normals point to
+#[derive(Debug, Clone, Default)]
+pub struct Mesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    /// one per vertex after `generate_tangents`, empty before. Functions that change
+    /// the normals clear them
+    pub tangents: Vec<[f32; 4]>,
+}
+
+#[allow(dead_code)]
+impl Mesh {
+    pub fn new(vertices: Vec<Vertex>, indices: Vec<u32>) -> Self {
+        Mesh {
+            vertices,
+            indices,
+            tangents: Vec::new(),
+        }
+    }
+
+    /// the vertices a prefab keeps on the cpu, without tangents
+    pub fn from_prefab(prefab: &Prefab) -> Self {
+        let indices = prefab
+            .get_triangles()
+            .iter()
+            .flat_map(|triangle| triangle.iter().map(|&index| index as u32))
+            .collect();
+        Mesh::new(prefab.get_vertices(), indices)
+    }
+
+    pub fn triangles(&self) -> impl Iterator<Item = [u32; 3]> + '_ {
+        self.indices
+            .chunks_exact(3)
+            .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+    }
+
+    pub fn has_tangents(&self) -> bool {
+        !self.vertices.is_empty() && self.tangents.len() == self.vertices.len()
+    }
+
+    /// adds the other mesh's triangles, its vertices are not shared with ours.
+    /// Tangents are kept only when both meshes have them
+    pub fn append(&mut self, other: &Mesh) {
+        let keep_tangents =
+            (self.vertices.is_empty() || self.has_tangents()) && other.has_tangents();
+        let offset = self.vertices.len() as u32;
+        self.vertices.extend_from_slice(&other.vertices);
+        self.indices
+            .extend(other.indices.iter().map(|index| index + offset));
+
+        if keep_tangents {
+            self.tangents.extend_from_slice(&other.tangents);
+        } else {
+            self.tangents.clear();
+        }
+    }
+
+    /// normals and tangents use the inverse transpose, so non uniform scales keep them
+    /// perpendicular. Mirroring transforms flip the triangles to keep them counter-clockwise
+    pub fn transformed(&self, matrix: Matrix4) -> Self {
+        let mut mesh = self.clone();
+        let inverse = matrix.inverse().unwrap_or_else(Matrix4::identity);
+        let [row_x, row_y, row_z] = [inverse.get_row(0), inverse.get_row(1), inverse.get_row(2)];
+
+        for vertex in &mut mesh.vertices {
+            vertex.position = matrix
+                .transform_point(to_vector(vertex.position))
+                .to_array();
+
+            let [x, y, z] = vertex.normal;
+            let normal = Vector3::new(
+                row_x[0] * x + row_y[0] * y + row_z[0] * z,
+                row_x[1] * x + row_y[1] * y + row_z[1] * z,
+                row_x[2] * x + row_y[2] * y + row_z[2] * z,
+            );
+            if normal.length() > 0.0 {
+                vertex.normal = normal.normalized().to_array();
+            }
+        }
+
+        let determinant = matrix
+            .get_side_vector()
+            .dot(matrix.get_up_vector().cross(matrix.get_forward_vector()));
+
+        // tangents lie on the surface, so they move like it
+        for tangent in &mut mesh.tangents {
+            let direction =
+                matrix.transform_vector(Vector3::new(tangent[0], tangent[1], tangent[2]));
+            if direction.length() > 0.0 {
+                let [x, y, z] = direction.normalized().to_array();
+                let sign = if determinant < 0.0 {
+                    -tangent[3]
+                } else {
+                    tangent[3]
+                };
+                *tangent = [x, y, z, sign];
+            }
+        }
+
+        if determinant < 0.0 {
+            for triangle in mesh.indices.chunks_exact_mut(3) {
+                triangle.swap(1, 2);
+            }
+        }
+        mesh
+    }
+
+    /// One mesh with every part moved by its transform, so static scenery made of many
+    /// pieces is drawn as one prefab with `to_prefab`
+    pub fn merge(parts: &[(&Mesh, Matrix4)]) -> Self {
+        let mut merged = Mesh::default();
+        for (mesh, matrix) in parts {
+            merged.append(&mesh.transformed(*matrix));
+        }
+        merged
+    }
+
+    /// not normalized, twice as long as the triangle's area
+    pub fn face_normal(&self, [a, b, c]: [u32; 3]) -> Vector3 {
+        let a = to_vector(self.vertices[a as usize].position);
+        let b = to_vector(self.vertices[b as usize].position);
+        let c = to_vector(self.vertices[c as usize].position);
+        (b - a).cross(c - a)
+    }
+
+    /// every triangle gets its own vertices with its own normal, for a faceted look
+    pub fn flat_normals(&mut self) {
+        let mut vertices = Vec::with_capacity(self.indices.len());
+        for triangle in self.triangles() {
+            let normal = self.face_normal(triangle);
+            for &index in &triangle {
+                let mut vertex = self.vertices[index as usize];
+                if normal.length() > 0.0 {
+                    vertex.normal = normal.normalized().to_array();
+                }
+                vertices.push(vertex);
+            }
+        }
+
+        self.indices = (0..vertices.len() as u32).collect();
+        self.vertices = vertices;
+        self.tangents.clear();
+    }
+
+    /// Averages the normals of the triangles around each position, bigger triangles
+    /// weigh more. Triangles more than `max_angle` degrees apart are not averaged, so
+    /// edges sharper than that stay hard and their vertices are split, vertices that end
+    /// up the same are shared. Vertices with the same position but different texture
+    /// coordinates still get the same normal
+    pub fn smooth_normals(&mut self, max_angle: f32) {
+        let min_cosine = max_angle.to_radians().cos();
+        let faces: Vec<Vector3> = self
+            .triangles()
+            .map(|triangle| self.face_normal(triangle))
+            .collect();
+
+        let mut around: HashMap<Vec<u32>, Vec<usize>> = HashMap::new();
+        for (face, triangle) in self.triangles().enumerate() {
+            for &index in &triangle {
+                around
+                    .entry(key(&self.vertices[index as usize].position))
+                    .or_default()
+                    .push(face);
+            }
+        }
+
+        let mut vertices = Vec::new();
+        let mut indices = Vec::with_capacity(self.indices.len());
+        let mut shared: HashMap<Vec<u32>, u32> = HashMap::new();
+        for (face, triangle) in self.triangles().enumerate() {
+            let own = faces[face];
+            for &index in &triangle {
+                let mut vertex = self.vertices[index as usize];
+                let mut sum = Vector3::new(0.0, 0.0, 0.0);
+                for &other in &around[&key(&vertex.position)] {
+                    let normal = faces[other];
+                    if other == face
+                        || (own.length() > 0.0
+                            && normal.length() > 0.0
+                            && own.normalized().dot(normal.normalized()) >= min_cosine)
+                    {
+                        sum = sum + normal;
+                    }
+                }
+                if sum.length() > 0.0 {
+                    vertex.normal = sum.normalized().to_array();
+                }
+
+                let next = vertices.len() as u32;
+                let shared_index = *shared.entry(vertex_key(&vertex)).or_insert_with(|| {
+                    vertices.push(vertex);
+                    next
+                });
+                indices.push(shared_index);
+            }
+        }
+
+        self.vertices = vertices;
+        self.indices = indices;
+        self.tangents.clear();
+    }
+
+    /// MikkTSpace tangents, the ones normal maps from most bakers expect. Needs normals
+    /// and texture coordinates. Vertices whose triangles disagree on the tangent, like
+    /// on mirrored uvs, are split. False when there is nothing to generate them for
+    ///
+    /// reference: http://www.mikktspace.com/
+    pub fn generate_tangents(&mut self) -> bool {
+        let mut space = TangentSpace {
+            mesh: self,
+            tangents: vec![[0.0; 4]; self.indices.len() / 3 * 3],
+        };
+        if !mikktspace::generate_tangents(&mut space) {
+            return false;
+        }
+        let corner_tangents = space.tangents;
+
+        let mut vertices = Vec::new();
+        let mut tangents = Vec::new();
+        let mut shared: HashMap<(u32, Vec<u32>), u32> = HashMap::new();
+        let indices = self.indices[..corner_tangents.len()]
+            .iter()
+            .zip(&corner_tangents)
+            .map(|(&index, tangent)| {
+                let next = vertices.len() as u32;
+                *shared.entry((index, key(tangent))).or_insert_with(|| {
+                    vertices.push(self.vertices[index as usize]);
+                    tangents.push(*tangent);
+                    next
+                })
+            })
+            .collect();
+
+        self.vertices = vertices;
+        self.indices = indices;
+        self.tangents = tangents;
+        true
+    }
+
+    /// Merges vertices closer than `tolerance` whose other attributes match, like the
+    /// duplicates of files that store every triangle on its own. Triangles left with no
+    /// area are removed. Seams of texture coordinates and hard edges stay split
+    pub fn weld(&mut self, tolerance: f32) {
+        // cells as big as the tolerance, so a match is always in a neighbour cell.
+        // Without a tolerance only the same bits match
+        let reach: i64 = if tolerance > 0.0 { 1 } else { 0 };
+        let cell_of = |position: [f32; 3]| -> [i64; 3] {
+            let mut cell = [0; 3];
+            for (axis, value) in position.iter().enumerate() {
+                cell[axis] = if tolerance > 0.0 {
+                    (value / tolerance).floor() as i64
+                } else {
+                    (value + 0.0).to_bits() as i64
+                };
+            }
+            cell
+        };
+
+        let has_tangents = self.has_tangents();
+        let mut grid: HashMap<[i64; 3], Vec<u32>> = HashMap::new();
+        let mut vertices: Vec<Vertex> = Vec::new();
+        let mut tangents: Vec<[f32; 4]> = Vec::new();
+        let mut remap = Vec::with_capacity(self.vertices.len());
+
+        for (index, vertex) in self.vertices.iter().enumerate() {
+            let cell = cell_of(vertex.position);
+            let same = |other: u32| {
+                let welded = &vertices[other as usize];
+                (to_vector(welded.position) - to_vector(vertex.position)).length() <= tolerance
+                    && close(&welded.normal, &vertex.normal, ATTRIBUTE_TOLERANCE)
+                    && close(&welded.tex_coords, &vertex.tex_coords, ATTRIBUTE_TOLERANCE)
+                    && close(&welded.color, &vertex.color, ATTRIBUTE_TOLERANCE)
+                    && (!has_tangents
+                        || close(
+                            &tangents[other as usize],
+                            &self.tangents[index],
+                            ATTRIBUTE_TOLERANCE,
+                        ))
+            };
+
+            let mut found = None;
+            'search: for x in -reach..=reach {
+                for y in -reach..=reach {
+                    for z in -reach..=reach {
+                        let neighbour = [cell[0] + x, cell[1] + y, cell[2] + z];
+                        if let Some(candidates) = grid.get(&neighbour) {
+                            found = candidates.iter().cloned().find(|&other| same(other));
+                            if found.is_some() {
+                                break 'search;
+                            }
+                        }
+                    }
+                }
+            }
+
+            let welded = match found {
+                Some(welded) => welded,
+                None => {
+                    vertices.push(*vertex);
+                    if has_tangents {
+                        tangents.push(self.tangents[index]);
+                    }
+                    grid.entry(cell)
+                        .or_default()
+                        .push(vertices.len() as u32 - 1);
+                    vertices.len() as u32 - 1
+                }
+            };
+            remap.push(welded);
+        }
+
+        self.indices = self
+            .triangles()
+            .map(|triangle| triangle.map(|index| remap[index as usize]))
+            .filter(|[a, b, c]| a != b && b != c && c != a)
+            .flat_map(|triangle| triangle.to_vec())
+            .collect();
+        self.vertices = vertices;
+        self.tangents = tangents;
+    }
+
+    /// Quadric error simplification: collapses the edges that change the surface the
+    /// least until `target` triangles are left, or until every collapse would fold the
+    /// mesh over. Vertices are collapsed into their neighbours and never moved, so their
+    /// texture coordinates and normals still fit. Borders of open meshes are kept.
+    ///
+    /// reference: https://www.cs.cmu.edu/~garland/Papers/quadrics.pdf
+    pub fn simplify(&mut self, target: usize) {
+        let mut simplifier = Simplifier::new(self);
+        simplifier.run(target);
+
+        let triangles: Vec<[u32; 3]> = simplifier
+            .triangles
+            .iter()
+            .zip(&simplifier.alive)
+            .filter(|(_, &alive)| alive)
+            .map(|(triangle, _)| *triangle)
+            .collect();
+
+        // the vertices collapsed away are dropped, the rest keep their order
+        let mut used = vec![false; self.vertices.len()];
+        for &index in triangles.iter().flatten() {
+            used[index as usize] = true;
+        }
+
+        let has_tangents = self.has_tangents();
+        let mut remap = vec![0; self.vertices.len()];
+        let mut vertices = Vec::new();
+        let mut tangents = Vec::new();
+        for index in (0..self.vertices.len()).filter(|&index| used[index]) {
+            remap[index] = vertices.len() as u32;
+            vertices.push(self.vertices[index]);
+            if has_tangents {
+                tangents.push(self.tangents[index]);
+            }
+        }
+
+        self.indices = triangles
+            .iter()
+            .flatten()
+            .map(|&index| remap[index as usize])
+            .collect();
+        self.vertices = vertices;
+        self.tangents = tangents;
+    }
+
+    /// meshes with tangents upload them too, as `TangentVertex`
+    pub fn to_prefab(&self, display: Display) -> Rc<Prefab> {
+        if !self.has_tangents() {
+            return Prefab::build_u32(display, self.vertices.clone(), self.indices.clone());
+        }
+
+        let vertices: Vec<TangentVertex> = self
+            .vertices
+            .iter()
+            .zip(&self.tangents)
+            .map(|(vertex, &tangent)| TangentVertex {
+                position: vertex.position,
+                color: vertex.color,
+                tex_coords: vertex.tex_coords,
+                normal: vertex.normal,
+                tangent,
+            })
+            .collect();
+        Prefab::from_vertices(
+            display,
+            &vertices,
+            PrefabIndices::smallest(vertices.len(), self.indices.clone()),
+            PrimitiveType::TrianglesList,
+        )
+        .unwrap()
+    }
+}
+
+/// the mesh as mikktspace sees it, with a tangent for every triangle corner
+struct TangentSpace<'a> {
+    mesh: &'a Mesh,
+    tangents: Vec<[f32; 4]>,
+}
+
+impl TangentSpace<'_> {
+    fn vertex(&self, face: usize, corner: usize) -> &Vertex {
+        &self.mesh.vertices[self.mesh.indices[face * 3 + corner] as usize]
+    }
+}
+
+impl mikktspace::Geometry for TangentSpace<'_> {
+    fn num_faces(&self) -> usize {
+        self.mesh.indices.len() / 3
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+
+    fn position(&self, face: usize, corner: usize) -> [f32; 3] {
+        self.vertex(face, corner).position
+    }
+
+    fn normal(&self, face: usize, corner: usize) -> [f32; 3] {
+        self.vertex(face, corner).normal
+    }
+
+    fn tex_coord(&self, face: usize, corner: usize) -> [f32; 2] {
+        self.vertex(face, corner).tex_coords
+    }
+
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, corner: usize) {
+        self.tangents[face * 3 + corner] = tangent;
+    }
+}
+
+/// symmetric 4x4 matrix of the squared distances to a set of planes, upper triangle only
+#[derive(Debug, Clone, Copy, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    fn plane(normal: Vector3, point: Vector3, weight: f64) -> Self {
+        let (a, b, c) = (normal.x as f64, normal.y as f64, normal.z as f64);
+        let d = -(a * point.x as f64 + b * point.y as f64 + c * point.z as f64);
+        let mut values = [
+            a * a,
+            a * b,
+            a * c,
+            a * d,
+            b * b,
+            b * c,
+            b * d,
+            c * c,
+            c * d,
+            d * d,
+        ];
+        for value in &mut values {
+            *value *= weight;
+        }
+        Quadric(values)
+    }
+
+    fn error(&self, point: Vector3) -> f64 {
+        let [aa, ab, ac, ad, bb, bc, bd, cc, cd, dd] = self.0;
+        let (x, y, z) = (point.x as f64, point.y as f64, point.z as f64);
+        aa * x * x
+            + 2.0 * ab * x * y
+            + 2.0 * ac * x * z
+            + 2.0 * ad * x
+            + bb * y * y
+            + 2.0 * bc * y * z
+            + 2.0 * bd * y
+            + cc * z * z
+            + 2.0 * cd * z
+            + dd
+    }
+}
+
+impl ops::Add<Quadric> for Quadric {
+    type Output = Quadric;
+
+    fn add(mut self, other: Quadric) -> Quadric {
+        for (value, other) in self.0.iter_mut().zip(&other.0) {
+            *value += other;
+        }
+        self
+    }
+}
+
+/// moving every vertex of the `from` position onto the `to` position, cheapest on top.
+/// Equal costs go by position, so the same mesh always simplifies the same way
+struct Collapse {
+    cost: f64,
+    from: usize,
+    to: usize,
+    versions: (u32, u32),
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Collapse {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .partial_cmp(&self.cost)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| (other.from, other.to).cmp(&(self.from, self.to)))
+    }
+}
+
+/// Collapses work on positions, vertices split along seams move together. A
+/// position's version changes with its quadric, queued collapses with old versions are stale
+struct Simplifier<'a> {
+    mesh: &'a Mesh,
+    position_of: Vec<usize>,
+    positions: Vec<Vector3>,
+    vertices_at: Vec<Vec<u32>>,
+    quadrics: Vec<Quadric>,
+    versions: Vec<u32>,
+    removed: Vec<bool>,
+    /// may still list triangles that moved away or died
+    triangles_at: Vec<Vec<usize>>,
+    triangles: Vec<[u32; 3]>,
+    alive: Vec<bool>,
+    queue: BinaryHeap<Collapse>,
+}
+
+impl<'a> Simplifier<'a> {
+    fn new(mesh: &'a Mesh) -> Self {
+        let mut ids: HashMap<Vec<u32>, usize> = HashMap::new();
+        let mut positions = Vec::new();
+        let mut vertices_at: Vec<Vec<u32>> = Vec::new();
+        let position_of: Vec<usize> = mesh
+            .vertices
+            .iter()
+            .enumerate()
+            .map(|(index, vertex)| {
+                let id = *ids.entry(key(&vertex.position)).or_insert_with(|| {
+                    positions.push(to_vector(vertex.position));
+                    vertices_at.push(Vec::new());
+                    positions.len() - 1
+                });
+                vertices_at[id].push(index as u32);
+                id
+            })
+            .collect();
+
+        let count = positions.len();
+        let mut simplifier = Simplifier {
+            mesh,
+            position_of,
+            positions,
+            vertices_at,
+            quadrics: vec![Quadric::default(); count],
+            versions: vec![0; count],
+            removed: vec![false; count],
+            triangles_at: vec![Vec::new(); count],
+            triangles: mesh.triangles().collect(),
+            alive: Vec::new(),
+            queue: BinaryHeap::new(),
+        };
+
+        // edges seen once are on a border, with the normal of their triangle
+        let mut edges: BTreeMap<(usize, usize), (usize, Vector3)> = BTreeMap::new();
+        for triangle in 0..simplifier.triangles.len() {
+            let [a, b, c] = simplifier.corners(triangle);
+            let alive = a != b && b != c && c != a;
+            simplifier.alive.push(alive);
+            if !alive {
+                continue;
+            }
+
+            let normal = simplifier.normal(triangle, None);
+            let area = normal.length() as f64 / 2.0;
+            if area > 0.0 {
+                let plane = Quadric::plane(normal.normalized(), simplifier.positions[a], area);
+                for &corner in &[a, b, c] {
+                    simplifier.quadrics[corner] = simplifier.quadrics[corner] + plane;
+                }
+            }
+
+            for &(from, to) in &[(a, b), (b, c), (c, a)] {
+                simplifier.triangles_at[from].push(triangle);
+                let edge = (from.min(to), from.max(to));
+                let count = edges.entry(edge).or_insert((0, normal));
+                count.0 += 1;
+            }
+        }
+
+        for ((a, b), (count, normal)) in edges {
+            let edge = simplifier.positions[b] - simplifier.positions[a];
+            let border = normal.cross(edge);
+            if count == 1 && border.length() > 0.0 {
+                let weight = BORDER_WEIGHT * (edge.length() as f64).powi(2);
+                let plane = Quadric::plane(border.normalized(), simplifier.positions[a], weight);
+                simplifier.quadrics[a] = simplifier.quadrics[a] + plane;
+                simplifier.quadrics[b] = simplifier.quadrics[b] + plane;
+            }
+        }
+
+        for position in 0..count {
+            simplifier.queue_collapses(position);
+        }
+        simplifier
+    }
+
+    fn corners(&self, triangle: usize) -> [usize; 3] {
+        let [a, b, c] = self.triangles[triangle];
+        [
+            self.position_of[a as usize],
+            self.position_of[b as usize],
+            self.position_of[c as usize],
+        ]
+    }
+
+    /// not normalized, with `moved` as (from, to) the normal after that collapse
+    fn normal(&self, triangle: usize, moved: Option<(usize, usize)>) -> Vector3 {
+        let [a, b, c] = self.corners(triangle).map(|corner| match moved {
+            Some((from, to)) if corner == from => self.positions[to],
+            _ => self.positions[corner],
+        });
+        (b - a).cross(c - a)
+    }
+
+    fn live_triangles_at(&self, position: usize) -> Vec<usize> {
+        let mut triangles: Vec<usize> = self.triangles_at[position]
+            .iter()
+            .cloned()
+            .filter(|&triangle| self.alive[triangle] && self.corners(triangle).contains(&position))
+            .collect();
+        triangles.sort_unstable();
+        triangles.dedup();
+        triangles
+    }
+
+    fn neighbours(&self, position: usize) -> BTreeSet<usize> {
+        self.live_triangles_at(position)
+            .iter()
+            .flat_map(|&triangle| self.corners(triangle).to_vec())
+            .filter(|&corner| corner != position)
+            .collect()
+    }
+
+    fn queue_collapses(&mut self, position: usize) {
+        for neighbour in self.neighbours(position) {
+            for &(from, to) in &[(position, neighbour), (neighbour, position)] {
+                let quadric = self.quadrics[from] + self.quadrics[to];
+                self.queue.push(Collapse {
+                    cost: quadric.error(self.positions[to]),
+                    from,
+                    to,
+                    versions: (self.versions[from], self.versions[to]),
+                });
+            }
+        }
+    }
+
+    /// the edge must still be there, the two ends may only share the neighbours across
+    /// the edge's triangles, and no triangle may flip over
+    fn can_collapse(&self, from: usize, to: usize) -> bool {
+        let triangles = self.live_triangles_at(from);
+        let shared = triangles
+            .iter()
+            .filter(|&&triangle| self.corners(triangle).contains(&to))
+            .count();
+        if shared == 0 {
+            return false;
+        }
+
+        let common = self
+            .neighbours(from)
+            .intersection(&self.neighbours(to))
+            .count();
+        if common != shared {
+            return false;
+        }
+
+        triangles
+            .iter()
+            .filter(|&&triangle| !self.corners(triangle).contains(&to))
+            .all(|&triangle| {
+                let before = self.normal(triangle, None);
+                let after = self.normal(triangle, Some((from, to)));
+                before.length() > 0.0
+                    && after.length() > 0.0
+                    && before.normalized().dot(after.normalized()) >= MIN_FLIP_COSINE
+            })
+    }
+
+    /// the vertex at `position` that looks most like `vertex`, for corners that move there
+    fn closest_vertex(&self, position: usize, vertex: u32) -> u32 {
+        let attributes = |index: u32| {
+            let vertex = &self.mesh.vertices[index as usize];
+            let mut values = vertex.normal.to_vec();
+            values.extend_from_slice(&vertex.tex_coords);
+            values.extend_from_slice(&vertex.color);
+            values
+        };
+        let target = attributes(vertex);
+        let distance = |index: &u32| -> f32 {
+            attributes(*index)
+                .iter()
+                .zip(&target)
+                .map(|(a, b)| (a - b) * (a - b))
+                .sum()
+        };
+
+        *self.vertices_at[position]
+            .iter()
+            .min_by(|a, b| {
+                distance(a)
+                    .partial_cmp(&distance(b))
+                    .unwrap_or(Ordering::Equal)
+            })
+            .unwrap()
+    }
+
+    /// returns how many triangles went away
+    fn collapse(&mut self, from: usize, to: usize) -> usize {
+        let mut gone = 0;
+        for triangle in self.live_triangles_at(from) {
+            if self.corners(triangle).contains(&to) {
+                self.alive[triangle] = false;
+                gone += 1;
+                continue;
+            }
+
+            for corner in 0..3 {
+                let vertex = self.triangles[triangle][corner];
+                if self.position_of[vertex as usize] == from {
+                    self.triangles[triangle][corner] = self.closest_vertex(to, vertex);
+                }
+            }
+            self.triangles_at[to].push(triangle);
+        }
+
+        self.quadrics[to] = self.quadrics[to] + self.quadrics[from];
+        self.removed[from] = true;
+        self.versions[to] += 1;
+        gone
+    }
+
+    fn run(&mut self, target: usize) {
+        let mut live = self.alive.iter().filter(|&&alive| alive).count();
+        while live > target {
+            let collapse = match self.queue.pop() {
+                Some(collapse) => collapse,
+                None => break,
+            };
+            let (from, to) = (collapse.from, collapse.to);
+            if self.removed[from]
+                || self.removed[to]
+                || collapse.versions != (self.versions[from], self.versions[to])
+                || !self.can_collapse(from, to)
+            {
+                continue;
+            }
+
+            live -= self.collapse(from, to);
+            self.queue_collapses(to);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matrices::MatrixOperation;
+    use crate::shapes;
+
+    fn area(mesh: &Mesh) -> f32 {
+        mesh.triangles()
+            .map(|triangle| mesh.face_normal(triangle).length() / 2.0)
+            .sum()
+    }
+
+    fn volume(mesh: &Mesh) -> f32 {
+        mesh.triangles()
+            .map(|[a, b, c]| {
+                let position = |index: u32| to_vector(mesh.vertices[index as usize].position);
+                position(a).dot(position(b).cross(position(c))) / 6.0
+            })
+            .sum()
+    }
+
+    fn faces_match_normals(mesh: &Mesh) -> bool {
+        mesh.triangles().all(|triangle| {
+            let face = mesh.face_normal(triangle);
+            triangle
+                .iter()
+                .all(|&index| face.dot(to_vector(mesh.vertices[index as usize].normal)) > 0.0)
+        })
+    }
+
+    #[test]
+    fn flat_normals_split_every_corner() {
+        let mut mesh = shapes::sphere(1.0, 8, 4);
+        let triangles = mesh.indices.len();
+        mesh.flat_normals();
+
+        assert_eq!(mesh.vertices.len(), triangles);
+        for triangle in mesh.triangles() {
+            let face = mesh.face_normal(triangle).normalized();
+            for &index in &triangle {
+                let normal = to_vector(mesh.vertices[index as usize].normal);
+                assert!(face.dot(normal) > 0.9999);
+            }
+        }
+    }
+
+    #[test]
+    fn smooth_normals_keep_edges_sharper_than_the_angle() {
+        let mut mesh = shapes::cylinder(1.0, 2.0, 16, 1);
+        mesh.flat_normals();
+        mesh.smooth_normals(60.0);
+        assert!(faces_match_normals(&mesh));
+        for vertex in mesh
+            .vertices
+            .iter()
+            .filter(|vertex| vertex.position[1] == 1.0)
+        {
+            let normal = vertex.normal;
+            let radius = Vector3::new(vertex.position[0], 0.0, vertex.position[2]);
+            // the cap stays flat, the side only faces out
+            assert!(
+                normal[1] > 0.9999
+                    || (normal[1].abs() < 1e-5
+                        && to_vector(normal).dot(radius.normalized()) > 0.99)
+            );
+        }
+        // shared again, only the seam of the texture keeps two columns
+        let side_vertices = mesh
+            .vertices
+            .iter()
+            .filter(|vertex| vertex.normal[1].abs() < 1e-5)
+            .count();
+        assert_eq!(side_vertices, 2 * 16 + 2);
+
+        mesh.smooth_normals(180.0);
+        let rim = mesh
+            .vertices
+            .iter()
+            .find(|vertex| vertex.position == [1.0, 1.0, 0.0])
+            .unwrap();
+        assert!(rim.normal[0] > 0.1 && rim.normal[1] > 0.1);
+    }
+
+    #[test]
+    fn tangents_follow_the_texture() {
+        let mut mesh = shapes::plane(2.0, 2.0, 2, 2);
+        assert!(mesh.generate_tangents());
+        assert!(mesh.has_tangents());
+        assert_eq!(mesh.vertices.len(), 9);
+        for tangent in &mesh.tangents {
+            assert!(close(tangent, &[1.0, 0.0, 0.0, 1.0], 1e-5));
+        }
+
+        let mut mesh = shapes::torus(2.0, 0.5, 24, 12);
+        assert!(mesh.generate_tangents());
+        for (vertex, tangent) in mesh.vertices.iter().zip(&mesh.tangents) {
+            let direction = Vector3::new(tangent[0], tangent[1], tangent[2]);
+            assert!((direction.length() - 1.0).abs() < 1e-4);
+            assert!(direction.dot(to_vector(vertex.normal)).abs() < 1e-4);
+        }
+
+        assert!(!Mesh::default().generate_tangents());
+    }
+
+    #[test]
+    fn changing_normals_clears_tangents() {
+        let mut mesh = shapes::plane(1.0, 1.0, 1, 1);
+        mesh.generate_tangents();
+        mesh.smooth_normals(30.0);
+        assert!(!mesh.has_tangents());
+    }
+
+    #[test]
+    fn weld_merges_close_vertices() {
+        let plane = shapes::plane(1.0, 1.0, 1, 1);
+        let mut nudged = plane.clone();
+        for vertex in &mut nudged.vertices {
+            vertex.position[0] += 1e-5;
+        }
+        let mut mesh = plane.clone();
+        mesh.append(&nudged);
+
+        let mut strict = mesh.clone();
+        strict.weld(0.0);
+        assert_eq!(strict.vertices.len(), 8);
+
+        mesh.weld(1e-4);
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.indices.len(), 12);
+        assert!(mesh.indices.iter().all(|&index| index < 4));
+    }
+
+    #[test]
+    fn weld_keeps_seams_and_drops_collapsed_triangles() {
+        // the sphere repeats its first column at u = 1
+        let mut mesh = shapes::sphere(1.0, 8, 4);
+        let vertices = mesh.vertices.len();
+        mesh.weld(1e-4);
+        assert_eq!(mesh.vertices.len(), vertices);
+
+        let mut sliver = shapes::plane(1.0, 1.0, 1, 1);
+        sliver.vertices[1].position = sliver.vertices[0].position;
+        sliver.vertices[1].tex_coords = sliver.vertices[0].tex_coords;
+        sliver.weld(1e-4);
+        assert_eq!(sliver.vertices.len(), 3);
+        assert_eq!(sliver.indices.len(), 3);
+    }
+
+    #[test]
+    fn merge_moves_and_mirrors_parts() {
+        let plane = shapes::plane(1.0, 1.0, 1, 1);
+        let moved = MatrixOperation::translation(Vector3::new(0.0, 2.0, 0.0));
+        let mirrored = MatrixOperation::scale(Vector3::new(-1.0, 1.0, 1.0));
+        let mesh = Mesh::merge(&[(&plane, moved), (&plane, mirrored)]);
+
+        assert_eq!(mesh.vertices.len(), 8);
+        assert_eq!(mesh.indices.len(), 12);
+        assert!(mesh.vertices[..4]
+            .iter()
+            .all(|vertex| vertex.position[1] == 2.0));
+        assert!(faces_match_normals(&mesh));
+        assert!(mesh
+            .vertices
+            .iter()
+            .all(|vertex| vertex.normal == [0.0, 1.0, 0.0]));
+    }
+
+    #[test]
+    fn merge_flips_the_bitangent_of_mirrored_parts() {
+        let mut plane = shapes::plane(1.0, 1.0, 1, 1);
+        plane.generate_tangents();
+        let mirrored = MatrixOperation::scale(Vector3::new(-1.0, 1.0, 1.0));
+        let mesh = Mesh::merge(&[(&plane, Matrix4::identity()), (&plane, mirrored)]);
+
+        assert!(mesh.has_tangents());
+        assert!(close(&mesh.tangents[0], &[1.0, 0.0, 0.0, 1.0], 1e-5));
+        assert!(close(&mesh.tangents[4], &[-1.0, 0.0, 0.0, -1.0], 1e-5));
+    }
+
+    #[test]
+    fn simplify_reaches_the_target() {
+        let mut mesh = shapes::sphere(1.0, 32, 16);
+        let before = volume(&mesh);
+        mesh.simplify(200);
+
+        let triangles = mesh.indices.len() / 3;
+        assert!(
+            triangles <= 200 && triangles > 150,
+            "{} triangles",
+            triangles
+        );
+        assert!(faces_match_normals(&mesh));
+        assert!((volume(&mesh) - before).abs() < 0.1 * before);
+
+        let mut used = vec![false; mesh.vertices.len()];
+        for &index in &mesh.indices {
+            used[index as usize] = true;
+        }
+        assert!(used.iter().all(|&used| used));
+    }
+
+    #[test]
+    fn simplify_keeps_flat_borders() {
+        let mut mesh = shapes::plane(2.0, 2.0, 8, 8);
+        mesh.simplify(2);
+
+        assert!(mesh.indices.len() / 3 < 16);
+        assert!((area(&mesh) - 4.0).abs() < 1e-4);
+        for corner in &[[-1.0, 0.0, -1.0], [1.0, 0.0, 1.0]] {
+            assert!(mesh
+                .vertices
+                .iter()
+                .any(|vertex| &vertex.position == corner));
+        }
+    }
+}
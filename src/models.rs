@@ -1,43 +1,186 @@
-use crate::math::{Matrix4, Quaternion, Vector3};
+use crate::config::{WindowMode, WorldConfig};
+use crate::export::{export_meshes, ExportError, ExportFormat, ExportMesh};
+use crate::fog::Fog;
+use crate::lights::Light;
+use crate::materials::Material;
+use crate::math::{Aabb, Frustum, Matrix4, Quaternion, Ray, Sphere, Triangle, Vector3};
 use crate::matrices::MatrixOperation;
-use crate::primitives::Vertex;
+use crate::physics::{PhysicsWorld, RigidBody};
+use crate::post_processing::PostProcessing;
+use crate::primitives::{
+    check_attributes, InstanceAttributes, MeshVertex, MissingAttributes, Primitive, Vertex,
+};
+use crate::render_target::RenderTarget;
+use crate::scene::{
+    local_transforms, CameraDescription, InstanceDescription, LightDescription,
+    MaterialDescription, RenderTargetDescription, Scene, SceneError, SceneNames, Transform,
+};
 use crate::shaders::{FragmentShader, VertexShader};
+use crate::spatial::{ProxyId, SpatialIndex};
 
 use glium::backend::glutin::Display;
-use glium::{DrawParameters, Program};
+use glium::framebuffer::{DepthRenderBuffer, SimpleFrameBuffer};
+use glium::index::{IndexBufferAny, IndicesSource, NoIndices, PrimitiveType};
+use glium::texture::{DepthFormat, MipmapsOption, RawImage2d, UncompressedFloatFormat};
+use glium::vertex::VerticesSource;
+use glium::{DrawParameters, Program, Rect, Texture2d};
 use glium::{IndexBuffer, VertexBuffer};
+use std::any::Any;
+use std::cell::{RefCell, RefMut};
+use std::cmp::Ordering;
 use std::collections::hash_set::Iter;
 use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::ops::{Deref, Range};
+use std::rc::Rc;
 use std::sync::Arc;
+use std::time::Instant;
 
 use glium::glutin::{
-    event::{ElementState, KeyboardInput, MouseScrollDelta, VirtualKeyCode},
+    event::{ElementState, KeyboardInput, MouseButton, MouseScrollDelta, VirtualKeyCode},
     event_loop::EventLoop,
 };
-use glium::{glutin, Surface};
+use glium::Surface;
+use serde::{Deserialize, Serialize};
+
+pub const Z_NEAR: f32 = 1.0;
+pub const Z_FAR: f32 = 1000.0;
+pub const VIEW_ANGLE: f32 = 45.0;
+/// the camera given to `World::new`
+pub const MAIN_CAMERA: &str = "main";
+const INITIAL_INSTANCE_CAPACITY: usize = 1024;
+const SPATIAL_INDEX_MARGIN: f32 = 0.5;
+/// longer frames, like the first one or after dragging the window, reach the update as this
+const MAX_FRAME_TIME: f32 = 0.1;
+
+/// what happened on the last `draw_update`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FrameStats {
+    pub drawn: usize,
+    pub culled: usize,
+    pub draw_calls: usize,
+}
+
+impl fmt::Display for FrameStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} drawn, {} culled, {} draw calls",
+            self.drawn, self.culled, self.draw_calls
+        )
+    }
+}
+
+impl FrameStats {
+    /// every camera adds its own draws
+    fn add(&mut self, other: FrameStats) {
+        self.drawn += other.drawn;
+        self.culled += other.culled;
+        self.draw_calls += other.draw_calls;
+    }
+}
 
-const Z_NEAR: f32 = 1.0;
-const Z_FAR: f32 = 1000.0;
-const VIEW_ANGLE: f32 = 45.0;
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PickingMode {
+    /// ray against instance bounds, then against the triangles of the closest candidates
+    Geometry,
+    /// renders instance ids to an offscreen buffer and reads the pixel under the cursor
+    IdBuffer,
+}
+
+#[derive(Debug, Clone)]
+pub struct PickResult {
+    pub name: String,
+    pub point: Vector3,
+    pub distance: f32,
+}
 
 pub struct World<'a> {
     pub display: Display,
+    config: WorldConfig,
+    /// some settings can only change by creating the context again
+    rebuild_requested: bool,
     draw_parameters: DrawParameters<'a>,
     program: Program,
-    perspective_matrix: Matrix4,
-    camera: Camera,
+    id_program: Program,
+    picking_mode: PickingMode,
+    instance_buffer: VertexBuffer<InstanceAttributes>,
+    cameras: HashMap<String, Camera>,
+    render_targets: HashMap<String, RenderTarget>,
+    /// shared geometry and materials, by the names scenes use for them
+    prefabs: HashMap<String, Rc<Prefab>>,
+    materials: HashMap<String, Arc<Material>>,
+    lights: HashMap<String, Light>,
+    /// bound for materials without texture, so one shader serves both
+    white_texture: Texture2d,
+    fog: Fog,
+    /// when set, the window cameras draw into its HDR scene texture instead of the window
+    post_processing: Option<PostProcessing>,
+    frame_stats: FrameStats,
+    /// kept between clicks, made again when the window size changes
+    id_buffer: RefCell<Option<IdBuffer>>,
+    default_material: Arc<Material>,
     device_manager: DeviceManager,
     instances: HashMap<String, Instance>,
-    update: Option<Box<dyn FnMut(&DeviceManager, &mut HashMap<String, Instance>, &mut Camera)>>,
+    /// the parent of each instance that has one, by name. Only scenes are parented,
+    /// children are moved with their parent when loaded and are independent after that
+    parents: HashMap<String, String>,
+    spatial_index: SpatialIndex<String>,
+    proxies: HashMap<String, ProxyId>,
+    physics: PhysicsWorld,
+    last_update: Instant,
+    update: Option<UpdateFn>,
 }
 
+/// color and depth the pick ids are drawn into, as big as the window
+struct IdBuffer {
+    color: Texture2d,
+    depth: DepthRenderBuffer,
+    size: (u32, u32),
+}
+
+impl IdBuffer {
+    fn new(display: &Display, width: u32, height: u32) -> Self {
+        IdBuffer {
+            color: Texture2d::empty_with_format(
+                display,
+                UncompressedFloatFormat::U8U8U8U8,
+                MipmapsOption::NoMipmap,
+                width,
+                height,
+            )
+            .unwrap(),
+            depth: DepthRenderBuffer::new(display, DepthFormat::I24, width, height).unwrap(),
+            size: (width, height),
+        }
+    }
+}
+
+/// the last argument is the time since the last frame, in seconds
+type UpdateFn = Box<
+    dyn FnMut(
+        &DeviceManager,
+        &mut HashMap<String, Instance>,
+        &mut PhysicsWorld,
+        &mut HashMap<String, Camera>,
+        f32,
+    ),
+>;
+
 impl<'a> World<'static> {
+    #[allow(dead_code)]
     pub fn new(event_loop: &EventLoop<()>, camera: Camera) -> World<'static> {
-        let wb = glutin::window::WindowBuilder::new()
-            .with_title("Hello OpenGL - focus on game math")
-            .with_inner_size(glutin::dpi::LogicalSize::new(600.0, 600.0));
+        World::with_config(event_loop, camera, WorldConfig::default())
+    }
 
-        let cb = glutin::ContextBuilder::new();
+    pub fn with_config(
+        event_loop: &EventLoop<()>,
+        camera: Camera,
+        config: WorldConfig,
+    ) -> World<'static> {
+        let wb = config.window_builder(event_loop);
+        let cb = config.context_builder();
         let display = glium::Display::new(wb, cb, &event_loop).unwrap();
 
         let draw_parameters = glium::DrawParameters {
@@ -54,32 +197,490 @@ impl<'a> World<'static> {
         // uniforms are completely related to shaders, so it must be improved to be a generic solution
         let program = glium::Program::from_source(
             &display,
-            &VertexShader::color_world_model_camera_clip(),
+            &VertexShader::color_instanced(),
             &FragmentShader::smooth_color(),
             None,
         )
         .unwrap();
 
-        let perspective_matrix = MatrixOperation::perspective(1.0, VIEW_ANGLE, Z_NEAR, Z_FAR);
+        let id_program = glium::Program::from_source(
+            &display,
+            &VertexShader::id_instanced(),
+            &FragmentShader::flat_id(),
+            None,
+        )
+        .unwrap();
+
+        let instance_buffer =
+            glium::VertexBuffer::empty_dynamic(&display, INITIAL_INSTANCE_CAPACITY).unwrap();
+
+        let mut cameras = HashMap::new();
+        cameras.insert(String::from(MAIN_CAMERA), camera);
+
+        let white_texture =
+            Texture2d::new(&display, RawImage2d::from_raw_rgba(vec![255u8; 4], (1, 1))).unwrap();
 
         let device_manager = DeviceManager::new();
 
+        let mut prefabs = HashMap::new();
+        prefabs.insert(String::from("cube"), Primitive::cube(display.clone()));
+        prefabs.insert(String::from("quad"), Primitive::quad(display.clone()));
+        prefabs.insert(String::from("sphere"), Primitive::sphere(display.clone()));
+        prefabs.insert(
+            String::from("icosphere"),
+            Primitive::icosphere(display.clone()),
+        );
+        prefabs.insert(
+            String::from("cylinder"),
+            Primitive::cylinder(display.clone()),
+        );
+        prefabs.insert(String::from("cone"), Primitive::cone(display.clone()));
+        prefabs.insert(String::from("torus"), Primitive::torus(display.clone()));
+        prefabs.insert(String::from("plane"), Primitive::plane(display.clone()));
+        prefabs.insert(String::from("capsule"), Primitive::capsule(display.clone()));
+        prefabs.insert(String::from("arrow"), Primitive::arrow(display.clone()));
+
         World {
             display,
+            config,
+            rebuild_requested: false,
             draw_parameters,
             program,
-            perspective_matrix,
-            camera,
+            id_program,
+            picking_mode: PickingMode::Geometry,
+            instance_buffer,
+            cameras,
+            render_targets: HashMap::new(),
+            prefabs,
+            materials: HashMap::new(),
+            lights: HashMap::new(),
+            white_texture,
+            fog: Fog::off(),
+            post_processing: None,
+            frame_stats: FrameStats::default(),
+            id_buffer: RefCell::new(None),
+            default_material: Material::opaque(),
             device_manager,
             instances: HashMap::new(),
+            parents: HashMap::new(),
+            spatial_index: SpatialIndex::new(SPATIAL_INDEX_MARGIN),
+            proxies: HashMap::new(),
+            physics: PhysicsWorld::new(),
+            last_update: Instant::now(),
             update: None,
         }
     }
 
+    /// the instance starts without parent
     pub fn add_instance(&mut self, name: String, instance: Instance) {
+        self.parents.remove(&name);
         self.instances.insert(name, instance);
     }
 
+    #[allow(dead_code)]
+    pub fn get_parent(&self, name: &str) -> Option<&str> {
+        self.parents.get(name).map(String::as_str)
+    }
+
+    /// the body starts where the instance with the same name is, and moves it from then on.
+    /// Static bodies keep the transform they were added with.
+    pub fn add_rigid_body(&mut self, name: String, body: RigidBody) {
+        let body = match self.instances.get(&name) {
+            Some(instance) => body.with_transform(instance.operations),
+            None => body,
+        };
+
+        self.physics.add_body(name, body);
+    }
+
+    /// drawn on its viewport after the cameras with lower order
+    #[allow(dead_code)]
+    pub fn add_camera(&mut self, name: String, camera: Camera) {
+        self.cameras.insert(name, camera);
+    }
+
+    #[allow(dead_code)]
+    pub fn remove_camera(&mut self, name: &str) -> Option<Camera> {
+        self.cameras.remove(name)
+    }
+
+    #[allow(dead_code)]
+    pub fn get_camera(&self, name: &str) -> Option<&Camera> {
+        self.cameras.get(name)
+    }
+
+    #[allow(dead_code)]
+    pub fn get_camera_mut(&mut self, name: &str) -> Option<&mut Camera> {
+        self.cameras.get_mut(name)
+    }
+
+    /// cameras with `render_target` set to `name` draw into it, before the window cameras
+    #[allow(dead_code)]
+    pub fn add_render_target(&mut self, name: String, render_target: RenderTarget) {
+        self.render_targets.insert(name, render_target);
+    }
+
+    #[allow(dead_code)]
+    pub fn remove_render_target(&mut self, name: &str) -> Option<RenderTarget> {
+        self.render_targets.remove(name)
+    }
+
+    #[allow(dead_code)]
+    pub fn get_render_target(&self, name: &str) -> Option<&RenderTarget> {
+        self.render_targets.get(name)
+    }
+
+    /// "cube", "quad" and the shapes made by `Primitive`, like "sphere", are always there
+    #[allow(dead_code)]
+    pub fn add_prefab(&mut self, name: String, prefab: Rc<Prefab>) {
+        self.prefabs.insert(name, prefab);
+    }
+
+    pub fn get_prefab(&self, name: &str) -> Option<Rc<Prefab>> {
+        self.prefabs.get(name).cloned()
+    }
+
+    /// only named materials can be saved with a scene
+    pub fn add_material(&mut self, name: String, material: Arc<Material>) {
+        self.materials.insert(name, material);
+    }
+
+    #[allow(dead_code)]
+    pub fn get_material(&self, name: &str) -> Option<Arc<Material>> {
+        self.materials.get(name).cloned()
+    }
+
+    #[allow(dead_code)]
+    pub fn add_light(&mut self, name: String, light: Light) {
+        self.lights.insert(name, light);
+    }
+
+    #[allow(dead_code)]
+    pub fn remove_light(&mut self, name: &str) -> Option<Light> {
+        self.lights.remove(name)
+    }
+
+    #[allow(dead_code)]
+    pub fn get_light(&self, name: &str) -> Option<&Light> {
+        self.lights.get(name)
+    }
+
+    #[allow(dead_code)]
+    pub fn lights(&self) -> &HashMap<String, Light> {
+        &self.lights
+    }
+
+    /// every mesh becomes an instance named "name/mesh", placed at `transform`.
+    /// Prefabs and materials are registered under the same names, so scenes can use them
+    #[allow(dead_code)]
+    pub fn add_model(&mut self, name: &str, model: &Model, transform: Matrix4) {
+        for mesh in &model.meshes {
+            let mesh_name = format!("{}/{}", name, mesh.name);
+            self.add_prefab(mesh_name.clone(), mesh.prefab.clone());
+
+            let mut instance = Instance::new(mesh.prefab.clone());
+            instance.set_transform(transform);
+            if let Some(material) = &mesh.material {
+                self.add_material(mesh_name.clone(), material.clone());
+                instance.set_material(material.clone());
+            }
+            self.add_instance(mesh_name, instance);
+        }
+    }
+
+    /// what a scene may refer to without declaring it
+    pub fn scene_names(&self) -> SceneNames {
+        SceneNames {
+            prefabs: self.prefabs.keys().cloned().collect(),
+            materials: self.materials.keys().cloned().collect(),
+            render_targets: self.render_targets.keys().cloned().collect(),
+        }
+    }
+
+    /// adds everything in `scene`, replacing what has the same name.
+    /// Nothing changes when the scene has an error
+    pub fn load_scene(&mut self, scene: &Scene) -> Result<(), SceneError> {
+        scene.validate(&self.scene_names())?;
+        let transforms = scene.world_transforms()?;
+
+        for target in &scene.render_targets {
+            let render_target = RenderTarget::new(&self.display, target.width, target.height);
+            self.add_render_target(target.name.clone(), render_target);
+        }
+
+        for description in &scene.materials {
+            let material = match &description.texture {
+                Some(texture) => Material::textured(
+                    description.color,
+                    description.blend_mode,
+                    self.render_targets[texture].texture(),
+                ),
+                None => Material::new(description.color, description.blend_mode),
+            };
+            self.add_material(description.name.clone(), material);
+        }
+
+        for description in &scene.instances {
+            let mut instance = Instance::new(self.prefabs[&description.prefab].clone());
+            instance.set_transform(transforms[&description.name]);
+            instance.set_color(description.color);
+            if let Some(material) = &description.material {
+                instance.set_material(self.materials[material].clone());
+            }
+            self.add_instance(description.name.clone(), instance);
+            if let Some(parent) = &description.parent {
+                self.parents
+                    .insert(description.name.clone(), parent.clone());
+            }
+        }
+
+        for description in &scene.lights {
+            self.add_light(description.name.clone(), description.light);
+        }
+
+        for description in &scene.cameras {
+            let mut camera = Camera::new()
+                .with_clip_planes(description.z_near, description.z_far)
+                .with_viewport(description.viewport)
+                .with_order(description.order);
+            camera.projection = description.projection;
+            camera.active = description.active;
+            camera.render_target = description.render_target.clone();
+            camera.operations = Transform {
+                scale: Vector3::new(1.0, 1.0, 1.0),
+                ..description.transform
+            }
+            .to_matrix();
+            if let Some(target) = description.look_at {
+                camera.look_at(description.transform.translation, target);
+            }
+            self.add_camera(description.name.clone(), camera);
+        }
+
+        Ok(())
+    }
+
+    pub fn load_scene_file(&mut self, path: &str) -> Result<(), SceneError> {
+        self.load_scene(&Scene::from_file(path)?)
+    }
+
+    /// everything is saved by name, sorted. Instances keep their parent, with the transform
+    /// relative to it. Textures are saved as the render target that draws them,
+    /// materials with other textures are saved untextured and reported on stderr.
+    /// Instances need a registered prefab, and a registered material when they have one
+    pub fn to_scene(&self) -> Result<Scene, SceneError> {
+        let mut scene = Scene::default();
+
+        let render_target_of = |texture: &Texture2d| {
+            self.render_targets
+                .iter()
+                .find(|(_, render_target)| render_target.is_texture(texture))
+                .map(|(name, _)| name.clone())
+        };
+
+        for (name, render_target) in sorted(&self.render_targets) {
+            scene.render_targets.push(RenderTargetDescription {
+                name: name.clone(),
+                width: render_target.width(),
+                height: render_target.height(),
+            });
+        }
+
+        for (name, material) in sorted(&self.materials) {
+            let texture = match &material.texture {
+                Some(texture) => {
+                    let render_target = render_target_of(texture);
+                    if render_target.is_none() {
+                        eprintln!("material \"{}\" saved without its texture", name);
+                    }
+                    render_target
+                }
+                None => None,
+            };
+
+            scene.materials.push(MaterialDescription {
+                name: name.clone(),
+                color: material.color,
+                blend_mode: material.blend_mode,
+                texture,
+            });
+        }
+
+        let world = self
+            .instances
+            .iter()
+            .map(|(name, instance)| (name.clone(), instance.get_transform()))
+            .collect();
+        let locals = local_transforms(&world, &self.parents);
+
+        for (name, instance) in sorted(&self.instances) {
+            let entry_error = |message: &str| SceneError::Entry {
+                section: "instances",
+                name: name.clone(),
+                message: String::from(message),
+            };
+
+            let prefab = find_name(&self.prefabs, instance.get_prefab())
+                .ok_or_else(|| entry_error("its prefab is not registered on the world"))?;
+            let material = match instance.get_material() {
+                Some(material) => {
+                    Some(find_name(&self.materials, material).ok_or_else(|| {
+                        entry_error("its material is not registered on the world")
+                    })?)
+                }
+                None => None,
+            };
+
+            let (transform, parent) = locals[name].clone();
+
+            scene.instances.push(InstanceDescription {
+                name: name.clone(),
+                prefab,
+                material,
+                color: instance.get_color(),
+                transform,
+                parent,
+            });
+        }
+
+        for (name, light) in sorted(&self.lights) {
+            scene.lights.push(LightDescription {
+                name: name.clone(),
+                light: *light,
+            });
+        }
+
+        for (name, camera) in sorted(&self.cameras) {
+            scene.cameras.push(CameraDescription {
+                name: name.clone(),
+                transform: Transform::from_matrix(camera.operations),
+                look_at: None,
+                projection: camera.projection,
+                z_near: camera.z_near,
+                z_far: camera.z_far,
+                viewport: camera.viewport,
+                order: camera.order,
+                active: camera.active,
+                render_target: camera.render_target.clone(),
+            });
+        }
+
+        Ok(scene)
+    }
+
+    /// RON or JSON, from the file extension
+    pub fn save_scene(&self, path: &str) -> Result<(), SceneError> {
+        self.to_scene()?.save(path)
+    }
+
+    /// every instance with its world transform baked into the vertices, sorted by name
+    pub fn to_export_meshes(&self) -> Vec<ExportMesh> {
+        sorted(&self.instances)
+            .into_iter()
+            .map(|(name, instance)| ExportMesh::from_instance(name, instance))
+            .collect()
+    }
+
+    /// the whole world in one .obj, .stl or .ply file
+    pub fn export(&self, path: &str) -> Result<(), ExportError> {
+        export_meshes(
+            &self.to_export_meshes(),
+            path,
+            ExportFormat::from_path(path)?,
+        )
+    }
+
+    fn sorted_cameras(&self, render_target: Option<&str>) -> Vec<&Camera> {
+        cameras_in_draw_order(&self.cameras, render_target)
+    }
+
+    /// the last drawn camera under the cursor, with the cursor as window fractions
+    fn camera_at(&self, screen_x: f32, screen_y: f32) -> Option<(&Camera, f32, f32)> {
+        let (width, height) = self.display.get_framebuffer_dimensions();
+        let x = screen_x / width as f32;
+        let y = screen_y / height as f32;
+
+        self.sorted_cameras(None)
+            .into_iter()
+            .rev()
+            .find(|camera| camera.viewport.contains(x, y))
+            .map(|camera| (camera, x, y))
+    }
+
+    fn window_ratio(&self) -> f32 {
+        let (width, height) = self.display.get_framebuffer_dimensions();
+        width as f32 / height.max(1) as f32
+    }
+
+    pub fn config(&self) -> &WorldConfig {
+        &self.config
+    }
+
+    /// the config title stays in front, `text` follows it
+    pub fn set_title_suffix(&self, text: &str) {
+        let title = format!("{} | {}", self.config.title, text);
+        self.display.gl_window().window().set_title(&title);
+    }
+
+    /// applied right away, on the monitor the window is on
+    pub fn set_window_mode(&mut self, window_mode: WindowMode) {
+        self.config.window_mode = window_mode;
+
+        let gl_window = self.display.gl_window();
+        let window = gl_window.window();
+        window.set_fullscreen(self.config.fullscreen(window.current_monitor()));
+    }
+
+    /// between windowed and borderless, or back to windowed from any fullscreen mode
+    pub fn toggle_fullscreen(&mut self) {
+        let window_mode = match self.config.window_mode {
+            WindowMode::Windowed => WindowMode::Borderless,
+            _ => WindowMode::Windowed,
+        };
+        self.set_window_mode(window_mode);
+    }
+
+    /// the swap interval is fixed when the context is created, see `needs_rebuild`
+    pub fn set_vsync(&mut self, vsync: bool) {
+        if self.config.vsync != vsync {
+            self.config.vsync = vsync;
+            self.rebuild_requested = true;
+        }
+    }
+
+    /// the event loop must stop and call `rebuild`, it needs the `EventLoop` itself
+    pub fn needs_rebuild(&self) -> bool {
+        self.rebuild_requested
+    }
+
+    /// creates window and context again from the current config. the new context shares
+    /// the old one's objects, so buffers, textures and programs stay valid
+    pub fn rebuild(&mut self, event_loop: &EventLoop<()>) {
+        let wb = self.config.window_builder(event_loop);
+        let cb = self.config.context_builder();
+        self.display.rebuild(wb, cb, event_loop).unwrap();
+        self.rebuild_requested = false;
+    }
+
+    pub fn set_post_processing(&mut self, post_processing: PostProcessing) {
+        self.post_processing = Some(post_processing);
+    }
+
+    /// passes can be toggled and tuned here at runtime
+    pub fn post_processing_mut(&mut self) -> Option<&mut PostProcessing> {
+        self.post_processing.as_mut()
+    }
+
+    #[allow(dead_code)]
+    pub fn remove_post_processing(&mut self) -> Option<PostProcessing> {
+        self.post_processing.take()
+    }
+
+    /// the clear color follows the fog color, so distant instances fade into the background
+    pub fn set_fog(&mut self, fog: Fog) {
+        self.fog = fog;
+    }
+
     pub fn update_key_manager(&mut self, input: &KeyboardInput) {
         self.device_manager.update_keys(input);
     }
@@ -88,84 +689,1014 @@ impl<'a> World<'static> {
         self.device_manager.update_mouse_motion(delta.0, delta.1);
     }
 
-    pub fn update_mouse_wheel(&mut self, delta: MouseScrollDelta) {}
+    pub fn update_mouse_wheel(&mut self, delta: MouseScrollDelta) {
+        self.device_manager.update_mouse_wheel(delta);
+    }
+
+    pub fn update_mouse_buttons(&mut self, button: MouseButton, state: ElementState) {
+        self.device_manager.update_mouse_buttons(button, state);
+    }
 
     pub fn draw_update(&mut self) {
         let mut target = self.display.draw();
-        target.clear_color_and_depth((0.0, 0.0, 0.0, 1.0), 1.0);
+        target.clear_color_and_depth(self.fog.clear_color(), 1.0);
+
+        let elapsed = self.last_update.elapsed().as_secs_f32();
+        self.last_update = Instant::now();
 
         if let Some(update_action) = &mut self.update {
-            update_action(&self.device_manager, &mut self.instances, &mut self.camera);
-
-            for instance in self.instances.values() {
-                let uniforms = uniform! {
-                    modelToWorldMatrix: instance.operations,
-                    worldToCameraMatrix: self.camera.camera_matrix_from_target(),
-                    cameraToClipMatrix: self.perspective_matrix
-                };
-
-                target
-                    .draw(
-                        &instance.prefab.vertex,
-                        &instance.prefab.indices,
-                        &self.program,
-                        &uniforms,
-                        &self.draw_parameters,
-                    )
-                    .unwrap();
+            update_action(
+                &self.device_manager,
+                &mut self.instances,
+                &mut self.physics,
+                &mut self.cameras,
+                elapsed.min(MAX_FRAME_TIME),
+            );
+            self.step_physics(elapsed);
+            self.refresh_spatial_index();
+
+            self.reserve_instance_buffer();
+            if let Some(post_processing) = &mut self.post_processing {
+                let (width, height) = self.display.get_framebuffer_dimensions();
+                post_processing.resize(width, height);
+            }
+            let mut frame_stats = FrameStats::default();
+
+            // offscreen first, so window cameras see this frame on textured materials
+            let mut render_target_names: Vec<&String> = self.render_targets.keys().collect();
+            render_target_names.sort();
+
+            for name in render_target_names {
+                let cameras = self.sorted_cameras(Some(name));
+                if cameras.is_empty() {
+                    continue;
+                }
+
+                let render_target = &self.render_targets[name];
+                let mut framebuffer = render_target.framebuffer(&self.display);
+                framebuffer.clear_color_and_depth(self.fog.clear_color(), 1.0);
+
+                for camera in cameras {
+                    let stats = self.draw_camera(&mut framebuffer, camera, Some(render_target));
+                    frame_stats.add(stats);
+                }
             }
+
+            match &self.post_processing {
+                Some(post_processing) => {
+                    let mut scene = post_processing.scene().framebuffer(&self.display);
+                    scene.clear_color_and_depth(self.fog.clear_color(), 1.0);
+
+                    for camera in self.sorted_cameras(None) {
+                        let stats = self.draw_camera(&mut scene, camera, None);
+                        frame_stats.add(stats);
+                    }
+
+                    post_processing.apply(&mut target);
+                }
+                None => {
+                    for camera in self.sorted_cameras(None) {
+                        let stats = self.draw_camera(&mut target, camera, None);
+                        frame_stats.add(stats);
+                    }
+                }
+            }
+
+            self.frame_stats = frame_stats;
         }
 
         target.finish().unwrap();
-        self.device_manager.reset_mouse();
+        self.device_manager.reset_frame();
+    }
+
+    /// a camera draws at most every instance, so that is the most the buffer needs per draw
+    fn reserve_instance_buffer(&mut self) {
+        if self.instances.len() > self.instance_buffer.len() {
+            let capacity = self.instances.len().next_power_of_two();
+            self.instance_buffer =
+                glium::VertexBuffer::empty_dynamic(&self.display, capacity).unwrap();
+        }
+    }
+
+    /// culls, batches and draws the instances seen by one camera, on its viewport only.
+    /// instances showing the texture of `render_target` are left out, it is being drawn into
+    fn draw_camera<S: Surface>(
+        &self,
+        target: &mut S,
+        camera: &Camera,
+        render_target: Option<&RenderTarget>,
+    ) -> FrameStats {
+        let (width, height) = target.get_dimensions();
+        let viewport = camera.viewport.to_rect(width, height);
+        if viewport.width == 0 || viewport.height == 0 {
+            return FrameStats::default();
+        }
+
+        // each viewport starts empty, whatever cameras before drew there
+        target.clear(
+            Some(&viewport),
+            Some(self.fog.clear_color()),
+            false,
+            Some(1.0),
+            None,
+        );
+
+        let projection_matrix = camera.projection_matrix(width as f32 / height.max(1) as f32);
+        let camera_position = camera.operations.get_position();
+        let frustum = Frustum::from_matrix(projection_matrix * camera.camera_matrix_from_target());
+        let mut opaque: HashMap<BatchKey, Vec<&Instance>> = HashMap::new();
+        let mut transparent: Vec<&Instance> = Vec::new();
+
+        let instances = &self.instances;
+        let visible = self.spatial_index.query_frustum(&frustum);
+        let culled = instances.len() - visible.len();
+
+        for instance in visible.into_iter().map(|name| &instances[name]) {
+            let material = self.material_of(instance);
+            let feedback = match (render_target, &material.texture) {
+                (Some(render_target), Some(texture)) => render_target.is_texture(texture),
+                _ => false,
+            };
+
+            if feedback {
+                continue;
+            } else if material.is_transparent() {
+                transparent.push(instance);
+            } else {
+                opaque
+                    .entry(instance.batch_key())
+                    .or_default()
+                    .push(instance);
+            }
+        }
+
+        // back to front, so what is behind is already on the target when closer instances blend over it
+        transparent.sort_by(|a, b| {
+            let distance_a = (a.operations.get_position() - camera_position).length();
+            let distance_b = (b.operations.get_position() - camera_position).length();
+            distance_b
+                .partial_cmp(&distance_a)
+                .unwrap_or(Ordering::Equal)
+        });
+
+        // each batch is one instanced draw call over a range of the instance buffer.
+        // transparent instances only share a batch with their neighbours on the sorted list
+        let mut attributes = Vec::with_capacity(self.instances.len());
+        let mut batches: Vec<(&Instance, Range<usize>)> = Vec::new();
+
+        for group in opaque.values() {
+            let start = attributes.len();
+            attributes.extend(group.iter().map(|instance| instance.attributes()));
+            batches.push((group[0], start..attributes.len()));
+        }
+
+        for instance in transparent {
+            attributes.push(instance.attributes());
+
+            match batches.last_mut() {
+                Some((first, range)) if first.batch_key() == instance.batch_key() => {
+                    range.end = attributes.len()
+                }
+                _ => batches.push((instance, attributes.len() - 1..attributes.len())),
+            };
+        }
+
+        if !attributes.is_empty() {
+            let slice = self.instance_buffer.slice(0..attributes.len()).unwrap();
+            slice.invalidate();
+            slice.write(&attributes);
+        }
+
+        let stats = FrameStats {
+            drawn: attributes.len(),
+            culled,
+            draw_calls: batches.len(),
+        };
+
+        let view = CameraView {
+            world_to_camera: camera.camera_matrix_from_target(),
+            camera_to_clip: projection_matrix,
+            viewport,
+        };
+
+        for (instance, range) in batches {
+            self.draw_batch(target, &view, instance, range);
+        }
+
+        stats
+    }
+
+    /// fixed steps over the time since the last frame, then dynamic bodies move their instances
+    fn step_physics(&mut self, elapsed: f32) {
+        if self.physics.update(elapsed) == 0 {
+            return;
+        }
+
+        for (name, body) in self.physics.bodies() {
+            if body.is_static() {
+                continue;
+            }
+
+            if let Some(instance) = self.instances.get_mut(name) {
+                instance.set_transform(body.transform());
+            }
+        }
+    }
+
+    /// instances live on a plain map and are moved freely by the update closure,
+    /// so their bounds are compared against the index once per frame
+    fn refresh_spatial_index(&mut self) {
+        let instances = &self.instances;
+        let spatial_index = &mut self.spatial_index;
+
+        self.proxies.retain(|name, proxy| {
+            let keep = instances.contains_key(name);
+            if !keep {
+                spatial_index.remove(*proxy);
+            }
+            keep
+        });
+
+        for (name, instance) in instances {
+            let bounds = instance.get_world_bounds();
+
+            match self.proxies.get(name) {
+                Some(proxy) => {
+                    spatial_index.update(*proxy, bounds);
+                }
+                None => {
+                    let proxy = spatial_index.insert(bounds, name.clone());
+                    self.proxies.insert(name.clone(), proxy);
+                }
+            };
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn set_picking_mode(&mut self, picking_mode: PickingMode) {
+        self.picking_mode = picking_mode;
+    }
+
+    /// screen coordinates are in physical pixels, starting at the top left corner of the window
+    /// the camera whose viewport is under the cursor does the picking
+    pub fn pick(&self, screen_x: f32, screen_y: f32) -> Option<PickResult> {
+        let (camera, x, y) = self.camera_at(screen_x, screen_y)?;
+        let (ray, max_distance) = self.screen_ray(camera, x, y)?;
+
+        let name = match self.picking_mode {
+            PickingMode::Geometry => return self.pick_geometry(&ray, max_distance),
+            PickingMode::IdBuffer => self.pick_id_buffer(camera, screen_x, screen_y)?,
+        };
+
+        // the id buffer already found the instance, the ray only finds where it was hit
+        let instance = self.instances.get(&name)?;
+        let distance = instance
+            .intersect_ray(&ray)
+            .or_else(|| ray.intersect_aabb(&instance.get_world_bounds()))
+            .unwrap_or(0.0);
+
+        Some(PickResult {
+            name,
+            point: ray.point_at(distance),
+            distance,
+        })
+    }
+
+    /// unprojects the cursor on the near and far planes, the ray goes from one to the other.
+    /// `x` and `y` are window fractions
+    fn screen_ray(&self, camera: &Camera, x: f32, y: f32) -> Option<(Ray, f32)> {
+        let (x, y) = camera.viewport.to_ndc(x, y);
+
+        let clip_to_world = camera.view_projection(self.window_ratio()).inverse()?;
+        let near = clip_to_world.project_point(Vector3::new(x, y, -1.0));
+        let far = clip_to_world.project_point(Vector3::new(x, y, 1.0));
+
+        Some((Ray::new(near, far - near), (far - near).length()))
+    }
+
+    fn pick_geometry(&self, ray: &Ray, max_distance: f32) -> Option<PickResult> {
+        let mut closest: Option<PickResult> = None;
+
+        for (name, bounds_distance) in self.spatial_index.query_ray(ray, max_distance) {
+            // candidates come sorted, nothing after this one can be closer
+            if closest
+                .as_ref()
+                .is_some_and(|hit| hit.distance < bounds_distance)
+            {
+                break;
+            }
+
+            let distance = match self.instances.get(name).and_then(|i| i.intersect_ray(ray)) {
+                Some(distance) => distance,
+                None => continue,
+            };
+
+            if closest.as_ref().is_none_or(|hit| distance < hit.distance) {
+                closest = Some(PickResult {
+                    name: name.clone(),
+                    point: ray.point_at(distance),
+                    distance,
+                });
+            }
+        }
+
+        closest
+    }
+
+    fn pick_id_buffer(&self, camera: &Camera, screen_x: f32, screen_y: f32) -> Option<String> {
+        let (width, height) = self.display.get_framebuffer_dimensions();
+        if screen_x < 0.0 || screen_y < 0.0 || screen_x >= width as f32 || screen_y >= height as f32
+        {
+            return None;
+        }
+
+        // only the pixel under the cursor is rasterized
+        let pixel = Rect {
+            left: screen_x as u32,
+            bottom: height - 1 - screen_y as u32,
+            width: 1,
+            height: 1,
+        };
+
+        let mut id_buffer = self.id_buffer.borrow_mut();
+        if id_buffer.as_ref().map(|buffer| buffer.size) != Some((width, height)) {
+            *id_buffer = Some(IdBuffer::new(&self.display, width, height));
+        }
+        let IdBuffer { color, depth, .. } = id_buffer.as_ref().unwrap();
+        let mut target = SimpleFrameBuffer::with_depth_buffer(&self.display, color, depth).unwrap();
+        target.clear_color_and_depth((0.0, 0.0, 0.0, 0.0), 1.0);
+
+        let names: Vec<&String> = self.instances.keys().collect();
+        let mut groups: HashMap<usize, (&Instance, Vec<InstanceAttributes>)> = HashMap::new();
+
+        for (id, name) in names.iter().enumerate() {
+            let instance = &self.instances[*name];
+            let (prefab, _) = instance.batch_key();
+
+            groups
+                .entry(prefab)
+                .or_insert_with(|| (instance, Vec::new()))
+                .1
+                .push(InstanceAttributes {
+                    model_matrix: instance.operations.to_opengl_array(),
+                    instance_color: encode_pick_id(id),
+                });
+        }
+
+        let draw_parameters = DrawParameters {
+            viewport: Some(camera.viewport.to_rect(width, height)),
+            scissor: Some(pixel),
+            ..self.draw_parameters.clone()
+        };
+
+        let uniforms = uniform! {
+            worldToCameraMatrix: camera.camera_matrix_from_target(),
+            cameraToClipMatrix: camera.projection_matrix(self.window_ratio())
+        };
+
+        for (instance, attributes) in groups.values() {
+            let buffers = instance.prefab.buffers.borrow();
+            if buffers.is_empty() {
+                continue;
+            }
+            let per_instance = glium::VertexBuffer::new(&self.display, attributes).unwrap();
+
+            target
+                .draw(
+                    (buffers.vertices(), per_instance.per_instance().unwrap()),
+                    buffers.indices(),
+                    &self.id_program,
+                    &uniforms,
+                    &draw_parameters,
+                )
+                .unwrap();
+        }
+
+        let read: Vec<Vec<(u8, u8, u8, u8)>> = color
+            .main_level()
+            .first_layer()
+            .into_image(None)
+            .unwrap()
+            .raw_read(&pixel);
+
+        let id = decode_pick_id(read[0][0])?;
+        names.get(id).map(|name| (*name).clone())
+    }
+
+    /// instance bounds as of the last `draw_update`, for frustum, sphere, box and ray queries
+    #[allow(dead_code)]
+    pub fn spatial_index(&self) -> &SpatialIndex<String> {
+        &self.spatial_index
+    }
+
+    fn material_of<'b>(&'b self, instance: &'b Instance) -> &'b Material {
+        instance.material.as_ref().unwrap_or(&self.default_material)
+    }
+
+    fn draw_batch<S: Surface>(
+        &self,
+        target: &mut S,
+        view: &CameraView,
+        instance: &Instance,
+        range: Range<usize>,
+    ) {
+        let material = self.material_of(instance);
+
+        // transparent instances still test against the depth buffer, but must not hide what is drawn after them
+        let draw_parameters = if material.is_transparent() {
+            DrawParameters {
+                depth: glium::Depth {
+                    write: false,
+                    ..self.draw_parameters.depth
+                },
+                blend: material.blend_mode.to_blend(),
+                viewport: Some(view.viewport),
+                ..self.draw_parameters.clone()
+            }
+        } else {
+            DrawParameters {
+                viewport: Some(view.viewport),
+                ..self.draw_parameters.clone()
+            }
+        };
+
+        let uniforms = uniform! {
+            worldToCameraMatrix: view.world_to_camera,
+            cameraToClipMatrix: view.camera_to_clip,
+            materialColor: material.color,
+            materialTexture: material.texture.as_deref().unwrap_or(&self.white_texture),
+            fogMode: self.fog.mode.to_uniform(),
+            fogColor: self.fog.color,
+            fogStart: self.fog.start,
+            fogEnd: self.fog.end,
+            fogDensity: self.fog.density,
+            fogHeightBase: self.fog.height_base(),
+            fogHeightFalloff: self.fog.height_falloff()
+        };
+
+        let buffers = instance.prefab.buffers.borrow();
+        if buffers.is_empty() {
+            return;
+        }
+        let per_instance = self.instance_buffer.slice(range).unwrap();
+
+        target
+            .draw(
+                (buffers.vertices(), per_instance.per_instance().unwrap()),
+                buffers.indices(),
+                &self.program,
+                &uniforms,
+                &draw_parameters,
+            )
+            .unwrap();
     }
 
     pub fn set_update<F>(&mut self, update_fn: F)
     where
-        F: 'static + FnMut(&DeviceManager, &mut HashMap<String, Instance>, &mut Camera),
+        F: 'static
+            + FnMut(
+                &DeviceManager,
+                &mut HashMap<String, Instance>,
+                &mut PhysicsWorld,
+                &mut HashMap<String, Camera>,
+                f32,
+            ),
     {
         self.update.replace(Box::from(update_fn));
     }
 
-    pub fn change_perspective_ratio(&mut self, ratio: f32) {
-        self.perspective_matrix = MatrixOperation::perspective(ratio, VIEW_ANGLE, Z_NEAR, Z_FAR);
+    pub fn frame_stats(&self) -> FrameStats {
+        self.frame_stats
+    }
+
+    /// saves the last finished frame, the format comes from the file extension
+    pub fn screenshot(&self, path: &str) -> image::ImageResult<()> {
+        let raw: RawImage2d<u8> = self.display.read_front_buffer().unwrap();
+        let (width, height) = (raw.width, raw.height);
+        let buffer = image::RgbaImage::from_raw(width, height, raw.data.into_owned()).unwrap();
+
+        // OpenGL rows start at the bottom
+        image::DynamicImage::ImageRgba8(buffer).flipv().save(path)
+    }
+}
+
+/// what `draw_batch` needs from the camera being drawn
+struct CameraView {
+    world_to_camera: Matrix4,
+    camera_to_clip: Matrix4,
+    viewport: Rect,
+}
+
+/// id 0 is left for the cleared background
+fn encode_pick_id(id: usize) -> [f32; 4] {
+    let id = id + 1;
+    [
+        (id & 0xff) as f32 / 255.0,
+        ((id >> 8) & 0xff) as f32 / 255.0,
+        ((id >> 16) & 0xff) as f32 / 255.0,
+        1.0,
+    ]
+}
+
+fn decode_pick_id(pixel: (u8, u8, u8, u8)) -> Option<usize> {
+    let (r, g, b, _) = pixel;
+    let id = r as usize | (g as usize) << 8 | (b as usize) << 16;
+
+    id.checked_sub(1)
+}
+
+/// A loaded file, one mesh per part with its own material.
+pub struct Model {
+    pub meshes: Vec<ModelMesh>,
+}
+
+pub struct ModelMesh {
+    pub name: String,
+    pub prefab: Rc<Prefab>,
+    /// the default material when `None`
+    pub material: Option<Arc<Material>>,
+}
+
+/// entries sorted by name, for files that do not change when nothing changed
+fn sorted<T>(map: &HashMap<String, T>) -> Vec<(&String, &T)> {
+    let mut entries: Vec<(&String, &T)> = map.iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    entries
+}
+
+/// the name `value` was registered with
+fn find_name<P: Deref>(map: &HashMap<String, P>, value: &P::Target) -> Option<String> {
+    map.iter()
+        .find(|&(_, registered)| std::ptr::eq(&**registered, value))
+        .map(|(name, _)| name.clone())
+}
+
+/// strips and fans are turned into separate triangles, lines and points have none
+pub fn triangles_of(primitive_type: PrimitiveType, indices: &[u32]) -> Vec<[usize; 3]> {
+    let index = |i: usize| indices[i] as usize;
+    let count = indices.len().saturating_sub(2);
+
+    match primitive_type {
+        PrimitiveType::TrianglesList => indices
+            .chunks_exact(3)
+            .map(|triangle| {
+                [
+                    triangle[0] as usize,
+                    triangle[1] as usize,
+                    triangle[2] as usize,
+                ]
+            })
+            .collect(),
+        // every other triangle is flipped to keep the winding
+        PrimitiveType::TriangleStrip => (0..count)
+            .map(|i| {
+                if i % 2 == 0 {
+                    [index(i), index(i + 1), index(i + 2)]
+                } else {
+                    [index(i), index(i + 2), index(i + 1)]
+                }
+            })
+            .collect(),
+        PrimitiveType::TriangleFan => (0..count)
+            .map(|i| [index(0), index(i + 1), index(i + 2)])
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// what a prefab is drawn with: u16 or u32 buffers, or the vertices in order
+#[allow(dead_code)]
+pub enum PrefabIndices {
+    None,
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl PrefabIndices {
+    /// u16 when every vertex can be reached with one
+    pub fn smallest(vertex_count: usize, indices: Vec<u32>) -> Self {
+        if vertex_count <= u16::MAX as usize + 1 {
+            PrefabIndices::U16(indices.iter().map(|&index| index as u16).collect())
+        } else {
+            PrefabIndices::U32(indices)
+        }
+    }
+
+    /// which vertices are drawn, in order, `None` draws each vertex once
+    pub fn index_list(&self, vertex_count: usize) -> Vec<u32> {
+        match self {
+            PrefabIndices::None => (0..vertex_count as u32).collect(),
+            PrefabIndices::U16(indices) => indices.iter().copied().map(u32::from).collect(),
+            PrefabIndices::U32(indices) => indices.clone(),
+        }
+    }
+}
+
+enum IndexSource {
+    Buffer(IndexBufferAny),
+    None(NoIndices),
+}
+
+/// The gpu side of a prefab, uploaded once or rewritten by a `DynamicMesh`.
+pub trait PrefabBuffers {
+    fn vertices(&self) -> VerticesSource<'_>;
+    fn indices(&self) -> IndicesSource<'_>;
+    /// empty buffers are not drawn
+    fn is_empty(&self) -> bool;
+    /// lets a `DynamicMesh` get back to its typed buffers
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+/// buffers of any vertex type and index width, written once
+struct StaticBuffers<V: MeshVertex> {
+    vertex: glium::VertexBuffer<V>,
+    indices: IndexSource,
+}
+
+impl<V: MeshVertex> PrefabBuffers for StaticBuffers<V> {
+    fn vertices(&self) -> VerticesSource<'_> {
+        (&self.vertex).into()
+    }
+
+    fn indices(&self) -> IndicesSource<'_> {
+        match &self.indices {
+            IndexSource::Buffer(buffer) => buffer.into(),
+            IndexSource::None(no_indices) => no_indices.into(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.vertex.len() == 0
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// cpu copies of the geometry, used for picking and exporting
+struct Geometry {
+    vertices: Vec<Vertex>,
+    positions: Vec<Vector3>,
+    /// empty for lines and points, which rays can not hit
+    triangles: Vec<[usize; 3]>,
+    bounds: Aabb,
+    bounding_sphere: Sphere,
+}
+
+impl Geometry {
+    fn new(primitive_type: PrimitiveType, vertices: Vec<Vertex>, indices: &[u32]) -> Self {
+        let positions: Vec<Vector3> = vertices
+            .iter()
+            .map(|vertex| Vector3::new(vertex.position[0], vertex.position[1], vertex.position[2]))
+            .collect();
+
+        Geometry {
+            bounds: Aabb::from_points(&positions),
+            bounding_sphere: Sphere::from_points(&positions),
+            // indices past the last vertex are left for the gpu to complain about
+            triangles: triangles_of(primitive_type, indices)
+                .into_iter()
+                .filter(|triangle| triangle.iter().all(|&index| index < positions.len()))
+                .collect(),
+            vertices,
+            positions,
+        }
     }
 }
 
+/// Geometry shared by instances. The buffers and the cpu copies only change through a
+/// `DynamicMesh`, instances see the new geometry on the next frame.
 pub struct Prefab {
-    vertex: VertexBuffer<Vertex>,
-    indices: IndexBuffer<u16>,
+    primitive_type: PrimitiveType,
+    buffers: RefCell<Box<dyn PrefabBuffers>>,
+    geometry: RefCell<Geometry>,
 }
 
+#[allow(dead_code)]
 impl Prefab {
-    pub fn build(display: Display, shape: Vec<Vertex>, indices: Vec<u16>) -> Arc<Self> {
-        let vertex = glium::VertexBuffer::new(&display, &shape).unwrap();
-        let indices = glium::IndexBuffer::new(
-            &display,
-            glium::index::PrimitiveType::TrianglesList,
-            &indices,
+    pub fn build(display: Display, shape: Vec<Vertex>, indices: Vec<u16>) -> Rc<Self> {
+        Prefab::from_vertices(
+            display,
+            &shape,
+            PrefabIndices::U16(indices),
+            PrimitiveType::TrianglesList,
         )
-        .unwrap();
+        .unwrap()
+    }
+
+    /// meshes with more than 65536 vertices keep the u32 indices, smaller ones get u16 buffers
+    pub fn build_u32(display: Display, shape: Vec<Vertex>, indices: Vec<u32>) -> Rc<Self> {
+        Prefab::from_vertices(
+            display,
+            &shape,
+            PrefabIndices::smallest(shape.len(), indices),
+            PrimitiveType::TrianglesList,
+        )
+        .unwrap()
+    }
+
+    /// any vertex type and topology, like lines for gizmos or points for clouds.
+    /// Fails when the vertex type lacks one of the `SHADER_ATTRIBUTES`
+    pub fn from_vertices<V: MeshVertex>(
+        display: Display,
+        shape: &[V],
+        indices: PrefabIndices,
+        primitive_type: PrimitiveType,
+    ) -> Result<Rc<Self>, MissingAttributes> {
+        check_attributes::<V>()?;
+        let vertex = glium::VertexBuffer::new(&display, shape).unwrap();
+        let index_list = indices.index_list(shape.len());
+
+        let indices = match indices {
+            PrefabIndices::None => IndexSource::None(NoIndices(primitive_type)),
+            PrefabIndices::U16(indices) => IndexSource::Buffer(
+                IndexBuffer::new(&display, primitive_type, &indices)
+                    .unwrap()
+                    .into(),
+            ),
+            PrefabIndices::U32(indices) => IndexSource::Buffer(
+                IndexBuffer::new(&display, primitive_type, &indices)
+                    .unwrap()
+                    .into(),
+            ),
+        };
+
+        let vertices = shape.iter().map(MeshVertex::to_vertex).collect();
+        Ok(Prefab::from_buffers(
+            Box::new(StaticBuffers { vertex, indices }),
+            primitive_type,
+            vertices,
+            &index_list,
+        ))
+    }
+
+    /// `indices` says which vertices make the triangles, like the index buffer would
+    pub fn from_buffers(
+        buffers: Box<dyn PrefabBuffers>,
+        primitive_type: PrimitiveType,
+        vertices: Vec<Vertex>,
+        indices: &[u32],
+    ) -> Rc<Self> {
+        Rc::new(Prefab {
+            primitive_type,
+            buffers: RefCell::new(buffers),
+            geometry: RefCell::new(Geometry::new(primitive_type, vertices, indices)),
+        })
+    }
+
+    pub fn buffers_mut(&self) -> RefMut<'_, Box<dyn PrefabBuffers>> {
+        self.buffers.borrow_mut()
+    }
+
+    /// replaces the cpu copies, after the buffers were rewritten
+    pub fn set_geometry(&self, vertices: Vec<Vertex>, indices: &[u32]) {
+        self.geometry
+            .replace(Geometry::new(self.primitive_type, vertices, indices));
+    }
+
+    /// moves the vertices from `start` on, the triangles stay the same.
+    /// Panics when the range goes past the last vertex, before changing any
+    pub fn update_geometry(&self, start: usize, vertices: &[Vertex]) {
+        let mut geometry = self.geometry.borrow_mut();
+        let end = start + vertices.len();
+        assert!(
+            end <= geometry.vertices.len(),
+            "vertices {}..{} out of {}",
+            start,
+            end,
+            geometry.vertices.len()
+        );
+
+        for (offset, vertex) in vertices.iter().enumerate() {
+            let [x, y, z] = vertex.position;
+            geometry.vertices[start + offset] = *vertex;
+            geometry.positions[start + offset] = Vector3::new(x, y, z);
+        }
+        geometry.bounds = Aabb::from_points(&geometry.positions);
+        geometry.bounding_sphere = Sphere::from_points(&geometry.positions);
+    }
+
+    pub fn get_primitive_type(&self) -> PrimitiveType {
+        self.primitive_type
+    }
+
+    /// a copy, a `DynamicMesh` may change the geometry at any time
+    pub fn get_vertices(&self) -> Vec<Vertex> {
+        self.geometry.borrow().vertices.clone()
+    }
+
+    /// indices into `get_vertices`, counter-clockwise
+    pub fn get_triangles(&self) -> Vec<[usize; 3]> {
+        self.geometry.borrow().triangles.clone()
+    }
+
+    /// local space, before any instance transform
+    pub fn get_bounds(&self) -> Aabb {
+        self.geometry.borrow().bounds
+    }
+
+    pub fn get_bounding_sphere(&self) -> Sphere {
+        self.geometry.borrow().bounding_sphere
+    }
+
+    /// closest triangle hit, in local space
+    pub fn intersect_ray(&self, ray: &Ray) -> Option<f32> {
+        let geometry = self.geometry.borrow();
 
-        Arc::new(Prefab { vertex, indices })
+        geometry
+            .triangles
+            .iter()
+            .filter_map(|[a, b, c]| {
+                ray.intersect_triangle(&Triangle::new(
+                    geometry.positions[*a],
+                    geometry.positions[*b],
+                    geometry.positions[*c],
+                ))
+            })
+            .fold(None, |closest: Option<f32>, distance| {
+                Some(closest.map_or(distance, |closest| closest.min(distance)))
+            })
     }
 }
 
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Projection {
+    /// vertical field of view, in degrees
+    Perspective { fov: f32 },
+    /// world units visible from the bottom to the top of the viewport
+    Orthographic { height: f32 },
+}
+
+/// fractions of the window, from the top left corner like screen coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+#[allow(dead_code)]
+impl Viewport {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Viewport {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn full() -> Self {
+        Viewport::new(0.0, 0.0, 1.0, 1.0)
+    }
+
+    /// pixels on a framebuffer of the given size, OpenGL counts rows from the bottom
+    pub fn to_rect(self, framebuffer_width: u32, framebuffer_height: u32) -> Rect {
+        let left = (self.x * framebuffer_width as f32).round() as u32;
+        let top = (self.y * framebuffer_height as f32).round() as u32;
+        let width = (self.width * framebuffer_width as f32).round() as u32;
+        let height = (self.height * framebuffer_height as f32).round() as u32;
+
+        Rect {
+            left,
+            bottom: framebuffer_height.saturating_sub(top + height),
+            width,
+            height,
+        }
+    }
+
+    /// `x` and `y` are window fractions too
+    pub fn contains(self, x: f32, y: f32) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+
+    /// from window fractions to -1.0..1.0 inside the viewport, y going up
+    pub fn to_ndc(self, x: f32, y: f32) -> (f32, f32) {
+        (
+            2.0 * (x - self.x) / self.width - 1.0,
+            1.0 - 2.0 * (y - self.y) / self.height,
+        )
+    }
+}
+
+/// active cameras drawing into `render_target`, the window when `None`, in drawing order.
+/// ties are broken by name so the order is stable
+fn cameras_in_draw_order<'c>(
+    cameras: &'c HashMap<String, Camera>,
+    render_target: Option<&str>,
+) -> Vec<&'c Camera> {
+    let mut cameras: Vec<(&String, &Camera)> = cameras
+        .iter()
+        .filter(|(_, camera)| camera.active && camera.render_target.as_deref() == render_target)
+        .collect();
+    cameras.sort_by(|(name_a, a), (name_b, b)| a.order.cmp(&b.order).then(name_a.cmp(name_b)));
+
+    cameras.into_iter().map(|(_, camera)| camera).collect()
+}
+
+#[derive(Clone)]
 pub struct Camera {
     pub operations: Matrix4,
+    pub projection: Projection,
+    pub z_near: f32,
+    pub z_far: f32,
+    pub viewport: Viewport,
+    /// cameras are drawn from the lowest order up, so higher ones end on top
+    pub order: i32,
+    /// inactive cameras are neither drawn nor picked from
+    pub active: bool,
+    /// the `World` render target this camera draws into, instead of the window
+    pub render_target: Option<String>,
 }
 
+#[allow(dead_code)]
 impl Camera {
     pub fn new() -> Camera {
         Camera {
             operations: Matrix4::identity(),
+            projection: Projection::Perspective { fov: VIEW_ANGLE },
+            z_near: Z_NEAR,
+            z_far: Z_FAR,
+            viewport: Viewport::full(),
+            order: 0,
+            active: true,
+            render_target: None,
+        }
+    }
+
+    pub fn with_perspective(mut self, fov: f32) -> Self {
+        self.projection = Projection::Perspective { fov };
+        self
+    }
+
+    pub fn with_orthographic(mut self, height: f32) -> Self {
+        self.projection = Projection::Orthographic { height };
+        self
+    }
+
+    pub fn with_clip_planes(mut self, z_near: f32, z_far: f32) -> Self {
+        self.z_near = z_near;
+        self.z_far = z_far;
+        self
+    }
+
+    pub fn with_viewport(mut self, viewport: Viewport) -> Self {
+        self.viewport = viewport;
+        self
+    }
+
+    pub fn with_order(mut self, order: i32) -> Self {
+        self.order = order;
+        self
+    }
+
+    pub fn with_render_target(mut self, render_target: &str) -> Self {
+        self.render_target = Some(String::from(render_target));
+        self
+    }
+
+    /// `window_ratio` is width over height of the whole window, the viewport shape is applied here
+    pub fn projection_matrix(&self, window_ratio: f32) -> Matrix4 {
+        let ratio = window_ratio * self.viewport.width / self.viewport.height;
+
+        match self.projection {
+            Projection::Perspective { fov } => {
+                MatrixOperation::perspective(ratio, fov, self.z_near, self.z_far)
+            }
+            Projection::Orthographic { height } => {
+                MatrixOperation::orthographic(ratio, height, self.z_near, self.z_far)
+            }
         }
     }
 
+    /// world to clip space
+    pub fn view_projection(&self, window_ratio: f32) -> Matrix4 {
+        self.projection_matrix(window_ratio) * self.camera_matrix_from_target()
+    }
+
+    #[allow(dead_code)]
     pub fn set_parent(&mut self, instance: &Instance) {
         self.operations = instance.operations.clone();
     }
 
+    /// places the camera at `position`, looking at `target` with y as up
+    #[rustfmt::skip]
+    pub fn look_at(&mut self, position: Vector3, target: Vector3) {
+        let forward = (target - position).normalized();
+        let side = Vector3::new(0.0, 1.0, 0.0).cross(forward).normalized();
+        let up = forward.cross(side);
+
+        self.operations = Matrix4::from([
+            side.x,     up.x,   forward.x,  position.x,
+            side.y,     up.y,   forward.y,  position.y,
+            side.z,     up.z,   forward.z,  position.z,
+            0.0,        0.0,    0.0,        1.0,
+        ]);
+    }
+
     /// original version: MatrixOperation::camera_matrix(self.camera_position, self.target_position, Vector3::up())
     #[rustfmt::skip]
     pub fn camera_matrix_from_target(&self) -> Matrix4 {
@@ -195,17 +1726,24 @@ impl Camera {
     }
 }
 
+/// instances sharing prefab and material are drawn together
+type BatchKey = (usize, usize);
+
 pub struct Instance {
     operations: Matrix4,
-    prefab: Arc<Prefab>,
+    prefab: Rc<Prefab>,
+    material: Option<Arc<Material>>,
+    color: [f32; 4],
 }
 
 #[allow(dead_code)]
 impl Instance {
-    pub fn new(prefab: Arc<Prefab>) -> Self {
+    pub fn new(prefab: Rc<Prefab>) -> Self {
         Instance {
             operations: Matrix4::identity(),
             prefab,
+            material: None,
+            color: [1.0, 1.0, 1.0, 1.0],
         }
     }
 
@@ -213,6 +1751,76 @@ impl Instance {
         Instance {
             operations: self.operations.clone(),
             prefab: self.prefab.clone(),
+            material: self.material.clone(),
+            color: self.color,
+        }
+    }
+
+    /// instances without a material are drawn opaque
+    pub fn set_material(&mut self, material: Arc<Material>) {
+        self.material = Some(material);
+    }
+
+    pub fn get_material(&self) -> Option<&Arc<Material>> {
+        self.material.as_ref()
+    }
+
+    pub fn get_prefab(&self) -> &Rc<Prefab> {
+        &self.prefab
+    }
+
+    pub fn get_color(&self) -> [f32; 4] {
+        self.color
+    }
+
+    /// multiplies the prefab vertex colors of this instance only
+    pub fn set_color(&mut self, color: [f32; 4]) {
+        self.color = color;
+    }
+
+    pub fn get_world_bounds(&self) -> Aabb {
+        self.prefab.get_bounds().transform(self.operations)
+    }
+
+    pub fn get_world_bounding_sphere(&self) -> Sphere {
+        self.prefab.get_bounding_sphere().transform(self.operations)
+    }
+
+    /// distance along the world space ray to the closest triangle hit
+    pub fn intersect_ray(&self, ray: &Ray) -> Option<f32> {
+        let world_to_local = self.operations.inverse()?;
+        let local_ray = Ray::new(
+            world_to_local.transform_point(ray.origin),
+            world_to_local.transform_vector(ray.direction),
+        );
+
+        let local_distance = self.prefab.intersect_ray(&local_ray)?;
+        let hit = self
+            .operations
+            .transform_point(local_ray.point_at(local_distance));
+
+        Some((hit - ray.origin).length())
+    }
+
+    /// the sphere test is cheaper and rejects most instances, the box is tighter for the rest
+    pub fn is_visible(&self, frustum: &Frustum) -> bool {
+        frustum.intersects_sphere(&self.get_world_bounding_sphere())
+            && frustum.intersects_aabb(&self.get_world_bounds())
+    }
+
+    fn batch_key(&self) -> BatchKey {
+        let material = self
+            .material
+            .as_ref()
+            .map_or(0, |material| Arc::as_ptr(material) as usize);
+
+        (Rc::as_ptr(&self.prefab) as usize, material)
+    }
+
+    fn attributes(&self) -> InstanceAttributes {
+        InstanceAttributes {
+            model_matrix: self.operations.to_opengl_array(),
+            instance_color: self.color,
         }
     }
 
@@ -221,6 +1829,14 @@ impl Instance {
             MatrixOperation::translation(parent.operations.get_position()) * self.operations;
     }
 
+    pub fn get_transform(&self) -> Matrix4 {
+        self.operations
+    }
+
+    pub fn set_transform(&mut self, transform: Matrix4) {
+        self.operations = transform;
+    }
+
     pub fn reset_transform(&mut self) {
         self.operations = Matrix4::identity();
     }
@@ -245,15 +1861,27 @@ impl Instance {
         self.operations = self.operations * Quaternion::rotate_z(angle);
     }
 
+    /// moves the instance so its origin ends at `position`, keeping rotation and scale
+    pub fn set_position(&mut self, position: Vector3) {
+        let offset = position - self.operations.get_position();
+        self.operations = MatrixOperation::translation(offset) * self.operations;
+    }
+
+    pub fn get_forward_vector(&self) -> Vector3 {
+        self.operations.get_forward_vector().normalized()
+    }
+
+    pub fn get_side_vector(&self) -> Vector3 {
+        self.operations.get_side_vector().normalized()
+    }
+
     pub fn add_front_translation(&mut self, amount: f32) {
-        let vector = self.operations.get_forward_vector().normalized();
-        let vector = vector * amount;
+        let vector = self.get_forward_vector() * amount;
         self.operations = MatrixOperation::translation(vector) * self.operations;
     }
 
     pub fn add_side_translation(&mut self, amount: f32) {
-        let vector = self.operations.get_side_vector().normalized();
-        let vector = vector * amount;
+        let vector = self.get_side_vector() * amount;
         self.operations = MatrixOperation::translation(vector) * self.operations;
     }
 
@@ -266,30 +1894,54 @@ impl Instance {
 
 pub struct DeviceManager {
     pressed_keys: HashSet<VirtualKeyCode>,
+    /// keys that went down since the last frame
+    just_pressed_keys: HashSet<VirtualKeyCode>,
+    pressed_buttons: HashSet<MouseButton>,
     mouse_delta_y: Option<f32>,
     mouse_delta_x: Option<f32>,
+    /// in lines, positive when scrolling away from the user
+    mouse_wheel: f32,
 }
 
 const MIN_CHANGE: f64 = 0.001;
+/// touchpads scroll in pixels, everything is counted in lines
+const PIXELS_PER_LINE: f32 = 20.0;
 
+#[allow(dead_code)]
 impl DeviceManager {
     pub fn new() -> Self {
         DeviceManager {
             pressed_keys: HashSet::new(),
+            just_pressed_keys: HashSet::new(),
+            pressed_buttons: HashSet::new(),
             mouse_delta_y: None,
             mouse_delta_x: None,
+            mouse_wheel: 0.0,
         }
     }
 
     pub fn update_keys(&mut self, input: &KeyboardInput) {
         if let Some(code) = input.virtual_keycode {
             match input.state {
-                ElementState::Pressed => self.pressed_keys.insert(code),
-                ElementState::Released => self.pressed_keys.remove(&code),
+                ElementState::Pressed => {
+                    if self.pressed_keys.insert(code) {
+                        self.just_pressed_keys.insert(code);
+                    }
+                }
+                ElementState::Released => {
+                    self.pressed_keys.remove(&code);
+                }
             };
         }
     }
 
+    pub fn update_mouse_buttons(&mut self, button: MouseButton, state: ElementState) {
+        match state {
+            ElementState::Pressed => self.pressed_buttons.insert(button),
+            ElementState::Released => self.pressed_buttons.remove(&button),
+        };
+    }
+
     pub fn update_mouse_motion(&mut self, x: f64, y: f64) {
         self.mouse_delta_x = if x.abs() > MIN_CHANGE {
             Some(x as f32)
@@ -304,10 +1956,34 @@ impl DeviceManager {
         };
     }
 
+    pub fn update_mouse_wheel(&mut self, delta: MouseScrollDelta) {
+        self.mouse_wheel += match delta {
+            MouseScrollDelta::LineDelta(_, y) => y,
+            MouseScrollDelta::PixelDelta(position) => position.y as f32 / PIXELS_PER_LINE,
+        };
+    }
+
     pub fn iter_keys(&self) -> Iter<VirtualKeyCode> {
         self.pressed_keys.iter()
     }
 
+    pub fn is_key_pressed(&self, key: VirtualKeyCode) -> bool {
+        self.pressed_keys.contains(&key)
+    }
+
+    /// true only on the frame the key went down
+    pub fn was_key_pressed(&self, key: VirtualKeyCode) -> bool {
+        self.just_pressed_keys.contains(&key)
+    }
+
+    pub fn is_shift_pressed(&self) -> bool {
+        self.is_key_pressed(VirtualKeyCode::LShift) || self.is_key_pressed(VirtualKeyCode::RShift)
+    }
+
+    pub fn is_mouse_button_pressed(&self, button: MouseButton) -> bool {
+        self.pressed_buttons.contains(&button)
+    }
+
     pub fn get_last_mouse_movement_x(&self) -> f32 {
         if let Some(value) = self.mouse_delta_x {
             value
@@ -324,8 +2000,170 @@ impl DeviceManager {
         }
     }
 
-    pub fn reset_mouse(&mut self) {
+    pub fn get_last_mouse_wheel(&self) -> f32 {
+        self.mouse_wheel
+    }
+
+    /// clears what only lasts one frame: mouse movement, wheel and just pressed keys
+    pub fn reset_frame(&mut self) {
         self.mouse_delta_x = None;
         self.mouse_delta_y = None;
+        self.mouse_wheel = 0.0;
+        self.just_pressed_keys.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::missing_attributes;
+
+    #[test]
+    fn viewport_to_rect_counts_rows_from_the_bottom() {
+        let viewport = Viewport::new(0.5, 0.0, 0.5, 0.25);
+
+        assert_eq!(
+            Rect {
+                left: 400,
+                bottom: 450,
+                width: 400,
+                height: 150,
+            },
+            viewport.to_rect(800, 600)
+        );
+        assert_eq!(
+            Rect {
+                left: 0,
+                bottom: 0,
+                width: 800,
+                height: 600,
+            },
+            Viewport::full().to_rect(800, 600)
+        );
+    }
+
+    #[test]
+    fn viewport_contains_includes_the_top_left_edge_only() {
+        let viewport = Viewport::new(0.25, 0.5, 0.5, 0.5);
+
+        assert!(viewport.contains(0.25, 0.5));
+        assert!(viewport.contains(0.5, 0.75));
+        assert!(!viewport.contains(0.75, 0.75));
+        assert!(!viewport.contains(0.5, 1.0));
+        assert!(!viewport.contains(0.1, 0.75));
+        assert!(!viewport.contains(0.5, 0.4));
+    }
+
+    #[test]
+    fn viewport_to_ndc_goes_up_from_the_bottom() {
+        let viewport = Viewport::new(0.5, 0.5, 0.5, 0.5);
+
+        assert_eq!((-1.0, 1.0), viewport.to_ndc(0.5, 0.5));
+        assert_eq!((1.0, -1.0), viewport.to_ndc(1.0, 1.0));
+        assert_eq!((0.0, 0.0), viewport.to_ndc(0.75, 0.75));
+    }
+
+    #[test]
+    fn strip_triangles_alternate_winding() {
+        assert_eq!(
+            vec![[0, 1, 2], [1, 3, 2], [2, 3, 4], [3, 5, 4]],
+            triangles_of(PrimitiveType::TriangleStrip, &[0, 1, 2, 3, 4, 5])
+        );
+        assert!(triangles_of(PrimitiveType::TriangleStrip, &[0, 1]).is_empty());
+    }
+
+    #[test]
+    fn fan_triangles_share_the_first_vertex() {
+        assert_eq!(
+            vec![[0, 1, 2], [0, 2, 3], [0, 3, 4]],
+            triangles_of(PrimitiveType::TriangleFan, &[0, 1, 2, 3, 4])
+        );
+    }
+
+    #[test]
+    fn lists_drop_the_unfinished_triangle() {
+        assert_eq!(
+            vec![[0, 1, 2]],
+            triangles_of(PrimitiveType::TrianglesList, &[0, 1, 2, 3])
+        );
+        assert!(triangles_of(PrimitiveType::LinesList, &[0, 1, 2, 3]).is_empty());
+    }
+
+    #[test]
+    fn no_indices_draw_every_vertex_in_order() {
+        let index_list = PrefabIndices::None.index_list(4);
+
+        assert_eq!(vec![0, 1, 2, 3], index_list);
+        assert_eq!(
+            vec![[0, 1, 2], [1, 3, 2]],
+            triangles_of(PrimitiveType::TriangleStrip, &index_list)
+        );
+        assert_eq!(
+            vec![0, 1, 2],
+            PrefabIndices::smallest(3, vec![0, 1, 2]).index_list(3)
+        );
+    }
+
+    #[derive(Copy, Clone)]
+    struct PositionVertex {
+        position: [f32; 3],
+    }
+    implement_vertex!(PositionVertex, position);
+
+    #[test]
+    fn vertex_types_need_the_shader_attributes() {
+        assert!(missing_attributes::<Vertex>().is_empty());
+        assert_eq!(
+            vec!["color", "tex_coords"],
+            missing_attributes::<PositionVertex>()
+        );
+    }
+
+    #[test]
+    fn position_only_vertices_are_rejected() {
+        let error = check_attributes::<PositionVertex>().unwrap_err();
+        assert_eq!(vec!["color", "tex_coords"], error.attributes);
+        assert!(error
+            .to_string()
+            .contains("lacks the color, tex_coords vertex attributes"));
+        assert!(check_attributes::<Vertex>().is_ok());
+    }
+
+    #[test]
+    fn cameras_draw_by_order_then_name() {
+        let mut cameras = HashMap::new();
+        cameras.insert(String::from("b"), Camera::new().with_order(1));
+        cameras.insert(String::from("a"), Camera::new().with_order(1));
+        cameras.insert(String::from("c"), Camera::new().with_order(-1));
+        cameras.insert(String::from("d"), Camera::new().with_order(0));
+        let mut inactive = Camera::new();
+        inactive.active = false;
+        cameras.insert(String::from("inactive"), inactive);
+        cameras.insert(
+            String::from("mirror"),
+            Camera::new().with_render_target("mirror"),
+        );
+
+        let order = |sorted: Vec<&Camera>| -> Vec<String> {
+            sorted
+                .into_iter()
+                .map(|camera| {
+                    cameras
+                        .iter()
+                        .find(|(_, other)| std::ptr::eq(*other, camera))
+                        .map(|(name, _)| name.clone())
+                        .unwrap()
+                })
+                .collect()
+        };
+
+        assert_eq!(
+            vec!["c", "d", "a", "b"],
+            order(cameras_in_draw_order(&cameras, None))
+        );
+        assert_eq!(
+            vec!["mirror"],
+            order(cameras_in_draw_order(&cameras, Some("mirror")))
+        );
     }
 }
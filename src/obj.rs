@@ -0,0 +1,688 @@
+use crate::materials::{load_texture, BlendMode, Material};
+use crate::math::Vector3;
+use crate::models::{Model, ModelMesh, Prefab};
+use crate::primitives::Vertex;
+use glium::backend::glutin::Display;
+use glium::Texture2d;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::read_to_string;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Triangles sharing a group and a material, with the vertices they use.
+#[derive(Debug, Clone)]
+pub struct ObjMesh {
+    pub name: String,
+    pub material: Option<String>,
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjMaterial {
+    pub name: String,
+    pub diffuse: [f32; 3],
+    /// 1.0 is fully opaque
+    pub opacity: f32,
+    /// as written on the file, relative to it
+    pub diffuse_map: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ObjData {
+    pub meshes: Vec<ObjMesh>,
+    /// `mtllib` files, relative to the obj file
+    pub material_libraries: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io {
+        path: String,
+        error: std::io::Error,
+    },
+    Parse {
+        path: String,
+        line: usize,
+        message: String,
+    },
+    Texture {
+        path: String,
+        error: image::ImageError,
+    },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjError::Io { path, error } => write!(f, "{}: {}", path, error),
+            ObjError::Parse {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", path, line, message),
+            ObjError::Texture { path, error } => write!(f, "{}: {}", path, error),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+/// a face corner: position, texture coordinate and normal indices, from zero
+type Corner = (usize, Option<usize>, Option<usize>);
+
+/// builds one `ObjMesh`, sharing vertices between faces that use the same corner
+struct MeshBuilder {
+    name: String,
+    material: Option<String>,
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+    corners: HashMap<Corner, u32>,
+    /// face normals summed for vertices the file gives no normal
+    missing_normals: HashMap<u32, Vector3>,
+}
+
+impl MeshBuilder {
+    fn new(name: &str, material: &Option<String>) -> Self {
+        MeshBuilder {
+            name: String::from(name),
+            material: material.clone(),
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            corners: HashMap::new(),
+            missing_normals: HashMap::new(),
+        }
+    }
+
+    fn finish(self) -> ObjMesh {
+        let mut vertices = self.vertices;
+        for (index, normal) in self.missing_normals {
+            let normal = if normal.length() > 0.0 {
+                normal.normalized()
+            } else {
+                normal
+            };
+            vertices[index as usize].normal = normal.to_array();
+        }
+
+        ObjMesh {
+            name: self.name,
+            material: self.material,
+            vertices,
+            indices: self.indices,
+        }
+    }
+}
+
+struct ObjParser<'a> {
+    path: &'a str,
+    line: usize,
+    positions: Vec<[f32; 3]>,
+    colors: Vec<[f32; 4]>,
+    tex_coords: Vec<[f32; 2]>,
+    normals: Vec<[f32; 3]>,
+    name: String,
+    material: Option<String>,
+    builders: Vec<MeshBuilder>,
+    /// group and material to builder
+    current: HashMap<(String, Option<String>), usize>,
+}
+
+impl<'a> ObjParser<'a> {
+    fn error(&self, message: String) -> ObjError {
+        ObjError::Parse {
+            path: String::from(self.path),
+            line: self.line,
+            message,
+        }
+    }
+
+    fn numbers(&self, values: &[&str], min: usize, max: usize) -> Result<Vec<f32>, ObjError> {
+        if values.len() < min || values.len() > max {
+            return Err(self.error(format!(
+                "expected {} to {} numbers, found {}",
+                min,
+                max,
+                values.len()
+            )));
+        }
+
+        values
+            .iter()
+            .map(|value| {
+                value
+                    .parse::<f32>()
+                    .map_err(|_| self.error(format!("invalid number \"{}\"", value)))
+            })
+            .collect()
+    }
+
+    /// 1 is the first element, -1 the last one read so far
+    fn index(&self, value: &str, count: usize, kind: &str) -> Result<usize, ObjError> {
+        let index = value
+            .parse::<i64>()
+            .map_err(|_| self.error(format!("invalid {} index \"{}\"", kind, value)))?;
+
+        let resolved = if index > 0 {
+            index - 1
+        } else {
+            count as i64 + index
+        };
+
+        if index == 0 || resolved < 0 || resolved >= count as i64 {
+            return Err(self.error(format!(
+                "{} index {} out of range, there are {}",
+                kind, index, count
+            )));
+        }
+        Ok(resolved as usize)
+    }
+
+    fn corner(&self, value: &str) -> Result<Corner, ObjError> {
+        let mut parts = value.split('/');
+        let position = self.index(parts.next().unwrap(), self.positions.len(), "position")?;
+        let tex_coord = match parts.next() {
+            Some(part) if !part.is_empty() => {
+                Some(self.index(part, self.tex_coords.len(), "texture coordinate")?)
+            }
+            _ => None,
+        };
+        let normal = match parts.next() {
+            Some(part) if !part.is_empty() => {
+                Some(self.index(part, self.normals.len(), "normal")?)
+            }
+            _ => None,
+        };
+
+        if parts.next().is_some() {
+            return Err(self.error(format!("invalid face vertex \"{}\"", value)));
+        }
+        Ok((position, tex_coord, normal))
+    }
+
+    /// the mesh for the current group and material, created on its first face
+    fn builder_index(&mut self) -> usize {
+        let key = (self.name.clone(), self.material.clone());
+
+        match self.current.get(&key) {
+            Some(&index) => index,
+            None => {
+                self.builders
+                    .push(MeshBuilder::new(&self.name, &self.material));
+                self.current.insert(key, self.builders.len() - 1);
+                self.builders.len() - 1
+            }
+        }
+    }
+
+    fn face(&mut self, values: &[&str]) -> Result<(), ObjError> {
+        if values.len() < 3 {
+            return Err(self.error(format!(
+                "a face needs at least 3 vertices, found {}",
+                values.len()
+            )));
+        }
+
+        let corners: Vec<Corner> = values
+            .iter()
+            .map(|value| self.corner(value))
+            .collect::<Result<_, _>>()?;
+
+        let builder_index = self.builder_index();
+        let (tex_coords, normals) = (&self.tex_coords, &self.normals);
+        let builder = &mut self.builders[builder_index];
+        let mut indices = Vec::with_capacity(corners.len());
+
+        for corner in corners {
+            let index = match builder.corners.get(&corner) {
+                Some(&index) => index,
+                None => {
+                    let index = builder.vertices.len() as u32;
+                    let (position, tex_coord, normal) = corner;
+
+                    builder.vertices.push(Vertex {
+                        position: self.positions[position],
+                        color: self.colors[position],
+                        tex_coords: tex_coord.map_or([0.0, 0.0], |i| tex_coords[i]),
+                        normal: normal.map_or([0.0, 0.0, 0.0], |i| normals[i]),
+                    });
+                    if normal.is_none() {
+                        builder
+                            .missing_normals
+                            .insert(index, Vector3::new(0.0, 0.0, 0.0));
+                    }
+                    builder.corners.insert(corner, index);
+                    index
+                }
+            };
+            indices.push(index);
+        }
+
+        // a fan from the first corner, fine for the convex polygons exporters write
+        for i in 1..indices.len() - 1 {
+            let triangle = [indices[0], indices[i], indices[i + 1]];
+            builder.indices.extend_from_slice(&triangle);
+
+            let a = position(&builder.vertices, triangle[0]);
+            let b = position(&builder.vertices, triangle[1]);
+            let c = position(&builder.vertices, triangle[2]);
+            // not normalized, so bigger triangles weigh more
+            let face_normal = (b - a).cross(c - a);
+
+            for index in triangle.iter() {
+                if let Some(normal) = builder.missing_normals.get_mut(index) {
+                    *normal = *normal + face_normal;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn position(vertices: &[Vertex], index: u32) -> Vector3 {
+    let [x, y, z] = vertices[index as usize].position;
+    Vector3::new(x, y, z)
+}
+
+/// Reads positions (with optional vertex colors), texture coordinates, normals and faces.
+/// Every `o` or `g` name and `usemtl` material pair becomes its own mesh.
+/// reference: http://paulbourke.net/dataformats/obj/
+pub fn parse_obj(path: &str, content: &str) -> Result<ObjData, ObjError> {
+    let mut parser = ObjParser {
+        path,
+        line: 0,
+        positions: Vec::new(),
+        colors: Vec::new(),
+        tex_coords: Vec::new(),
+        normals: Vec::new(),
+        name: String::from("default"),
+        material: None,
+        builders: Vec::new(),
+        current: HashMap::new(),
+    };
+    let mut material_libraries = Vec::new();
+
+    for (number, line) in content.lines().enumerate() {
+        parser.line = number + 1;
+
+        let line = line.split('#').next().unwrap().trim();
+        let mut values = line.split_whitespace();
+        let keyword = match values.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let values: Vec<&str> = values.collect();
+
+        match keyword {
+            "v" => {
+                let numbers = parser.numbers(&values, 3, 7)?;
+                parser.positions.push([numbers[0], numbers[1], numbers[2]]);
+                // x y z r g b is a common extension, x y z w is not a color
+                parser.colors.push(if numbers.len() >= 6 {
+                    [numbers[3], numbers[4], numbers[5], 1.0]
+                } else {
+                    [1.0, 1.0, 1.0, 1.0]
+                });
+            }
+            "vt" => {
+                let numbers = parser.numbers(&values, 1, 3)?;
+                parser
+                    .tex_coords
+                    .push([numbers[0], numbers.get(1).copied().unwrap_or(0.0)]);
+            }
+            "vn" => {
+                let numbers = parser.numbers(&values, 3, 3)?;
+                parser.normals.push([numbers[0], numbers[1], numbers[2]]);
+            }
+            "f" => parser.face(&values)?,
+            "o" | "g" => {
+                parser.name = if values.is_empty() {
+                    String::from("default")
+                } else {
+                    values.join(" ")
+                };
+            }
+            "usemtl" => {
+                if values.is_empty() {
+                    return Err(parser.error(String::from("usemtl needs a material name")));
+                }
+                parser.material = Some(values.join(" "));
+            }
+            "mtllib" => material_libraries.extend(values.iter().map(|value| value.to_string())),
+            // smoothing groups, lines, points and the rest do not change triangle meshes
+            _ => (),
+        }
+    }
+
+    let mut meshes: Vec<ObjMesh> = parser
+        .builders
+        .into_iter()
+        .map(MeshBuilder::finish)
+        .collect();
+
+    // a group drawn with several materials gets one mesh per material
+    let mut name_count: HashMap<String, usize> = HashMap::new();
+    for mesh in &meshes {
+        *name_count.entry(mesh.name.clone()).or_insert(0) += 1;
+    }
+    for mesh in &mut meshes {
+        if name_count[&mesh.name] > 1 {
+            let material = mesh.material.as_deref().unwrap_or("none");
+            mesh.name = format!("{}.{}", mesh.name, material);
+        }
+    }
+
+    Ok(ObjData {
+        meshes,
+        material_libraries,
+    })
+}
+
+/// Diffuse color, opacity and diffuse texture of each `newmtl`, the rest is ignored.
+/// reference: http://paulbourke.net/dataformats/mtl/
+pub fn parse_mtl(path: &str, content: &str) -> Result<Vec<ObjMaterial>, ObjError> {
+    let mut materials: Vec<ObjMaterial> = Vec::new();
+
+    for (number, line) in content.lines().enumerate() {
+        let error = |message: String| ObjError::Parse {
+            path: String::from(path),
+            line: number + 1,
+            message,
+        };
+
+        let line = line.split('#').next().unwrap().trim();
+        let mut values = line.split_whitespace();
+        let keyword = match values.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let values: Vec<&str> = values.collect();
+
+        if keyword == "newmtl" {
+            if values.is_empty() {
+                return Err(error(String::from("newmtl needs a material name")));
+            }
+            materials.push(ObjMaterial {
+                name: values.join(" "),
+                diffuse: [1.0, 1.0, 1.0],
+                opacity: 1.0,
+                diffuse_map: None,
+            });
+            continue;
+        }
+
+        let material = match (keyword, materials.last_mut()) {
+            ("Kd", Some(material))
+            | ("d", Some(material))
+            | ("Tr", Some(material))
+            | ("map_Kd", Some(material)) => material,
+            ("Kd", None) | ("d", None) | ("Tr", None) | ("map_Kd", None) => {
+                return Err(error(format!("{} before any newmtl", keyword)))
+            }
+            _ => continue,
+        };
+
+        let number = |value: &str| {
+            value
+                .parse::<f32>()
+                .map_err(|_| error(format!("invalid number \"{}\"", value)))
+        };
+
+        match keyword {
+            "Kd" => {
+                if values.len() != 3 {
+                    return Err(error(format!("Kd needs 3 numbers, found {}", values.len())));
+                }
+                material.diffuse = [number(values[0])?, number(values[1])?, number(values[2])?];
+            }
+            "d" | "Tr" => {
+                let value = number(values.first().copied().unwrap_or(""))?;
+                material.opacity = if keyword == "d" { value } else { 1.0 - value };
+            }
+            // options like -s 1 1 1 come before the file name
+            _ => match values.last() {
+                Some(file) => material.diffuse_map = Some(file.to_string()),
+                None => return Err(error(String::from("map_Kd needs a file name"))),
+            },
+        }
+    }
+
+    Ok(materials)
+}
+
+fn read(path: &Path) -> Result<String, ObjError> {
+    read_to_string(path).map_err(|error| ObjError::Io {
+        path: path.display().to_string(),
+        error,
+    })
+}
+
+/// Loads an obj file, its mtl files and their textures, one `ModelMesh` per obj mesh.
+#[allow(dead_code)]
+pub fn load_obj(display: &Display, path: &str) -> Result<Model, ObjError> {
+    let path = Path::new(path);
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    let data = parse_obj(&path.display().to_string(), &read(path)?)?;
+
+    let mut descriptions = HashMap::new();
+    for library in &data.material_libraries {
+        let library = directory.join(library);
+        for material in parse_mtl(&library.display().to_string(), &read(&library)?)? {
+            descriptions.insert(material.name.clone(), (material, library.clone()));
+        }
+    }
+
+    let mut textures: HashMap<String, Arc<Texture2d>> = HashMap::new();
+    let mut materials: HashMap<String, Arc<Material>> = HashMap::new();
+    let mut meshes = Vec::new();
+
+    for mesh in data.meshes {
+        // unknown material names are drawn with the default material, like most viewers do
+        let material = match mesh
+            .material
+            .as_ref()
+            .and_then(|name| descriptions.get(name))
+        {
+            Some((description, library)) => {
+                if !materials.contains_key(&description.name) {
+                    let texture = match &description.diffuse_map {
+                        Some(file) => {
+                            let texture_path = library
+                                .parent()
+                                .unwrap_or_else(|| Path::new(""))
+                                .join(file)
+                                .display()
+                                .to_string();
+                            if !textures.contains_key(&texture_path) {
+                                let texture =
+                                    load_texture(display, &texture_path).map_err(|error| {
+                                        ObjError::Texture {
+                                            path: texture_path.clone(),
+                                            error,
+                                        }
+                                    })?;
+                                textures.insert(texture_path.clone(), texture);
+                            }
+                            Some(textures[&texture_path].clone())
+                        }
+                        None => None,
+                    };
+
+                    let [r, g, b] = description.diffuse;
+                    let color = [r, g, b, description.opacity];
+                    let blend_mode = if description.opacity < 1.0 {
+                        BlendMode::Alpha
+                    } else {
+                        BlendMode::Opaque
+                    };
+                    let material = match texture {
+                        Some(texture) => Material::textured(color, blend_mode, texture),
+                        None => Material::new(color, blend_mode),
+                    };
+                    materials.insert(description.name.clone(), material);
+                }
+                Some(materials[&description.name].clone())
+            }
+            None => None,
+        };
+
+        meshes.push(ModelMesh {
+            name: mesh.name,
+            prefab: Prefab::build_u32(display.clone(), mesh.vertices, mesh.indices),
+            material,
+        });
+    }
+
+    Ok(Model { meshes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CUBE_SIDES: &str = "
+        # two faces of a cube, one as a quad and one as two triangles
+        mtllib box.mtl
+        v -0.5 -0.5 0.5
+        v 0.5 -0.5 0.5
+        v 0.5 0.5 0.5
+        v -0.5 0.5 0.5
+        v 0.5 -0.5 -0.5
+        v 0.5 0.5 -0.5
+        vt 0 0
+        vt 1 0
+        vt 1 1
+        vt 0 1
+        vn 0 0 1
+        o box
+        usemtl wood
+        f 1/1/1 2/2/1 3/3/1 4/4/1
+        g side
+        usemtl metal
+        f 2 5 6
+        f -5 -1 -4
+    ";
+
+    fn parse(content: &str) -> Result<ObjData, ObjError> {
+        parse_obj("test.obj", content)
+    }
+
+    fn parse_error(content: &str) -> (usize, String) {
+        match parse(content) {
+            Err(ObjError::Parse { line, message, .. }) => (line, message),
+            other => panic!(
+                "expected a parse error, got {:?}",
+                other.map(|data| data.meshes)
+            ),
+        }
+    }
+
+    #[test]
+    fn groups_and_materials_become_meshes() {
+        let data = parse(CUBE_SIDES).unwrap();
+
+        assert_eq!(data.material_libraries, vec![String::from("box.mtl")]);
+        assert_eq!(data.meshes.len(), 2);
+        assert_eq!(data.meshes[0].name, "box");
+        assert_eq!(data.meshes[0].material.as_deref(), Some("wood"));
+        assert_eq!(data.meshes[1].name, "side");
+        assert_eq!(data.meshes[1].material.as_deref(), Some("metal"));
+    }
+
+    #[test]
+    fn polygons_are_triangulated() {
+        let data = parse(CUBE_SIDES).unwrap();
+        let front = &data.meshes[0];
+
+        assert_eq!(front.vertices.len(), 4);
+        assert_eq!(front.indices, vec![0, 1, 2, 0, 2, 3]);
+        assert_eq!(front.vertices[2].tex_coords, [1.0, 1.0]);
+        assert_eq!(front.vertices[2].normal, [0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn shared_corners_are_deduplicated() {
+        let data = parse(CUBE_SIDES).unwrap();
+        let side = &data.meshes[1];
+
+        // 2 5 6 and 2 6 3 share two corners
+        assert_eq!(side.vertices.len(), 4);
+        assert_eq!(side.indices.len(), 6);
+        assert_eq!(side.indices[0], side.indices[3]);
+        assert_eq!(side.indices[2], side.indices[4]);
+    }
+
+    #[test]
+    fn missing_normals_come_from_the_faces() {
+        let data = parse(CUBE_SIDES).unwrap();
+
+        for vertex in &data.meshes[1].vertices {
+            let [x, y, z] = vertex.normal;
+            assert!((x - 1.0).abs() < 0.001 && y.abs() < 0.001 && z.abs() < 0.001);
+        }
+    }
+
+    #[test]
+    fn a_group_with_two_materials_is_split_by_material() {
+        let data = parse(
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\ng wall\nusemtl red\nf 1 2 3\nusemtl blue\nf 1 2 3\nusemtl red\nf 3 2 1\n",
+        )
+        .unwrap();
+
+        let names: Vec<&str> = data.meshes.iter().map(|mesh| mesh.name.as_str()).collect();
+        assert_eq!(names, vec!["wall.red", "wall.blue"]);
+        assert_eq!(data.meshes[0].indices.len(), 6);
+    }
+
+    #[test]
+    fn vertex_colors_are_read() {
+        let data = parse("v 0 0 0 1 0 0\nv 1 0 0 0 1 0\nv 0 1 0 0 0 1\nf 1 2 3\n").unwrap();
+
+        assert_eq!(data.meshes[0].name, "default");
+        assert_eq!(data.meshes[0].vertices[1].color, [0.0, 1.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn errors_carry_line_numbers() {
+        assert_eq!(
+            parse_error("v 0 0 0\nv 1 0 0\nf 1 2 3\n"),
+            (
+                3,
+                String::from("position index 3 out of range, there are 2")
+            )
+        );
+        assert_eq!(
+            parse_error("v 0 0 zero\n"),
+            (1, String::from("invalid number \"zero\""))
+        );
+        assert_eq!(parse_error("v 0 0 0\n\nf 1 1\n").0, 3);
+        assert_eq!(parse_error("v 0 0 0\nf 1/1 1 1\n").0, 2);
+    }
+
+    #[test]
+    fn mtl_materials() {
+        let materials = parse_mtl(
+            "box.mtl",
+            "newmtl wood\nKd 0.5 0.3 0.1\nmap_Kd -s 1 1 1 textures/wood.png\nnewmtl glass\nd 0.25\n",
+        )
+        .unwrap();
+
+        assert_eq!(materials.len(), 2);
+        assert_eq!(materials[0].diffuse, [0.5, 0.3, 0.1]);
+        assert_eq!(
+            materials[0].diffuse_map.as_deref(),
+            Some("textures/wood.png")
+        );
+        assert_eq!(materials[1].opacity, 0.25);
+    }
+
+    #[test]
+    fn mtl_errors_carry_line_numbers() {
+        match parse_mtl("box.mtl", "# nothing yet\nKd 1 1 1\n") {
+            Err(ObjError::Parse { line, .. }) => assert_eq!(line, 2),
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,1307 @@
+use crate::math::{clamp, Aabb, Matrix4, Obb, Quaternion, Ray, Sphere, Vector3};
+use crate::matrices::MatrixOperation;
+use crate::spatial::{ProxyId, SpatialIndex};
+use std::collections::HashMap;
+
+pub const FIXED_TIMESTEP: f32 = 1.0 / 60.0;
+/// steps allowed per `update`, so a long frame drops simulated time instead of spiralling
+const MAX_STEPS_PER_UPDATE: u32 = 5;
+const SOLVER_ITERATIONS: usize = 10;
+/// penetration left alone, keeps resting contacts touching from one step to the next
+const PENETRATION_SLOP: f32 = 0.01;
+const POSITION_CORRECTION: f32 = 0.4;
+/// slower impacts do not bounce, otherwise resting bodies jitter on restitution
+const RESTITUTION_THRESHOLD: f32 = 1.0;
+/// corners closer than this to the other box count as touching
+const CONTACT_TOLERANCE: f32 = 0.02;
+const BROADPHASE_MARGIN: f32 = 0.2;
+
+/// shapes are measured in the local units of the body, its scale is applied on top
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Collider {
+    Box { half_extents: Vector3 },
+    Sphere { radius: f32 },
+}
+
+#[allow(dead_code)]
+impl Collider {
+    /// fits the local bounds of a prefab, the prefab is expected to be centered on its origin
+    pub fn from_bounds(bounds: Aabb) -> Self {
+        Collider::Box {
+            half_extents: bounds.half_extents(),
+        }
+    }
+}
+
+pub struct RigidBody {
+    pub collider: Collider,
+    pub linear_velocity: Vector3,
+    /// radians per second, world space
+    pub angular_velocity: Vector3,
+    pub restitution: f32,
+    pub friction: f32,
+    pub linear_damping: f32,
+    pub angular_damping: f32,
+    position: Vector3,
+    orientation: Quaternion,
+    scale: Vector3,
+    inverse_mass: f32,
+    locked_rotation: bool,
+    force: Vector3,
+    torque: Vector3,
+}
+
+/// reference: https://box2d.org/files/ErinCatto_IterativeDynamics_GDC2005.pdf
+#[allow(dead_code)]
+impl RigidBody {
+    pub fn dynamic(collider: Collider, mass: f32) -> Self {
+        RigidBody {
+            collider,
+            linear_velocity: Vector3::new(0.0, 0.0, 0.0),
+            angular_velocity: Vector3::new(0.0, 0.0, 0.0),
+            restitution: 0.2,
+            friction: 0.5,
+            linear_damping: 0.0,
+            angular_damping: 0.05,
+            position: Vector3::new(0.0, 0.0, 0.0),
+            orientation: Quaternion::identity(),
+            scale: Vector3::new(1.0, 1.0, 1.0),
+            inverse_mass: if mass > 0.0 { 1.0 / mass } else { 0.0 },
+            locked_rotation: false,
+            force: Vector3::new(0.0, 0.0, 0.0),
+            torque: Vector3::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    /// never moves, other bodies collide against it
+    pub fn fixed(collider: Collider) -> Self {
+        RigidBody::dynamic(collider, 0.0)
+    }
+
+    /// takes position, rotation and scale from an instance-like transform without shear
+    pub fn with_transform(mut self, transform: Matrix4) -> Self {
+        let side = transform.get_side_vector();
+        let up = transform.get_up_vector();
+        let forward = transform.get_forward_vector();
+
+        self.position = transform.get_position();
+        self.scale = Vector3::new(side.length(), up.length(), forward.length());
+        self.orientation = Quaternion::from_rotation_matrix(columns_to_matrix(
+            side.normalized(),
+            up.normalized(),
+            forward.normalized(),
+        ));
+        self
+    }
+
+    pub fn with_position(mut self, position: Vector3) -> Self {
+        self.position = position;
+        self
+    }
+
+    pub fn with_restitution(mut self, restitution: f32) -> Self {
+        self.restitution = restitution;
+        self
+    }
+
+    pub fn with_friction(mut self, friction: f32) -> Self {
+        self.friction = friction;
+        self
+    }
+
+    /// collisions only push the body around, useful for characters that must stay upright
+    pub fn with_locked_rotation(mut self) -> Self {
+        self.locked_rotation = true;
+        self.angular_velocity = Vector3::new(0.0, 0.0, 0.0);
+        self
+    }
+
+    pub fn is_static(&self) -> bool {
+        self.inverse_mass == 0.0
+    }
+
+    pub fn mass(&self) -> f32 {
+        if self.is_static() {
+            0.0
+        } else {
+            1.0 / self.inverse_mass
+        }
+    }
+
+    pub fn position(&self) -> Vector3 {
+        self.position
+    }
+
+    pub fn orientation(&self) -> Quaternion {
+        self.orientation
+    }
+
+    /// translation * rotation * scale, ready to be written back to an instance
+    pub fn transform(&self) -> Matrix4 {
+        MatrixOperation::translation(self.position)
+            * self.orientation.to_matrix()
+            * MatrixOperation::scale(self.scale)
+    }
+
+    /// moves the body without going through the solver, whatever it overlaps is pushed out on the next step
+    pub fn set_position(&mut self, position: Vector3) {
+        self.position = position;
+    }
+
+    /// rotation in the local space of the body, like `Instance::set_rotate_y`
+    pub fn rotate(&mut self, rotation: Quaternion) {
+        self.orientation = (self.orientation * rotation).normalized();
+    }
+
+    /// applied on the center of mass during the next step
+    pub fn apply_force(&mut self, force: Vector3) {
+        self.force = self.force + force;
+    }
+
+    pub fn apply_torque(&mut self, torque: Vector3) {
+        self.torque = self.torque + torque;
+    }
+
+    /// instant change of momentum at a world space point
+    pub fn apply_impulse(&mut self, impulse: Vector3, point: Vector3) {
+        if self.is_static() {
+            return;
+        }
+
+        self.linear_velocity = self.linear_velocity + impulse * self.inverse_mass;
+        self.angular_velocity = self.angular_velocity
+            + self.apply_inverse_inertia((point - self.position).cross(impulse));
+    }
+
+    pub fn get_world_bounds(&self) -> Aabb {
+        match self.collider {
+            Collider::Box { .. } => self.obb().to_aabb(),
+            Collider::Sphere { .. } => {
+                let radius = self.world_radius();
+                let extent = Vector3::new(radius, radius, radius);
+                Aabb::new(self.position - extent, self.position + extent)
+            }
+        }
+    }
+
+    fn world_half_extents(&self) -> Vector3 {
+        match self.collider {
+            Collider::Box { half_extents } => multiply_components(half_extents, self.scale),
+            Collider::Sphere { .. } => {
+                let radius = self.world_radius();
+                Vector3::new(radius, radius, radius)
+            }
+        }
+    }
+
+    fn world_radius(&self) -> f32 {
+        match self.collider {
+            Collider::Box { half_extents } => {
+                multiply_components(half_extents, self.scale).length()
+            }
+            Collider::Sphere { radius } => {
+                radius * self.scale.x.max(self.scale.y).max(self.scale.z)
+            }
+        }
+    }
+
+    fn obb(&self) -> Obb {
+        Obb::new(
+            self.position,
+            self.world_half_extents(),
+            [
+                self.orientation.rotate_vector(Vector3::new(1.0, 0.0, 0.0)),
+                self.orientation.rotate_vector(Vector3::new(0.0, 1.0, 0.0)),
+                self.orientation.rotate_vector(Vector3::new(0.0, 0.0, 1.0)),
+            ],
+        )
+    }
+
+    /// diagonal of the inverse inertia tensor on the body axes, solid box or solid sphere.
+    /// reference: https://en.wikipedia.org/wiki/List_of_moments_of_inertia
+    fn local_inverse_inertia(&self) -> Vector3 {
+        if self.is_static() || self.locked_rotation {
+            return Vector3::new(0.0, 0.0, 0.0);
+        }
+
+        let mass = self.mass();
+        let inertia = match self.collider {
+            Collider::Box { .. } => {
+                let extents = self.world_half_extents();
+                let (x, y, z) = (
+                    extents.x * extents.x,
+                    extents.y * extents.y,
+                    extents.z * extents.z,
+                );
+                Vector3::new(
+                    mass * (y + z) / 3.0,
+                    mass * (x + z) / 3.0,
+                    mass * (x + y) / 3.0,
+                )
+            }
+            Collider::Sphere { .. } => {
+                let radius = self.world_radius();
+                let value = 0.4 * mass * radius * radius;
+                Vector3::new(value, value, value)
+            }
+        };
+
+        Vector3::new(1.0 / inertia.x, 1.0 / inertia.y, 1.0 / inertia.z)
+    }
+
+    /// the tensor is diagonal on the body axes, so the vector is rotated there and back
+    fn apply_inverse_inertia(&self, vector: Vector3) -> Vector3 {
+        let local = self.orientation.conjugate().rotate_vector(vector);
+        self.orientation
+            .rotate_vector(multiply_components(self.local_inverse_inertia(), local))
+    }
+
+    fn velocity_at(&self, point: Vector3) -> Vector3 {
+        self.linear_velocity + self.angular_velocity.cross(point - self.position)
+    }
+
+    fn integrate_velocity(&mut self, gravity: Vector3, dt: f32) {
+        if self.is_static() {
+            return;
+        }
+
+        let acceleration = gravity + self.force * self.inverse_mass;
+        self.linear_velocity =
+            (self.linear_velocity + acceleration * dt) * (1.0 / (1.0 + dt * self.linear_damping));
+        self.angular_velocity = (self.angular_velocity
+            + self.apply_inverse_inertia(self.torque) * dt)
+            * (1.0 / (1.0 + dt * self.angular_damping));
+
+        self.force = Vector3::new(0.0, 0.0, 0.0);
+        self.torque = Vector3::new(0.0, 0.0, 0.0);
+    }
+
+    fn integrate_position(&mut self, dt: f32) {
+        if self.is_static() {
+            return;
+        }
+
+        self.position = self.position + self.linear_velocity * dt;
+        self.orientation = self.orientation.integrate(self.angular_velocity, dt);
+    }
+}
+
+/// what the narrowphase finds between two bodies, `normal` goes from the first body to the second
+#[derive(Debug, Clone)]
+pub struct ContactManifold {
+    pub normal: Vector3,
+    pub depth: f32,
+    pub points: Vec<Vector3>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RayHit {
+    pub distance: f32,
+    pub point: Vector3,
+    /// surface normal of the collider at `point`
+    pub normal: Vector3,
+}
+
+struct Contact {
+    point: Vector3,
+    normal_impulse: f32,
+    tangent_impulses: [f32; 2],
+    /// separating speed asked by restitution
+    bias: f32,
+}
+
+struct Manifold {
+    a: usize,
+    b: usize,
+    normal: Vector3,
+    tangents: [Vector3; 2],
+    depth: f32,
+    friction: f32,
+    contacts: Vec<Contact>,
+}
+
+/// bodies are looked up by the name of the instance they move
+pub struct PhysicsWorld {
+    pub gravity: Vector3,
+    bodies: Vec<RigidBody>,
+    names: Vec<String>,
+    proxies: Vec<ProxyId>,
+    lookup: HashMap<String, usize>,
+    broadphase: SpatialIndex<String>,
+    /// (first, second, normal from first to second) found on the last step
+    touching: Vec<(usize, usize, Vector3)>,
+    accumulator: f32,
+}
+
+/// fixed timestep, sequential impulses with accumulated clamping, and a separate position correction.
+/// reference: https://gafferongames.com/post/fix_your_timestep/
+#[allow(dead_code)]
+impl PhysicsWorld {
+    pub fn new() -> Self {
+        PhysicsWorld {
+            gravity: Vector3::new(0.0, -9.81, 0.0),
+            bodies: Vec::new(),
+            names: Vec::new(),
+            proxies: Vec::new(),
+            lookup: HashMap::new(),
+            broadphase: SpatialIndex::new(BROADPHASE_MARGIN),
+            touching: Vec::new(),
+            accumulator: 0.0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bodies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bodies.is_empty()
+    }
+
+    /// replaces any body already added with the same name
+    pub fn add_body(&mut self, name: String, body: RigidBody) {
+        self.remove_body(&name);
+
+        let proxy = self
+            .broadphase
+            .insert(body.get_world_bounds(), name.clone());
+        self.lookup.insert(name.clone(), self.bodies.len());
+        self.bodies.push(body);
+        self.names.push(name);
+        self.proxies.push(proxy);
+    }
+
+    pub fn remove_body(&mut self, name: &str) -> Option<RigidBody> {
+        let index = self.lookup.remove(name)?;
+        self.broadphase.remove(self.proxies.swap_remove(index));
+        self.names.swap_remove(index);
+        self.touching.clear();
+
+        let body = self.bodies.swap_remove(index);
+        if let Some(moved) = self.names.get(index) {
+            self.lookup.insert(moved.clone(), index);
+        }
+
+        Some(body)
+    }
+
+    pub fn get_body(&self, name: &str) -> Option<&RigidBody> {
+        self.lookup.get(name).map(|index| &self.bodies[*index])
+    }
+
+    pub fn get_body_mut(&mut self, name: &str) -> Option<&mut RigidBody> {
+        let index = *self.lookup.get(name)?;
+        Some(&mut self.bodies[index])
+    }
+
+    pub fn bodies(&self) -> impl Iterator<Item = (&String, &RigidBody)> {
+        self.names.iter().zip(self.bodies.iter())
+    }
+
+    /// normals of the contacts found on the last step, pointing towards the named body
+    pub fn contact_normals(&self, name: &str) -> Vec<Vector3> {
+        let index = match self.lookup.get(name) {
+            Some(index) => *index,
+            None => return Vec::new(),
+        };
+
+        self.touching
+            .iter()
+            .filter_map(|(a, b, normal)| {
+                if *a == index {
+                    Some(-*normal)
+                } else if *b == index {
+                    Some(*normal)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// penetrations of a capsule, going from `start` to `end`, against every collider.
+    /// Normals point towards the capsule, for kinematic movement that is not simulated.
+    pub fn capsule_contacts(
+        &self,
+        start: Vector3,
+        end: Vector3,
+        radius: f32,
+    ) -> Vec<ContactManifold> {
+        let extent = Vector3::new(radius, radius, radius);
+        let bounds = Aabb::new(start.min(end) - extent, start.max(end) + extent);
+
+        self.broadphase
+            .query_aabb(&bounds)
+            .into_iter()
+            .filter_map(|name| {
+                let body = &self.bodies[self.lookup[name]];
+
+                match body.collider {
+                    Collider::Sphere { .. } => {
+                        let point = closest_point_on_segment(start, end, body.position);
+                        collide_spheres(body.position, body.world_radius(), point, radius)
+                    }
+                    Collider::Box { .. } => {
+                        let obb = body.obb();
+                        collide_box_sphere(&obb, closest_point_to_box(start, end, &obb), radius)
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// closest collider along the ray, with the name of its body
+    pub fn ray_cast(&self, ray: &Ray, max_distance: f32) -> Option<(&String, RayHit)> {
+        let mut closest: Option<(&String, RayHit)> = None;
+
+        for (name, bounds_distance) in self.broadphase.query_ray(ray, max_distance) {
+            // candidates come sorted, nothing after this one can be closer
+            if closest
+                .as_ref()
+                .is_some_and(|(_, hit)| hit.distance < bounds_distance)
+            {
+                break;
+            }
+
+            let body = &self.bodies[self.lookup[name]];
+            let hit = match body.collider {
+                Collider::Sphere { .. } => ray
+                    .intersect_sphere(&Sphere::new(body.position, body.world_radius()))
+                    .map(|distance| {
+                        let point = ray.point_at(distance);
+                        (distance, point, (point - body.position).normalized())
+                    }),
+                Collider::Box { .. } => {
+                    let obb = body.obb();
+                    ray.intersect_obb(&obb).map(|distance| {
+                        let point = ray.point_at(distance);
+                        (distance, point, box_face_normal(&obb, point))
+                    })
+                }
+            };
+
+            if let Some((distance, point, normal)) = hit {
+                if distance <= max_distance
+                    && closest
+                        .as_ref()
+                        .is_none_or(|(_, hit)| distance < hit.distance)
+                {
+                    closest = Some((
+                        name,
+                        RayHit {
+                            distance,
+                            point,
+                            normal,
+                        },
+                    ));
+                }
+            }
+        }
+
+        closest
+    }
+
+    /// runs as many fixed steps as fit on the elapsed time, the rest is kept for the next call
+    pub fn update(&mut self, elapsed: f32) -> u32 {
+        self.accumulator += elapsed;
+
+        let mut steps = 0;
+        while self.accumulator >= FIXED_TIMESTEP {
+            if steps == MAX_STEPS_PER_UPDATE {
+                self.accumulator = 0.0;
+                break;
+            }
+
+            self.step(FIXED_TIMESTEP);
+            self.accumulator -= FIXED_TIMESTEP;
+            steps += 1;
+        }
+
+        steps
+    }
+
+    pub fn step(&mut self, dt: f32) {
+        let gravity = self.gravity;
+        for body in self.bodies.iter_mut() {
+            body.integrate_velocity(gravity, dt);
+        }
+
+        let mut manifolds = self.find_contacts();
+
+        for manifold in manifolds.iter_mut() {
+            self.prepare(manifold);
+        }
+
+        for _ in 0..SOLVER_ITERATIONS {
+            for manifold in manifolds.iter_mut() {
+                self.solve_velocity(manifold);
+            }
+        }
+
+        for body in self.bodies.iter_mut() {
+            body.integrate_position(dt);
+        }
+
+        for manifold in manifolds.iter() {
+            self.correct_position(manifold);
+        }
+
+        self.touching = manifolds
+            .iter()
+            .map(|manifold| (manifold.a, manifold.b, manifold.normal))
+            .collect();
+
+        for (body, proxy) in self.bodies.iter().zip(self.proxies.iter()) {
+            if !body.is_static() {
+                self.broadphase.update(*proxy, body.get_world_bounds());
+            }
+        }
+    }
+
+    /// broadphase through the bvh, static pairs are skipped
+    fn find_contacts(&self) -> Vec<Manifold> {
+        let mut manifolds = Vec::new();
+
+        for (a, body) in self.bodies.iter().enumerate() {
+            if body.is_static() {
+                continue;
+            }
+
+            for name in self.broadphase.query_aabb(&body.get_world_bounds()) {
+                let b = self.lookup[name];
+                let other = &self.bodies[b];
+
+                // dynamic pairs are found from both sides, only one is kept
+                if b == a || (!other.is_static() && b < a) {
+                    continue;
+                }
+
+                if let Some(contact) = collide(body, other) {
+                    manifolds.push(Manifold {
+                        a,
+                        b,
+                        normal: contact.normal,
+                        tangents: tangent_basis(contact.normal),
+                        depth: contact.depth,
+                        friction: (body.friction * other.friction).sqrt(),
+                        contacts: contact
+                            .points
+                            .into_iter()
+                            .map(|point| Contact {
+                                point,
+                                normal_impulse: 0.0,
+                                tangent_impulses: [0.0, 0.0],
+                                bias: 0.0,
+                            })
+                            .collect(),
+                    });
+                }
+            }
+        }
+
+        manifolds
+    }
+
+    fn prepare(&self, manifold: &mut Manifold) {
+        let a = &self.bodies[manifold.a];
+        let b = &self.bodies[manifold.b];
+        let restitution = a.restitution.max(b.restitution);
+
+        for contact in manifold.contacts.iter_mut() {
+            let relative = b.velocity_at(contact.point) - a.velocity_at(contact.point);
+            let approaching = relative.dot(manifold.normal);
+
+            if approaching < -RESTITUTION_THRESHOLD {
+                contact.bias = -restitution * approaching;
+            }
+        }
+    }
+
+    fn solve_velocity(&mut self, manifold: &mut Manifold) {
+        let (a, b) = pair_mut(&mut self.bodies, manifold.a, manifold.b);
+        let normal = manifold.normal;
+
+        for contact in manifold.contacts.iter_mut() {
+            // friction first, bounded by the normal impulse of the previous iteration
+            for (tangent, accumulated) in manifold
+                .tangents
+                .iter()
+                .zip(contact.tangent_impulses.iter_mut())
+            {
+                let relative = b.velocity_at(contact.point) - a.velocity_at(contact.point);
+                let mass = effective_mass(a, b, contact.point, *tangent);
+                if mass == 0.0 {
+                    continue;
+                }
+
+                let limit = manifold.friction * contact.normal_impulse;
+                let previous = *accumulated;
+                *accumulated = (previous - relative.dot(*tangent) / mass)
+                    .max(-limit)
+                    .min(limit);
+
+                let impulse = *tangent * (*accumulated - previous);
+                a.apply_impulse(-impulse, contact.point);
+                b.apply_impulse(impulse, contact.point);
+            }
+
+            let relative = b.velocity_at(contact.point) - a.velocity_at(contact.point);
+            let mass = effective_mass(a, b, contact.point, normal);
+            if mass == 0.0 {
+                continue;
+            }
+
+            let previous = contact.normal_impulse;
+            contact.normal_impulse =
+                (previous + (contact.bias - relative.dot(normal)) / mass).max(0.0);
+
+            let impulse = normal * (contact.normal_impulse - previous);
+            a.apply_impulse(-impulse, contact.point);
+            b.apply_impulse(impulse, contact.point);
+        }
+    }
+
+    /// pushes the pair apart along the normal, split by inverse mass
+    fn correct_position(&mut self, manifold: &Manifold) {
+        let (a, b) = pair_mut(&mut self.bodies, manifold.a, manifold.b);
+        let inverse_mass = a.inverse_mass + b.inverse_mass;
+        if inverse_mass == 0.0 {
+            return;
+        }
+
+        let correction =
+            (manifold.depth - PENETRATION_SLOP).max(0.0) * POSITION_CORRECTION / inverse_mass;
+        let correction = manifold.normal * correction;
+
+        a.position = a.position - correction * a.inverse_mass;
+        b.position = b.position + correction * b.inverse_mass;
+    }
+}
+
+/// narrowphase, `None` when the colliders do not touch
+pub fn collide(a: &RigidBody, b: &RigidBody) -> Option<ContactManifold> {
+    match (a.collider, b.collider) {
+        (Collider::Sphere { .. }, Collider::Sphere { .. }) => {
+            collide_spheres(a.position, a.world_radius(), b.position, b.world_radius())
+        }
+        (Collider::Box { .. }, Collider::Sphere { .. }) => {
+            collide_box_sphere(&a.obb(), b.position, b.world_radius())
+        }
+        (Collider::Sphere { .. }, Collider::Box { .. }) => {
+            collide_box_sphere(&b.obb(), a.position, a.world_radius()).map(|mut manifold| {
+                manifold.normal = -manifold.normal;
+                manifold
+            })
+        }
+        (Collider::Box { .. }, Collider::Box { .. }) => collide_boxes(&a.obb(), &b.obb()),
+    }
+}
+
+fn collide_spheres(
+    center_a: Vector3,
+    radius_a: f32,
+    center_b: Vector3,
+    radius_b: f32,
+) -> Option<ContactManifold> {
+    let offset = center_b - center_a;
+    let distance = offset.length();
+    if distance > radius_a + radius_b {
+        return None;
+    }
+
+    let normal = if distance > 0.0 {
+        offset * (1.0 / distance)
+    } else {
+        Vector3::new(0.0, 1.0, 0.0)
+    };
+
+    Some(ContactManifold {
+        normal,
+        depth: radius_a + radius_b - distance,
+        points: vec![center_a + normal * radius_a],
+    })
+}
+
+/// reference: Real-Time Collision Detection (Christer Ericson), 5.2.5
+fn collide_box_sphere(obb: &Obb, center: Vector3, radius: f32) -> Option<ContactManifold> {
+    let closest = obb.closest_point(center);
+    let offset = center - closest;
+    let distance = offset.length();
+
+    if distance > radius {
+        return None;
+    }
+
+    if distance > 0.0 {
+        return Some(ContactManifold {
+            normal: offset * (1.0 / distance),
+            depth: radius - distance,
+            points: vec![closest],
+        });
+    }
+
+    // the center is inside the box, it leaves through the closest face
+    let local = center - obb.center;
+    let extents = obb.half_extents.to_array();
+    let (axis, face_distance) = (0..3)
+        .map(|axis| (axis, extents[axis] - local.dot(obb.axes[axis]).abs()))
+        .fold((0, f32::MAX), |closest, face| {
+            if face.1 < closest.1 {
+                face
+            } else {
+                closest
+            }
+        });
+
+    let normal = if local.dot(obb.axes[axis]) < 0.0 {
+        -obb.axes[axis]
+    } else {
+        obb.axes[axis]
+    };
+
+    Some(ContactManifold {
+        normal,
+        depth: radius + face_distance,
+        points: vec![center + normal * face_distance],
+    })
+}
+
+/// separating axis test on the 15 candidate axes, contacts are the corners found inside the other box.
+/// reference: Real-Time Collision Detection (Christer Ericson), 4.4.1
+fn collide_boxes(a: &Obb, b: &Obb) -> Option<ContactManifold> {
+    let offset = b.center - a.center;
+    let mut axes: Vec<(Vector3, bool)> = Vec::with_capacity(15);
+
+    for axis in a.axes.iter().chain(b.axes.iter()) {
+        axes.push((*axis, false));
+    }
+
+    for axis_a in a.axes.iter() {
+        for axis_b in b.axes.iter() {
+            let cross = axis_a.cross(*axis_b);
+            // parallel edges, already covered by the face axes
+            if cross.length() > 0.0001 {
+                axes.push((cross.normalized(), true));
+            }
+        }
+    }
+
+    let mut best: Option<(Vector3, f32)> = None;
+
+    for (axis, is_edge) in axes {
+        let overlap =
+            projected_radius(a, axis) + projected_radius(b, axis) - offset.dot(axis).abs();
+        if overlap < 0.0 {
+            return None;
+        }
+
+        // face contacts are preferred when the depth is about the same, they give stable resting contacts
+        let weighted = if is_edge {
+            overlap * 1.05 + 0.001
+        } else {
+            overlap
+        };
+        if best.is_none_or(|(_, depth)| weighted < depth) {
+            let axis = if offset.dot(axis) < 0.0 { -axis } else { axis };
+            best = Some((axis, weighted));
+        }
+    }
+
+    let (normal, _) = best?;
+    let depth = projected_radius(a, normal) + projected_radius(b, normal) - offset.dot(normal);
+
+    let expanded_a = Obb::new(a.center, a.half_extents + tolerance(), a.axes);
+    let expanded_b = Obb::new(b.center, b.half_extents + tolerance(), b.axes);
+
+    let mut points: Vec<Vector3> = b
+        .corners()
+        .iter()
+        .filter(|corner| expanded_a.contains_point(**corner))
+        .cloned()
+        .collect();
+    points.extend(
+        a.corners()
+            .iter()
+            .filter(|corner| expanded_b.contains_point(**corner)),
+    );
+
+    // edge against edge, no corner is inside
+    if points.is_empty() {
+        let on_a = a.closest_point(b.center);
+        let on_b = b.closest_point(a.center);
+        points.push((on_a + on_b) * 0.5);
+    }
+
+    Some(ContactManifold {
+        normal,
+        depth,
+        points,
+    })
+}
+
+fn closest_point_on_segment(start: Vector3, end: Vector3, point: Vector3) -> Vector3 {
+    let segment = end - start;
+    let length_squared = segment.dot(segment);
+    if length_squared == 0.0 {
+        return start;
+    }
+
+    start + segment * clamp((point - start).dot(segment) / length_squared, 0.0, 1.0)
+}
+
+/// point of the segment closest to the box, refined a few times since both shapes are convex
+fn closest_point_to_box(start: Vector3, end: Vector3, obb: &Obb) -> Vector3 {
+    let mut point = closest_point_on_segment(start, end, obb.center);
+
+    for _ in 0..4 {
+        point = closest_point_on_segment(start, end, obb.closest_point(point));
+    }
+
+    point
+}
+
+/// normal of the face the point is closest to, relative to the size of the box
+fn box_face_normal(obb: &Obb, point: Vector3) -> Vector3 {
+    let offset = point - obb.center;
+    let extents = obb.half_extents.to_array();
+
+    let (axis, distance) = (0..3).map(|axis| (axis, offset.dot(obb.axes[axis]))).fold(
+        (0, 0.0),
+        |best: (usize, f32), (axis, distance)| {
+            if (distance / extents[axis]).abs() > (best.1 / extents[best.0]).abs() {
+                (axis, distance)
+            } else {
+                best
+            }
+        },
+    );
+
+    if distance < 0.0 {
+        -obb.axes[axis]
+    } else {
+        obb.axes[axis]
+    }
+}
+
+fn projected_radius(obb: &Obb, axis: Vector3) -> f32 {
+    let extents = obb.half_extents.to_array();
+    (0..3)
+        .map(|index| extents[index] * obb.axes[index].dot(axis).abs())
+        .sum()
+}
+
+fn tolerance() -> Vector3 {
+    Vector3::new(CONTACT_TOLERANCE, CONTACT_TOLERANCE, CONTACT_TOLERANCE)
+}
+
+/// inverse of the mass felt by an impulse along `direction` at `point`
+fn effective_mass(a: &RigidBody, b: &RigidBody, point: Vector3, direction: Vector3) -> f32 {
+    let arm_a = point - a.position;
+    let arm_b = point - b.position;
+
+    let angular_a = a.apply_inverse_inertia(arm_a.cross(direction)).cross(arm_a);
+    let angular_b = b.apply_inverse_inertia(arm_b.cross(direction)).cross(arm_b);
+
+    a.inverse_mass + b.inverse_mass + (angular_a + angular_b).dot(direction)
+}
+
+/// two directions perpendicular to the normal and to each other
+fn tangent_basis(normal: Vector3) -> [Vector3; 2] {
+    let reference = if normal.x.abs() < 0.57 {
+        Vector3::new(1.0, 0.0, 0.0)
+    } else {
+        Vector3::new(0.0, 1.0, 0.0)
+    };
+
+    let first = normal.cross(reference).normalized();
+    [first, normal.cross(first)]
+}
+
+fn pair_mut(bodies: &mut [RigidBody], a: usize, b: usize) -> (&mut RigidBody, &mut RigidBody) {
+    if a < b {
+        let (left, right) = bodies.split_at_mut(b);
+        (&mut left[a], &mut right[0])
+    } else {
+        let (left, right) = bodies.split_at_mut(a);
+        (&mut right[0], &mut left[b])
+    }
+}
+
+fn multiply_components(a: Vector3, b: Vector3) -> Vector3 {
+    Vector3::new(a.x * b.x, a.y * b.y, a.z * b.z)
+}
+
+#[rustfmt::skip]
+fn columns_to_matrix(x: Vector3, y: Vector3, z: Vector3) -> Matrix4 {
+    Matrix4::from([
+        x.x,    y.x,    z.x,    0.0,
+        x.y,    y.y,    z.y,    0.0,
+        x.z,    y.z,    z.z,    0.0,
+        0.0,    0.0,    0.0,    1.0,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_box() -> Collider {
+        Collider::Box {
+            half_extents: Vector3::new(0.5, 0.5, 0.5),
+        }
+    }
+
+    /// top face at y = 0
+    fn floor() -> RigidBody {
+        RigidBody::fixed(Collider::Box {
+            half_extents: Vector3::new(50.0, 0.5, 50.0),
+        })
+        .with_position(Vector3::new(0.0, -0.5, 0.0))
+    }
+
+    fn run(world: &mut PhysicsWorld, seconds: f32) {
+        for _ in 0..(seconds / FIXED_TIMESTEP).round() as usize {
+            world.step(FIXED_TIMESTEP);
+        }
+    }
+
+    #[test]
+    fn body_falls_under_gravity() {
+        let mut world = PhysicsWorld::new();
+        world.add_body(
+            String::from("ball"),
+            RigidBody::dynamic(Collider::Sphere { radius: 0.5 }, 1.0)
+                .with_position(Vector3::new(0.0, 100.0, 0.0)),
+        );
+
+        run(&mut world, 1.0);
+        let ball = world.get_body("ball").unwrap();
+
+        assert!((ball.linear_velocity.y + 9.81).abs() < 0.01);
+        // semi-implicit euler lands a little below the exact 100 - g / 2
+        assert!((ball.position().y - (100.0 - 9.81 / 2.0)).abs() < 0.1);
+    }
+
+    #[test]
+    fn static_bodies_do_not_move() {
+        let mut world = PhysicsWorld::new();
+        world.add_body(String::from("floor"), floor());
+
+        run(&mut world, 1.0);
+
+        assert_eq!(
+            world.get_body("floor").unwrap().position(),
+            Vector3::new(0.0, -0.5, 0.0)
+        );
+    }
+
+    #[test]
+    fn sphere_rests_on_floor() {
+        let mut world = PhysicsWorld::new();
+        world.add_body(String::from("floor"), floor());
+        world.add_body(
+            String::from("ball"),
+            RigidBody::dynamic(Collider::Sphere { radius: 0.5 }, 1.0)
+                .with_restitution(0.0)
+                .with_position(Vector3::new(0.0, 3.0, 0.0)),
+        );
+
+        run(&mut world, 3.0);
+        let ball = world.get_body("ball").unwrap();
+
+        assert!((ball.position().y - 0.5).abs() < PENETRATION_SLOP * 2.0);
+        assert!(ball.linear_velocity.length() < 0.05);
+    }
+
+    #[test]
+    fn box_rests_flat_on_floor() {
+        let mut world = PhysicsWorld::new();
+        world.add_body(String::from("floor"), floor());
+        world.add_body(
+            String::from("crate"),
+            RigidBody::dynamic(unit_box(), 2.0).with_position(Vector3::new(0.0, 2.0, 0.0)),
+        );
+
+        run(&mut world, 4.0);
+        let body = world.get_body("crate").unwrap();
+        let up = body
+            .orientation()
+            .rotate_vector(Vector3::new(0.0, 1.0, 0.0));
+
+        assert!((body.position().y - 0.5).abs() < PENETRATION_SLOP * 2.0);
+        assert!(up.dot(Vector3::new(0.0, 1.0, 0.0)) > 0.999);
+        assert!(body.linear_velocity.length() < 0.05);
+        assert!(body.angular_velocity.length() < 0.05);
+    }
+
+    #[test]
+    fn box_stacked_on_box_stays_on_top() {
+        let mut world = PhysicsWorld::new();
+        world.add_body(String::from("floor"), floor());
+        world.add_body(
+            String::from("bottom"),
+            RigidBody::dynamic(unit_box(), 1.0).with_position(Vector3::new(0.0, 0.5, 0.0)),
+        );
+        world.add_body(
+            String::from("top"),
+            RigidBody::dynamic(unit_box(), 1.0).with_position(Vector3::new(0.1, 1.6, 0.0)),
+        );
+
+        run(&mut world, 4.0);
+        let top = world.get_body("top").unwrap();
+
+        assert!((top.position().y - 1.5).abs() < PENETRATION_SLOP * 4.0);
+        let bottom = world.get_body("bottom").unwrap();
+
+        assert!((top.position().x - bottom.position().x).abs() < 0.25);
+    }
+
+    #[test]
+    fn elastic_spheres_exchange_velocities() {
+        let mut world = PhysicsWorld::new();
+        world.gravity = Vector3::new(0.0, 0.0, 0.0);
+
+        let mut left = RigidBody::dynamic(Collider::Sphere { radius: 0.5 }, 1.0)
+            .with_restitution(1.0)
+            .with_friction(0.0)
+            .with_position(Vector3::new(-2.0, 0.0, 0.0));
+        left.linear_velocity = Vector3::new(5.0, 0.0, 0.0);
+
+        let right = RigidBody::dynamic(Collider::Sphere { radius: 0.5 }, 1.0)
+            .with_restitution(1.0)
+            .with_friction(0.0)
+            .with_position(Vector3::new(2.0, 0.0, 0.0));
+
+        world.add_body(String::from("left"), left);
+        world.add_body(String::from("right"), right);
+
+        run(&mut world, 1.0);
+
+        let left = world.get_body("left").unwrap();
+        let right = world.get_body("right").unwrap();
+        assert!(left.linear_velocity.length() < 0.01);
+        assert!((right.linear_velocity.x - 5.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn inelastic_sphere_does_not_bounce() {
+        let mut world = PhysicsWorld::new();
+        // the bounciest of the pair wins
+        world.add_body(String::from("floor"), floor().with_restitution(0.0));
+
+        let mut ball = RigidBody::dynamic(Collider::Sphere { radius: 0.5 }, 1.0)
+            .with_restitution(0.0)
+            .with_position(Vector3::new(0.0, 0.6, 0.0));
+        ball.linear_velocity = Vector3::new(0.0, -10.0, 0.0);
+        world.add_body(String::from("ball"), ball);
+
+        run(&mut world, 0.1);
+
+        assert!(world.get_body("ball").unwrap().linear_velocity.y <= 0.0);
+    }
+
+    #[test]
+    fn friction_stops_a_sliding_box() {
+        let slide = |friction: f32| {
+            let mut world = PhysicsWorld::new();
+            world.add_body(String::from("floor"), floor().with_friction(friction));
+
+            let mut body = RigidBody::dynamic(unit_box(), 1.0)
+                .with_friction(friction)
+                .with_locked_rotation()
+                .with_position(Vector3::new(0.0, 0.5, 0.0));
+            body.linear_velocity = Vector3::new(3.0, 0.0, 0.0);
+            world.add_body(String::from("crate"), body);
+
+            run(&mut world, 2.0);
+            world.get_body("crate").unwrap().linear_velocity.x
+        };
+
+        assert!(slide(0.8).abs() < 0.01);
+        assert!((slide(0.0) - 3.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn fixed_timestep_accumulates_elapsed_time() {
+        let mut world = PhysicsWorld::new();
+
+        assert_eq!(world.update(FIXED_TIMESTEP * 0.6), 0);
+        assert_eq!(world.update(FIXED_TIMESTEP * 0.6), 1);
+        assert_eq!(world.update(FIXED_TIMESTEP * 2.0), 2);
+        // a long stall is dropped instead of being caught up
+        assert_eq!(world.update(10.0), MAX_STEPS_PER_UPDATE);
+        assert_eq!(world.update(0.0), 0);
+    }
+
+    #[test]
+    fn contact_normals_point_towards_the_body() {
+        let mut world = PhysicsWorld::new();
+        world.add_body(String::from("floor"), floor());
+        world.add_body(
+            String::from("crate"),
+            RigidBody::dynamic(unit_box(), 1.0).with_position(Vector3::new(0.0, 0.5, 0.0)),
+        );
+
+        world.step(FIXED_TIMESTEP);
+
+        let normals = world.contact_normals("crate");
+        assert_eq!(normals.len(), 1);
+        assert!(normals[0].dot(Vector3::new(0.0, 1.0, 0.0)) > 0.999);
+        assert!(world.contact_normals("floor")[0].y < -0.999);
+        assert!(world.contact_normals("missing").is_empty());
+    }
+
+    #[test]
+    fn collide_separated_bodies() {
+        let a = RigidBody::dynamic(unit_box(), 1.0);
+        let b = RigidBody::dynamic(unit_box(), 1.0).with_position(Vector3::new(1.1, 0.0, 0.0));
+        let ball = RigidBody::dynamic(Collider::Sphere { radius: 0.5 }, 1.0)
+            .with_position(Vector3::new(0.0, 0.0, 1.1));
+
+        assert!(collide(&a, &b).is_none());
+        assert!(collide(&a, &ball).is_none());
+    }
+
+    #[test]
+    fn collide_sphere_and_box_normal_direction() {
+        let cube = RigidBody::dynamic(unit_box(), 1.0);
+        let ball = RigidBody::dynamic(Collider::Sphere { radius: 0.5 }, 1.0)
+            .with_position(Vector3::new(0.0, 0.0, 0.9));
+
+        let box_first = collide(&cube, &ball).unwrap();
+        let sphere_first = collide(&ball, &cube).unwrap();
+
+        assert_eq!(box_first.normal, Vector3::new(0.0, 0.0, 1.0));
+        assert_eq!(sphere_first.normal, Vector3::new(0.0, 0.0, -1.0));
+        assert!((box_first.depth - 0.1).abs() < 0.0001);
+    }
+
+    #[test]
+    fn collide_rotated_boxes_on_edge() {
+        let flat = RigidBody::dynamic(unit_box(), 1.0);
+        let tilted = RigidBody::dynamic(unit_box(), 1.0).with_transform(
+            MatrixOperation::translation(Vector3::new(0.0, 1.2, 0.0)) * Quaternion::rotate_z(45.0),
+        );
+
+        // the tilted box reaches sqrt(0.5) below its center
+        let manifold = collide(&flat, &tilted).unwrap();
+        assert!(manifold.normal.dot(Vector3::new(0.0, 1.0, 0.0)) > 0.999);
+        assert!((manifold.depth - (0.5 + 0.5_f32.sqrt() - 1.2)).abs() < 0.0001);
+    }
+
+    #[test]
+    fn body_transform_keeps_scale() {
+        let transform = MatrixOperation::translation(Vector3::new(1.0, 2.0, 3.0))
+            * Quaternion::rotate_y(30.0)
+            * MatrixOperation::scale(Vector3::new(2.0, 0.5, 1.0));
+        let body = RigidBody::fixed(unit_box()).with_transform(transform);
+
+        let expected = Vector3::new(0.3, -0.7, 1.1);
+        assert!(
+            (body.transform().transform_point(expected) - transform.transform_point(expected))
+                .length()
+                < 0.0001
+        );
+        assert_eq!(body.world_half_extents(), Vector3::new(1.0, 0.25, 0.5));
+    }
+
+    #[test]
+    fn capsule_contacts_against_floor_and_sphere() {
+        let mut world = PhysicsWorld::new();
+        world.add_body(String::from("floor"), floor());
+        world.add_body(
+            String::from("ball"),
+            RigidBody::fixed(Collider::Sphere { radius: 1.0 })
+                .with_position(Vector3::new(3.0, 1.0, 0.0)),
+        );
+
+        let standing = world.capsule_contacts(
+            Vector3::new(0.0, 0.4, 0.0),
+            Vector3::new(0.0, 1.5, 0.0),
+            0.5,
+        );
+        assert_eq!(standing.len(), 1);
+        assert_eq!(standing[0].normal, Vector3::new(0.0, 1.0, 0.0));
+        assert!((standing[0].depth - 0.1).abs() < 0.0001);
+
+        let beside_ball = world.capsule_contacts(
+            Vector3::new(1.6, 1.0, 0.0),
+            Vector3::new(1.6, 2.0, 0.0),
+            0.5,
+        );
+        assert_eq!(beside_ball.len(), 1);
+        assert_eq!(beside_ball[0].normal, Vector3::new(-1.0, 0.0, 0.0));
+
+        assert!(world
+            .capsule_contacts(
+                Vector3::new(0.0, 1.0, 0.0),
+                Vector3::new(0.0, 2.0, 0.0),
+                0.5
+            )
+            .is_empty());
+    }
+
+    #[test]
+    fn ray_cast_returns_closest_surface() {
+        let mut world = PhysicsWorld::new();
+        world.add_body(String::from("floor"), floor());
+        world.add_body(
+            String::from("ball"),
+            RigidBody::fixed(Collider::Sphere { radius: 1.0 })
+                .with_position(Vector3::new(0.0, 3.0, 0.0)),
+        );
+        world.add_body(
+            String::from("ramp"),
+            RigidBody::fixed(unit_box()).with_transform(
+                MatrixOperation::translation(Vector3::new(5.0, 0.0, 0.0))
+                    * Quaternion::rotate_z(30.0),
+            ),
+        );
+
+        let down = Vector3::new(0.0, -1.0, 0.0);
+
+        let (name, hit) = world
+            .ray_cast(&Ray::new(Vector3::new(0.0, 10.0, 0.0), down), 100.0)
+            .unwrap();
+        assert_eq!(name, "ball");
+        assert_eq!(hit.distance, 6.0);
+        assert_eq!(hit.normal, Vector3::new(0.0, 1.0, 0.0));
+
+        let (name, hit) = world
+            .ray_cast(&Ray::new(Vector3::new(2.0, 10.0, 0.0), down), 100.0)
+            .unwrap();
+        assert_eq!(name, "floor");
+        assert_eq!(hit.point, Vector3::new(2.0, 0.0, 0.0));
+
+        let (name, hit) = world
+            .ray_cast(&Ray::new(Vector3::new(5.0, 10.0, 0.0), down), 100.0)
+            .unwrap();
+        assert_eq!(name, "ramp");
+        assert!((hit.normal.y - 30.0_f32.to_radians().cos()).abs() < 0.0001);
+
+        assert!(world
+            .ray_cast(&Ray::new(Vector3::new(2.0, 10.0, 0.0), down), 5.0)
+            .is_none());
+    }
+
+    #[test]
+    fn remove_body_keeps_lookup_consistent() {
+        let mut world = PhysicsWorld::new();
+        world.add_body(String::from("a"), floor());
+        world.add_body(
+            String::from("b"),
+            RigidBody::dynamic(unit_box(), 1.0).with_position(Vector3::new(0.0, 5.0, 0.0)),
+        );
+        world.add_body(
+            String::from("c"),
+            RigidBody::dynamic(unit_box(), 3.0).with_position(Vector3::new(0.0, 9.0, 0.0)),
+        );
+
+        assert!(world.remove_body("a").is_some());
+        assert!(world.remove_body("a").is_none());
+        assert_eq!(world.len(), 2);
+        assert_eq!(world.get_body("c").unwrap().mass(), 3.0);
+        assert_eq!(world.get_body("b").unwrap().mass(), 1.0);
+    }
+}
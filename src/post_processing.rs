@@ -0,0 +1,428 @@
+use crate::render_target::RenderTarget;
+use crate::shaders::{FragmentShader, VertexShader};
+
+use glium::backend::glutin::Display;
+use glium::index::{NoIndices, PrimitiveType};
+use glium::program::{ProgramCreationError, ProgramCreationInput};
+use glium::texture::UncompressedFloatFormat;
+use glium::uniforms::{Sampler, SamplerWrapFunction, Uniforms};
+use glium::{DrawParameters, Program, Surface, Texture2d, VertexBuffer};
+use std::time::Instant;
+
+/// half floats keep colors over 1.0 until tone mapping
+const HDR_FORMAT: UncompressedFloatFormat = UncompressedFloatFormat::F16F16F16F16;
+
+#[derive(Copy, Clone)]
+struct ScreenVertex {
+    position: [f32; 2],
+}
+implement_vertex!(ScreenVertex, position);
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum PostEffect {
+    /// what is brighter than `threshold` is blurred at half resolution and added back
+    Bloom {
+        threshold: f32,
+        /// 0.0 to 1.0, how smooth the start of the bloom is under the threshold
+        soft_knee: f32,
+        intensity: f32,
+        /// each one is a horizontal and a vertical blur, more passes spread the glow further
+        blur_passes: u32,
+    },
+    /// exposure then the ACES filmic curve, from HDR to 0.0..1.0
+    ToneMapping { exposure: f32 },
+    /// linear to display colors, 2.2 approximates sRGB.
+    /// only for scenes lit in linear space, the built-in shaders and textures are not
+    Gamma { gamma: f32 },
+    /// anti aliasing on the final image, works best after gamma
+    Fxaa,
+    /// darkens the corners
+    Vignette {
+        intensity: f32,
+        radius: f32,
+        softness: f32,
+    },
+    /// a fragment shader source. it gets the `texCoords` input and the `sceneTexture`,
+    /// `texelSize` and `time` uniforms, and writes `outputColor`
+    Custom { fragment_shader: String },
+}
+
+#[allow(dead_code)]
+impl PostEffect {
+    pub fn bloom() -> Self {
+        PostEffect::Bloom {
+            threshold: 1.0,
+            soft_knee: 0.5,
+            intensity: 0.8,
+            blur_passes: 3,
+        }
+    }
+
+    pub fn tone_mapping() -> Self {
+        PostEffect::ToneMapping { exposure: 1.0 }
+    }
+
+    pub fn gamma() -> Self {
+        PostEffect::Gamma { gamma: 2.2 }
+    }
+
+    pub fn vignette() -> Self {
+        PostEffect::Vignette {
+            intensity: 0.6,
+            radius: 0.55,
+            softness: 0.35,
+        }
+    }
+}
+
+/// one step of the chain. the effect parameters can be changed at any time,
+/// a custom shader source is only compiled when the pass is added
+pub struct PostPass {
+    pub name: String,
+    pub effect: PostEffect,
+    pub enabled: bool,
+    custom_program: Option<Program>,
+}
+
+/// shared by every pass using the same built-in effect
+struct PostPrograms {
+    copy: Program,
+    bright_pass: Program,
+    blur: Program,
+    bloom_composite: Program,
+    tone_mapping: Program,
+    gamma: Program,
+    fxaa: Program,
+    vignette: Program,
+}
+
+/// scene and intermediate images, sized like the window
+struct PostTargets {
+    scene: RenderTarget,
+    swap: [RenderTarget; 2],
+    /// half resolution, for the bloom blur
+    bloom: [RenderTarget; 2],
+}
+
+/// The window cameras draw into an HDR texture, then each enabled pass draws the previous
+/// image through its shader. Passes alternate between two textures and the last one draws on the window.
+/// reference: https://learnopengl.com/Advanced-Lighting/Bloom
+pub struct PostProcessing {
+    display: Display,
+    passes: Vec<PostPass>,
+    programs: PostPrograms,
+    screen: VertexBuffer<ScreenVertex>,
+    targets: Option<PostTargets>,
+    start: Instant,
+}
+
+#[allow(dead_code)]
+impl PostProcessing {
+    /// no passes, the scene is copied to the window as it is
+    pub fn new(display: &Display) -> Self {
+        let programs = PostPrograms {
+            copy: compile(display, &FragmentShader::post_copy()).unwrap(),
+            bright_pass: compile(display, &FragmentShader::bright_pass()).unwrap(),
+            blur: compile(display, &FragmentShader::gaussian_blur()).unwrap(),
+            bloom_composite: compile(display, &FragmentShader::bloom_composite()).unwrap(),
+            tone_mapping: compile(display, &FragmentShader::tone_mapping()).unwrap(),
+            gamma: compile(display, &FragmentShader::gamma()).unwrap(),
+            fxaa: compile(display, &FragmentShader::fxaa()).unwrap(),
+            vignette: compile(display, &FragmentShader::vignette()).unwrap(),
+        };
+
+        let screen = VertexBuffer::new(
+            display,
+            &[
+                ScreenVertex {
+                    position: [-1.0, -1.0],
+                },
+                ScreenVertex {
+                    position: [1.0, -1.0],
+                },
+                ScreenVertex {
+                    position: [-1.0, 1.0],
+                },
+                ScreenVertex {
+                    position: [1.0, 1.0],
+                },
+            ],
+        )
+        .unwrap();
+
+        PostProcessing {
+            display: display.clone(),
+            passes: Vec::new(),
+            programs,
+            screen,
+            targets: None,
+            start: Instant::now(),
+        }
+    }
+
+    /// bloom, tone mapping, gamma, fxaa and vignette, in that order.
+    /// gamma starts disabled, the scene colors are already display colors
+    pub fn with_default_passes(display: &Display) -> Self {
+        let mut post_processing = PostProcessing::new(display);
+        post_processing
+            .add_pass("bloom", PostEffect::bloom())
+            .unwrap();
+        post_processing
+            .add_pass("tone_mapping", PostEffect::tone_mapping())
+            .unwrap();
+        post_processing
+            .add_pass("gamma", PostEffect::gamma())
+            .unwrap();
+        post_processing.set_enabled("gamma", false);
+        post_processing.add_pass("fxaa", PostEffect::Fxaa).unwrap();
+        post_processing
+            .add_pass("vignette", PostEffect::vignette())
+            .unwrap();
+        post_processing
+    }
+
+    /// at the end of the chain
+    pub fn add_pass(&mut self, name: &str, effect: PostEffect) -> Result<(), ProgramCreationError> {
+        let index = self.passes.len();
+        self.insert_pass(index, name, effect)
+    }
+
+    /// only a custom shader can fail to compile, the pass is not added then
+    pub fn insert_pass(
+        &mut self,
+        index: usize,
+        name: &str,
+        effect: PostEffect,
+    ) -> Result<(), ProgramCreationError> {
+        let custom_program = match &effect {
+            PostEffect::Custom { fragment_shader } => {
+                Some(compile(&self.display, fragment_shader)?)
+            }
+            _ => None,
+        };
+
+        self.passes.insert(
+            index,
+            PostPass {
+                name: String::from(name),
+                effect,
+                enabled: true,
+                custom_program,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn remove_pass(&mut self, name: &str) -> Option<PostPass> {
+        let index = self.passes.iter().position(|pass| pass.name == name)?;
+        Some(self.passes.remove(index))
+    }
+
+    pub fn passes(&self) -> &[PostPass] {
+        &self.passes
+    }
+
+    pub fn get_pass_mut(&mut self, name: &str) -> Option<&mut PostPass> {
+        self.passes.iter_mut().find(|pass| pass.name == name)
+    }
+
+    /// false when there is no pass with that name
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        match self.get_pass_mut(name) {
+            Some(pass) => {
+                pass.enabled = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn toggle(&mut self, name: &str) {
+        if let Some(pass) = self.get_pass_mut(name) {
+            pass.enabled = !pass.enabled;
+        }
+    }
+
+    /// keeps the textures as big as the window, they are only created again when the size changes
+    pub fn resize(&mut self, width: u32, height: u32) {
+        let (width, height) = (width.max(1), height.max(1));
+
+        if let Some(targets) = &self.targets {
+            if targets.scene.width() == width && targets.scene.height() == height {
+                return;
+            }
+        }
+
+        let target =
+            |width, height| RenderTarget::with_format(&self.display, width, height, HDR_FORMAT);
+        let (half_width, half_height) = ((width / 2).max(1), (height / 2).max(1));
+
+        self.targets = Some(PostTargets {
+            scene: target(width, height),
+            swap: [target(width, height), target(width, height)],
+            bloom: [
+                target(half_width, half_height),
+                target(half_width, half_height),
+            ],
+        });
+    }
+
+    /// where the window cameras draw this frame, `resize` must have been called before
+    pub fn scene(&self) -> &RenderTarget {
+        &self.targets.as_ref().unwrap().scene
+    }
+
+    /// runs the enabled passes over the scene, the last one draws on `target`
+    pub fn apply<S: Surface>(&self, target: &mut S) {
+        let targets = self.targets.as_ref().unwrap();
+        let enabled: Vec<&PostPass> = self.passes.iter().filter(|pass| pass.enabled).collect();
+
+        if enabled.is_empty() {
+            let uniforms = uniform! { sceneTexture: sample(targets.scene.color()) };
+            self.draw(target, &self.programs.copy, &uniforms);
+            return;
+        }
+
+        let mut source = targets.scene.color();
+
+        for (index, pass) in enabled.iter().enumerate() {
+            if index == enabled.len() - 1 {
+                self.draw_pass(target, pass, source);
+            } else {
+                let destination = &targets.swap[index % 2];
+                let mut framebuffer = destination.framebuffer(&self.display);
+                self.draw_pass(&mut framebuffer, pass, source);
+                source = destination.color();
+            }
+        }
+    }
+
+    fn draw_pass<S: Surface>(&self, target: &mut S, pass: &PostPass, source: &Texture2d) {
+        let texel_size = [1.0 / source.width() as f32, 1.0 / source.height() as f32];
+
+        match &pass.effect {
+            PostEffect::Bloom {
+                threshold,
+                soft_knee,
+                intensity,
+                blur_passes,
+            } => {
+                let bloom = &self.targets.as_ref().unwrap().bloom;
+                let half_texel_size = [
+                    1.0 / bloom[0].width() as f32,
+                    1.0 / bloom[0].height() as f32,
+                ];
+
+                let uniforms = uniform! {
+                    sceneTexture: sample(source),
+                    threshold: *threshold,
+                    softKnee: *soft_knee
+                };
+                self.draw(
+                    &mut bloom[0].framebuffer(&self.display),
+                    &self.programs.bright_pass,
+                    &uniforms,
+                );
+
+                for _ in 0..*blur_passes {
+                    for (from, to, direction) in [(0, 1, [1.0, 0.0]), (1, 0, [0.0, 1.0])].iter() {
+                        let uniforms = uniform! {
+                            sceneTexture: sample(bloom[*from].color()),
+                            texelSize: half_texel_size,
+                            direction: *direction
+                        };
+                        self.draw(
+                            &mut bloom[*to].framebuffer(&self.display),
+                            &self.programs.blur,
+                            &uniforms,
+                        );
+                    }
+                }
+
+                let uniforms = uniform! {
+                    sceneTexture: sample(source),
+                    bloomTexture: sample(bloom[0].color()),
+                    intensity: *intensity
+                };
+                self.draw(target, &self.programs.bloom_composite, &uniforms);
+            }
+            PostEffect::ToneMapping { exposure } => {
+                let uniforms = uniform! {
+                    sceneTexture: sample(source),
+                    exposure: *exposure
+                };
+                self.draw(target, &self.programs.tone_mapping, &uniforms);
+            }
+            PostEffect::Gamma { gamma } => {
+                let uniforms = uniform! {
+                    sceneTexture: sample(source),
+                    gamma: *gamma
+                };
+                self.draw(target, &self.programs.gamma, &uniforms);
+            }
+            PostEffect::Fxaa => {
+                let uniforms = uniform! {
+                    sceneTexture: sample(source),
+                    texelSize: texel_size
+                };
+                self.draw(target, &self.programs.fxaa, &uniforms);
+            }
+            PostEffect::Vignette {
+                intensity,
+                radius,
+                softness,
+            } => {
+                let uniforms = uniform! {
+                    sceneTexture: sample(source),
+                    intensity: *intensity,
+                    radius: *radius,
+                    softness: *softness
+                };
+                self.draw(target, &self.programs.vignette, &uniforms);
+            }
+            PostEffect::Custom { .. } => {
+                let uniforms = uniform! {
+                    sceneTexture: sample(source),
+                    texelSize: texel_size,
+                    time: self.start.elapsed().as_secs_f32()
+                };
+                self.draw(target, pass.custom_program.as_ref().unwrap(), &uniforms);
+            }
+        };
+    }
+
+    fn draw<S: Surface, U: Uniforms>(&self, target: &mut S, program: &Program, uniforms: &U) {
+        target
+            .draw(
+                &self.screen,
+                NoIndices(PrimitiveType::TriangleStrip),
+                program,
+                uniforms,
+                &DrawParameters::default(),
+            )
+            .unwrap();
+    }
+}
+
+/// blur and fxaa read past the borders, those reads must repeat the border instead of wrapping around
+fn sample(texture: &Texture2d) -> Sampler<'_, Texture2d> {
+    texture.sampled().wrap_function(SamplerWrapFunction::Clamp)
+}
+
+/// the colors are written as they are, the window framebuffer must not convert them either
+fn compile(display: &Display, fragment_shader: &str) -> Result<Program, ProgramCreationError> {
+    Program::new(
+        display,
+        ProgramCreationInput::SourceCode {
+            vertex_shader: &VertexShader::fullscreen(),
+            tessellation_control_shader: None,
+            tessellation_evaluation_shader: None,
+            geometry_shader: None,
+            fragment_shader,
+            transform_feedback_varyings: None,
+            outputs_srgb: true,
+            uses_point_size: false,
+        },
+    )
+}
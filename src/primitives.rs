@@ -1,114 +1,275 @@
 use crate::models::Prefab;
+use crate::shapes;
 use glium::backend::glutin::Display;
-use std::sync::Arc;
+use std::fmt;
+use std::rc::Rc;
 
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone)]
 pub struct Vertex {
     pub position: [f32; 3],
     pub color: [f32; 4],
+    pub tex_coords: [f32; 2],
+    /// not used by the shaders yet
+    pub normal: [f32; 3],
+}
+implement_vertex!(Vertex, position, color, tex_coords, normal);
+
+/// the vertex attributes color_instanced.vert reads, a prefab vertex type needs all of them
+pub const SHADER_ATTRIBUTES: [&str; 3] = ["position", "color", "tex_coords"];
+
+/// the `SHADER_ATTRIBUTES` the vertex type does not have
+pub fn missing_attributes<V: glium::Vertex>() -> Vec<&'static str> {
+    let bindings = V::build_bindings();
+
+    SHADER_ATTRIBUTES
+        .iter()
+        .copied()
+        .filter(|attribute| !bindings.iter().any(|(name, ..)| name == attribute))
+        .collect()
+}
+
+/// A vertex type the `World` shaders can not draw, with the attributes it lacks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MissingAttributes {
+    pub vertex_type: &'static str,
+    pub attributes: Vec<&'static str>,
+}
+
+impl fmt::Display for MissingAttributes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} lacks the {} vertex attributes the World shaders read",
+            self.vertex_type,
+            self.attributes.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for MissingAttributes {}
+
+/// fails when `V` can not be drawn by the `World` shaders, before anything is uploaded
+pub fn check_attributes<V: glium::Vertex>() -> Result<(), MissingAttributes> {
+    let attributes = missing_attributes::<V>();
+    if attributes.is_empty() {
+        Ok(())
+    } else {
+        Err(MissingAttributes {
+            vertex_type: std::any::type_name::<V>(),
+            attributes,
+        })
+    }
+}
+
+/// What a `Prefab` needs from a vertex type, on top of what glium needs to upload it.
+/// The positions give the bounds and picking, `to_vertex` the copy kept for exporting
+pub trait MeshVertex: glium::Vertex + 'static {
+    fn position(&self) -> [f32; 3];
+
+    /// attributes the type does not have are left white or zero
+    fn to_vertex(&self) -> Vertex {
+        Vertex {
+            position: self.position(),
+            color: [1.0, 1.0, 1.0, 1.0],
+            tex_coords: [0.0, 0.0],
+            normal: [0.0, 0.0, 0.0],
+        }
+    }
+}
+
+impl MeshVertex for Vertex {
+    fn position(&self) -> [f32; 3] {
+        self.position
+    }
+
+    fn to_vertex(&self) -> Vertex {
+        *self
+    }
+}
+
+/// `Vertex` with a tangent for normal maps, xyz along the texture u and w the sign of
+/// the bitangent, which is `w * cross(normal, tangent)`
+#[derive(Debug, Copy, Clone)]
+pub struct TangentVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+    pub tex_coords: [f32; 2],
+    pub normal: [f32; 3],
+    pub tangent: [f32; 4],
+}
+implement_vertex!(TangentVertex, position, color, tex_coords, normal, tangent);
+
+impl MeshVertex for TangentVertex {
+    fn position(&self) -> [f32; 3] {
+        self.position
+    }
+
+    fn to_vertex(&self) -> Vertex {
+        Vertex {
+            position: self.position,
+            color: self.color,
+            tex_coords: self.tex_coords,
+            normal: self.normal,
+        }
+    }
+}
+
+/// per instance data, sent as a second vertex buffer on instanced draws
+#[derive(Copy, Clone)]
+pub struct InstanceAttributes {
+    pub model_matrix: [[f32; 4]; 4],
+    pub instance_color: [f32; 4],
 }
-implement_vertex!(Vertex, position, color);
+implement_vertex!(InstanceAttributes, model_matrix, instance_color);
 
 pub struct Primitive {}
 
 impl Primitive {
-    pub fn cube(display: Display) -> Arc<Prefab> {
+    pub fn cube(display: Display) -> Rc<Prefab> {
         let shape = vec![
             Vertex {
                 position: [0.5, 0.5, 0.5],
                 color: [1.0, 0.0, 0.0, 1.0],
+                tex_coords: [1.0, 1.0],
+                normal: [0.0, 0.0, 1.0],
             },
             Vertex {
                 position: [0.5, -0.5, 0.5],
                 color: [1.0, 0.0, 0.0, 1.0],
+                tex_coords: [1.0, 0.0],
+                normal: [0.0, 0.0, 1.0],
             },
             Vertex {
                 position: [-0.5, 0.5, 0.5],
                 color: [1.0, 0.0, 0.0, 1.0],
+                tex_coords: [0.0, 1.0],
+                normal: [0.0, 0.0, 1.0],
             },
             Vertex {
                 position: [-0.5, -0.5, 0.5],
                 color: [1.0, 0.0, 0.0, 1.0],
+                tex_coords: [0.0, 0.0],
+                normal: [0.0, 0.0, 1.0],
             },
             Vertex {
                 position: [0.5, 0.5, -0.5],
                 color: [0.0, 1.0, 0.0, 1.0],
+                tex_coords: [0.0, 1.0],
+                normal: [0.0, 0.0, -1.0],
             },
             Vertex {
                 position: [0.5, -0.5, -0.5],
                 color: [0.0, 1.0, 0.0, 1.0],
+                tex_coords: [0.0, 0.0],
+                normal: [0.0, 0.0, -1.0],
             },
             Vertex {
                 position: [-0.5, 0.5, -0.5],
                 color: [0.0, 1.0, 0.0, 1.0],
+                tex_coords: [1.0, 1.0],
+                normal: [0.0, 0.0, -1.0],
             },
             Vertex {
                 position: [-0.5, -0.5, -0.5],
                 color: [0.0, 1.0, 0.0, 1.0],
+                tex_coords: [1.0, 0.0],
+                normal: [0.0, 0.0, -1.0],
             },
             Vertex {
                 position: [-0.5, 0.5, 0.5],
                 color: [0.0, 0.0, 1.0, 1.0],
+                tex_coords: [1.0, 1.0],
+                normal: [-1.0, 0.0, 0.0],
             },
             Vertex {
                 position: [-0.5, 0.5, -0.5],
                 color: [0.0, 0.0, 1.0, 1.0],
+                tex_coords: [0.0, 1.0],
+                normal: [-1.0, 0.0, 0.0],
             },
             Vertex {
                 position: [-0.5, -0.5, 0.5],
                 color: [0.0, 0.0, 1.0, 1.0],
+                tex_coords: [1.0, 0.0],
+                normal: [-1.0, 0.0, 0.0],
             },
             Vertex {
                 position: [-0.5, -0.5, -0.5],
                 color: [0.0, 0.0, 1.0, 1.0],
+                tex_coords: [0.0, 0.0],
+                normal: [-1.0, 0.0, 0.0],
             },
             Vertex {
                 position: [0.5, 0.5, 0.5],
                 color: [0.0, 1.0, 1.0, 1.0],
+                tex_coords: [0.0, 1.0],
+                normal: [1.0, 0.0, 0.0],
             },
             Vertex {
                 position: [0.5, 0.5, -0.5],
                 color: [0.0, 1.0, 1.0, 1.0],
+                tex_coords: [1.0, 1.0],
+                normal: [1.0, 0.0, 0.0],
             },
             Vertex {
                 position: [0.5, -0.5, 0.5],
                 color: [0.0, 1.0, 1.0, 1.0],
+                tex_coords: [0.0, 0.0],
+                normal: [1.0, 0.0, 0.0],
             },
             Vertex {
                 position: [0.5, -0.5, -0.5],
                 color: [0.0, 1.0, 1.0, 1.0],
+                tex_coords: [1.0, 0.0],
+                normal: [1.0, 0.0, 0.0],
             },
             Vertex {
                 position: [0.5, 0.5, 0.5],
                 color: [1.0, 0.0, 1.0, 1.0],
+                tex_coords: [1.0, 0.0],
+                normal: [0.0, 1.0, 0.0],
             },
             Vertex {
                 position: [-0.5, 0.5, 0.5],
                 color: [1.0, 0.0, 1.0, 1.0],
+                tex_coords: [0.0, 0.0],
+                normal: [0.0, 1.0, 0.0],
             },
             Vertex {
                 position: [0.5, 0.5, -0.5],
                 color: [1.0, 0.0, 1.0, 1.0],
+                tex_coords: [1.0, 1.0],
+                normal: [0.0, 1.0, 0.0],
             },
             Vertex {
                 position: [-0.5, 0.5, -0.5],
                 color: [1.0, 0.0, 1.0, 1.0],
+                tex_coords: [0.0, 1.0],
+                normal: [0.0, 1.0, 0.0],
             },
             Vertex {
                 position: [0.5, -0.5, 0.5],
                 color: [1.0, 1.0, 0.0, 1.0],
+                tex_coords: [1.0, 1.0],
+                normal: [0.0, -1.0, 0.0],
             },
             Vertex {
                 position: [-0.5, -0.5, 0.5],
                 color: [1.0, 1.0, 0.0, 1.0],
+                tex_coords: [0.0, 1.0],
+                normal: [0.0, -1.0, 0.0],
             },
             Vertex {
                 position: [0.5, -0.5, -0.5],
                 color: [1.0, 1.0, 0.0, 1.0],
+                tex_coords: [1.0, 0.0],
+                normal: [0.0, -1.0, 0.0],
             },
             Vertex {
                 position: [-0.5, -0.5, -0.5],
                 color: [1.0, 1.0, 0.0, 1.0],
+                tex_coords: [0.0, 0.0],
+                normal: [0.0, -1.0, 0.0],
             },
         ];
 
@@ -135,4 +296,80 @@ impl Primitive {
 
         Prefab::build(display, shape, indices)
     }
+
+    /// one by one square on the xy plane facing +z, with the whole texture on it
+    pub fn quad(display: Display) -> Rc<Prefab> {
+        let shape = vec![
+            Vertex {
+                position: [-0.5, -0.5, 0.0],
+                color: [1.0, 1.0, 1.0, 1.0],
+                tex_coords: [0.0, 0.0],
+                normal: [0.0, 0.0, 1.0],
+            },
+            Vertex {
+                position: [0.5, -0.5, 0.0],
+                color: [1.0, 1.0, 1.0, 1.0],
+                tex_coords: [1.0, 0.0],
+                normal: [0.0, 0.0, 1.0],
+            },
+            Vertex {
+                position: [0.5, 0.5, 0.0],
+                color: [1.0, 1.0, 1.0, 1.0],
+                tex_coords: [1.0, 1.0],
+                normal: [0.0, 0.0, 1.0],
+            },
+            Vertex {
+                position: [-0.5, 0.5, 0.0],
+                color: [1.0, 1.0, 1.0, 1.0],
+                tex_coords: [0.0, 1.0],
+                normal: [0.0, 0.0, 1.0],
+            },
+        ];
+
+        #[rustfmt::skip]
+        let indices: Vec<u16> = vec![
+            0, 1, 2,
+            0, 2, 3,
+        ];
+
+        Prefab::build(display, shape, indices)
+    }
+
+    /// the generated shapes below are one unit across like the cube, with enough
+    /// segments to look round at the size of a few blocks
+    pub fn sphere(display: Display) -> Rc<Prefab> {
+        shapes::sphere(0.5, 32, 16).to_prefab(display)
+    }
+
+    pub fn icosphere(display: Display) -> Rc<Prefab> {
+        shapes::icosphere(0.5, 3).to_prefab(display)
+    }
+
+    pub fn cylinder(display: Display) -> Rc<Prefab> {
+        shapes::cylinder(0.5, 1.0, 32, 1).to_prefab(display)
+    }
+
+    pub fn cone(display: Display) -> Rc<Prefab> {
+        shapes::cone(0.5, 1.0, 32, 1).to_prefab(display)
+    }
+
+    /// the tube is a quarter of the ring across
+    pub fn torus(display: Display) -> Rc<Prefab> {
+        shapes::torus(0.375, 0.125, 48, 24).to_prefab(display)
+    }
+
+    /// one by one square on the xz plane facing +y
+    pub fn plane(display: Display) -> Rc<Prefab> {
+        shapes::plane(1.0, 1.0, 1, 1).to_prefab(display)
+    }
+
+    /// two units tall, like the player block
+    pub fn capsule(display: Display) -> Rc<Prefab> {
+        shapes::capsule(0.5, 2.0, 32, 8).to_prefab(display)
+    }
+
+    /// one unit long, pointing up from the origin
+    pub fn arrow(display: Display) -> Rc<Prefab> {
+        shapes::arrow(1.0, 0.03, 0.08, 0.2, 16).to_prefab(display)
+    }
 }
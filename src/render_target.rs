@@ -0,0 +1,78 @@
+use glium::backend::glutin::Display;
+use glium::framebuffer::{DepthRenderBuffer, SimpleFrameBuffer};
+use glium::texture::{DepthFormat, MipmapsOption, UncompressedFloatFormat};
+use glium::Texture2d;
+use std::sync::Arc;
+
+/// Offscreen color and depth buffers a camera can draw into instead of the window.
+/// The color texture is shared with materials, so it can be shown on the same frame it was drawn.
+/// reference: https://learnopengl.com/Advanced-OpenGL/Framebuffers
+pub struct RenderTarget {
+    color: Arc<Texture2d>,
+    depth: DepthRenderBuffer,
+    format: UncompressedFloatFormat,
+    width: u32,
+    height: u32,
+}
+
+#[allow(dead_code)]
+impl RenderTarget {
+    /// 8 bits per channel color with alpha
+    pub fn new(display: &Display, width: u32, height: u32) -> Self {
+        RenderTarget::with_format(display, width, height, UncompressedFloatFormat::U8U8U8U8)
+    }
+
+    pub fn with_format(
+        display: &Display,
+        width: u32,
+        height: u32,
+        format: UncompressedFloatFormat,
+    ) -> Self {
+        let color =
+            Texture2d::empty_with_format(display, format, MipmapsOption::NoMipmap, width, height)
+                .unwrap();
+        let depth = DepthRenderBuffer::new(display, DepthFormat::I24, width, height).unwrap();
+
+        RenderTarget {
+            color: Arc::new(color),
+            depth,
+            format,
+            width,
+            height,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn ratio(&self) -> f32 {
+        self.width as f32 / self.height as f32
+    }
+
+    pub fn format(&self) -> UncompressedFloatFormat {
+        self.format
+    }
+
+    /// for `Material::textured`
+    pub fn texture(&self) -> Arc<Texture2d> {
+        self.color.clone()
+    }
+
+    pub fn color(&self) -> &Texture2d {
+        &self.color
+    }
+
+    /// a texture can not be sampled while it is being drawn into
+    pub fn is_texture(&self, texture: &Texture2d) -> bool {
+        std::ptr::eq(self.color.as_ref(), texture)
+    }
+
+    pub fn framebuffer<'a>(&'a self, display: &Display) -> SimpleFrameBuffer<'a> {
+        SimpleFrameBuffer::with_depth_buffer(display, self.color.as_ref(), &self.depth).unwrap()
+    }
+}
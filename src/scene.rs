@@ -0,0 +1,682 @@
+use crate::lights::Light;
+use crate::materials::BlendMode;
+use crate::math::{clamp, Matrix4, Quaternion, Vector3};
+use crate::matrices::MatrixOperation;
+use crate::models::{Projection, Viewport, VIEW_ANGLE, Z_FAR, Z_NEAR};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::{read_to_string, write};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SceneFormat {
+    Ron,
+    Json,
+}
+
+impl SceneFormat {
+    /// from the file extension, `.ron` or `.json`
+    pub fn from_path(path: &str) -> Result<Self, SceneError> {
+        match Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+        {
+            Some("ron") => Ok(SceneFormat::Ron),
+            Some("json") => Ok(SceneFormat::Json),
+            _ => Err(SceneError::UnknownFormat(String::from(path))),
+        }
+    }
+}
+
+/// Position, rotation in degrees and scale. Rotations are applied y, then x, then z,
+/// the same yaw, pitch and roll order the cameras use.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Transform {
+    pub translation: Vector3,
+    pub rotation: Vector3,
+    pub scale: Vector3,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform {
+            translation: Vector3::new(0.0, 0.0, 0.0),
+            rotation: Vector3::new(0.0, 0.0, 0.0),
+            scale: Vector3::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl Transform {
+    pub fn to_matrix(self) -> Matrix4 {
+        MatrixOperation::translation(self.translation)
+            * Quaternion::rotate_y(self.rotation.y)
+            * Quaternion::rotate_x(self.rotation.x)
+            * Quaternion::rotate_z(self.rotation.z)
+            * MatrixOperation::scale(self.scale)
+    }
+
+    /// expects translation, rotation and positive scale only, shear is lost
+    /// reference: https://www.geometrictools.com/Documentation/EulerAngles.pdf
+    pub fn from_matrix(matrix: Matrix4) -> Self {
+        let side = matrix.get_side_vector();
+        let up = matrix.get_up_vector();
+        let forward = matrix.get_forward_vector();
+        let scale = Vector3::new(side.length(), up.length(), forward.length());
+
+        let side = side * (1.0 / scale.x);
+        let up = up * (1.0 / scale.y);
+        let forward = forward * (1.0 / scale.z);
+
+        // rows of the rotation are (side.x, up.x, forward.x) and so on
+        let pitch = clamp(-forward.y, -1.0, 1.0).asin();
+        let (yaw, roll) = if forward.y.abs() < 0.9999 {
+            (forward.x.atan2(forward.z), side.y.atan2(up.y))
+        } else {
+            // looking straight up or down, yaw and roll turn around the same axis
+            ((-side.z).atan2(side.x), 0.0)
+        };
+
+        Transform {
+            translation: matrix.get_position(),
+            rotation: Vector3::new(pitch.to_degrees(), yaw.to_degrees(), roll.to_degrees()),
+            scale,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RenderTargetDescription {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MaterialDescription {
+    pub name: String,
+    #[serde(default = "white")]
+    pub color: [f32; 4],
+    #[serde(default = "opaque")]
+    pub blend_mode: BlendMode,
+    /// a render target name
+    #[serde(default)]
+    pub texture: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstanceDescription {
+    pub name: String,
+    /// a prefab registered on the `World`, like "cube" or "quad"
+    pub prefab: String,
+    #[serde(default)]
+    pub material: Option<String>,
+    #[serde(default = "white")]
+    pub color: [f32; 4],
+    /// relative to the parent, when there is one
+    #[serde(default)]
+    pub transform: Transform,
+    #[serde(default)]
+    pub parent: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LightDescription {
+    pub name: String,
+    pub light: Light,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CameraDescription {
+    pub name: String,
+    /// scale is ignored
+    #[serde(default)]
+    pub transform: Transform,
+    /// turns the camera from its translation to this point, instead of using the rotation
+    #[serde(default)]
+    pub look_at: Option<Vector3>,
+    #[serde(default = "perspective")]
+    pub projection: Projection,
+    #[serde(default = "z_near")]
+    pub z_near: f32,
+    #[serde(default = "z_far")]
+    pub z_far: f32,
+    #[serde(default = "Viewport::full")]
+    pub viewport: Viewport,
+    #[serde(default)]
+    pub order: i32,
+    #[serde(default = "active")]
+    pub active: bool,
+    #[serde(default)]
+    pub render_target: Option<String>,
+}
+
+fn white() -> [f32; 4] {
+    [1.0, 1.0, 1.0, 1.0]
+}
+
+fn opaque() -> BlendMode {
+    BlendMode::Opaque
+}
+
+fn perspective() -> Projection {
+    Projection::Perspective { fov: VIEW_ANGLE }
+}
+
+fn z_near() -> f32 {
+    Z_NEAR
+}
+
+fn z_far() -> f32 {
+    Z_FAR
+}
+
+fn active() -> bool {
+    true
+}
+
+/// What a `World` holds, by name, in a form that can be written by hand:
+///
+/// ```ron
+/// (
+///     instances: [
+///         (name: "floor", prefab: "cube", transform: (scale: (x: 100.0, y: 1.0, z: 100.0))),
+///         (name: "box", prefab: "cube", material: Some("glass"), parent: Some("floor")),
+///     ],
+///     materials: [(name: "glass", color: (0.6, 0.8, 1.0, 0.4), blend_mode: Alpha)],
+/// )
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scene {
+    pub render_targets: Vec<RenderTargetDescription>,
+    pub materials: Vec<MaterialDescription>,
+    pub instances: Vec<InstanceDescription>,
+    pub lights: Vec<LightDescription>,
+    pub cameras: Vec<CameraDescription>,
+}
+
+/// Names a scene can refer to without declaring them, because the `World` already has them.
+#[derive(Debug, Clone, Default)]
+pub struct SceneNames {
+    pub prefabs: HashSet<String>,
+    pub materials: HashSet<String>,
+    pub render_targets: HashSet<String>,
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(std::io::Error),
+    UnknownFormat(String),
+    /// the parser message, with the line and column
+    Parse(String),
+    Serialize(String),
+    /// a problem with one entry, like an unknown prefab or a parent loop
+    Entry {
+        section: &'static str,
+        name: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io(error) => write!(f, "could not access the scene file: {}", error),
+            SceneError::UnknownFormat(path) => {
+                write!(f, "{} is neither a .ron nor a .json scene", path)
+            }
+            SceneError::Parse(message) => write!(f, "invalid scene: {}", message),
+            SceneError::Serialize(message) => write!(f, "could not write the scene: {}", message),
+            SceneError::Entry {
+                section,
+                name,
+                message,
+            } => write!(f, "{} \"{}\": {}", section, name, message),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+fn entry_error(section: &'static str, name: &str, message: String) -> SceneError {
+    SceneError::Entry {
+        section,
+        name: String::from(name),
+        message,
+    }
+}
+
+#[allow(dead_code)]
+impl Scene {
+    pub fn from_file(path: &str) -> Result<Self, SceneError> {
+        let format = SceneFormat::from_path(path)?;
+        let content = read_to_string(path).map_err(SceneError::Io)?;
+        Scene::from_str(&content, format)
+    }
+
+    pub fn from_str(content: &str, format: SceneFormat) -> Result<Self, SceneError> {
+        match format {
+            SceneFormat::Ron => {
+                ron::de::from_str(content).map_err(|error| SceneError::Parse(error.to_string()))
+            }
+            SceneFormat::Json => {
+                serde_json::from_str(content).map_err(|error| SceneError::Parse(error.to_string()))
+            }
+        }
+    }
+
+    pub fn to_string(&self, format: SceneFormat) -> Result<String, SceneError> {
+        match format {
+            SceneFormat::Ron => ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::new())
+                .map_err(|error| SceneError::Serialize(error.to_string())),
+            SceneFormat::Json => serde_json::to_string_pretty(self)
+                .map_err(|error| SceneError::Serialize(error.to_string())),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), SceneError> {
+        let content = self.to_string(SceneFormat::from_path(path)?)?;
+        write(path, content).map_err(SceneError::Io)
+    }
+
+    /// checks names are unique and every reference exists, in the scene or in `known`
+    pub fn validate(&self, known: &SceneNames) -> Result<(), SceneError> {
+        let render_targets = unique_names(
+            "render_targets",
+            self.render_targets.iter().map(|target| &target.name),
+        )?;
+        let materials = unique_names(
+            "materials",
+            self.materials.iter().map(|material| &material.name),
+        )?;
+        unique_names(
+            "instances",
+            self.instances.iter().map(|instance| &instance.name),
+        )?;
+        unique_names("lights", self.lights.iter().map(|light| &light.name))?;
+        unique_names("cameras", self.cameras.iter().map(|camera| &camera.name))?;
+
+        let has_render_target =
+            |name: &str| render_targets.contains(name) || known.render_targets.contains(name);
+
+        for target in &self.render_targets {
+            if target.width == 0 || target.height == 0 {
+                return Err(entry_error(
+                    "render_targets",
+                    &target.name,
+                    String::from("width and height must be bigger than zero"),
+                ));
+            }
+        }
+
+        for material in &self.materials {
+            if let Some(texture) = &material.texture {
+                if !has_render_target(texture) {
+                    return Err(entry_error(
+                        "materials",
+                        &material.name,
+                        format!("unknown render target \"{}\"", texture),
+                    ));
+                }
+            }
+        }
+
+        for instance in &self.instances {
+            if !known.prefabs.contains(&instance.prefab) {
+                return Err(entry_error(
+                    "instances",
+                    &instance.name,
+                    format!("unknown prefab \"{}\"", instance.prefab),
+                ));
+            }
+            if let Some(material) = &instance.material {
+                if !materials.contains(material.as_str()) && !known.materials.contains(material) {
+                    return Err(entry_error(
+                        "instances",
+                        &instance.name,
+                        format!("unknown material \"{}\"", material),
+                    ));
+                }
+            }
+        }
+
+        for camera in &self.cameras {
+            if let Some(render_target) = &camera.render_target {
+                if !has_render_target(render_target) {
+                    return Err(entry_error(
+                        "cameras",
+                        &camera.name,
+                        format!("unknown render target \"{}\"", render_target),
+                    ));
+                }
+            }
+        }
+
+        self.world_transforms().map(|_| ())
+    }
+
+    /// instance transforms with their parents applied, by name
+    pub fn world_transforms(&self) -> Result<HashMap<String, Matrix4>, SceneError> {
+        let instances: HashMap<&str, &InstanceDescription> = self
+            .instances
+            .iter()
+            .map(|instance| (instance.name.as_str(), instance))
+            .collect();
+        let mut transforms = HashMap::new();
+
+        for instance in &self.instances {
+            // walks up until an instance without parent, then composes on the way back down
+            let mut chain = vec![instance];
+            let mut current = instance;
+
+            while let Some(parent) = &current.parent {
+                let parent = *instances.get(parent.as_str()).ok_or_else(|| {
+                    entry_error(
+                        "instances",
+                        &current.name,
+                        format!("unknown parent \"{}\"", parent),
+                    )
+                })?;
+
+                if chain.iter().any(|visited| visited.name == parent.name) {
+                    return Err(entry_error(
+                        "instances",
+                        &instance.name,
+                        String::from("is its own ancestor"),
+                    ));
+                }
+                chain.push(parent);
+                current = parent;
+            }
+
+            let transform = chain
+                .iter()
+                .rev()
+                .fold(Matrix4::identity(), |transform, link| {
+                    transform * link.transform.to_matrix()
+                });
+            transforms.insert(instance.name.clone(), transform);
+        }
+
+        Ok(transforms)
+    }
+}
+
+/// the inverse of `Scene::world_transforms`, from world transforms and the parent of each instance.
+/// Instances whose parent is missing or cannot be inverted keep their world transform and no parent
+pub fn local_transforms(
+    world: &HashMap<String, Matrix4>,
+    parents: &HashMap<String, String>,
+) -> HashMap<String, (Transform, Option<String>)> {
+    world
+        .iter()
+        .map(|(name, transform)| {
+            let parent_inverse = parents.get(name).and_then(|parent| {
+                let inverse = world.get(parent)?.inverse()?;
+                Some((parent.clone(), inverse))
+            });
+            let local = match parent_inverse {
+                Some((parent, inverse)) => {
+                    (Transform::from_matrix(inverse * *transform), Some(parent))
+                }
+                None => (Transform::from_matrix(*transform), None),
+            };
+            (name.clone(), local)
+        })
+        .collect()
+}
+
+fn unique_names<'a, I: Iterator<Item = &'a String>>(
+    section: &'static str,
+    names: I,
+) -> Result<HashSet<&'a str>, SceneError> {
+    let mut unique = HashSet::new();
+
+    for name in names {
+        if name.is_empty() {
+            return Err(entry_error(section, name, String::from("has no name")));
+        }
+        if !unique.insert(name.as_str()) {
+            return Err(entry_error(
+                section,
+                name,
+                String::from("is declared twice"),
+            ));
+        }
+    }
+
+    Ok(unique)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCENE: &str = r#"(
+        render_targets: [(name: "monitor", width: 256, height: 256)],
+        materials: [
+            (name: "glass", color: (0.6, 0.8, 1.0, 0.4), blend_mode: Alpha),
+            (name: "screen", texture: Some("monitor")),
+        ],
+        instances: [
+            (name: "base", prefab: "cube", transform: (translation: (x: 0.0, y: 1.0, z: 0.0))),
+            (
+                name: "top",
+                prefab: "cube",
+                material: Some("glass"),
+                parent: Some("base"),
+                transform: (translation: (x: 0.0, y: 2.0, z: 0.0)),
+            ),
+        ],
+        lights: [
+            (
+                name: "sun",
+                light: (
+                    kind: Directional(direction: (x: 0.0, y: -1.0, z: 0.0)),
+                    color: (x: 1.0, y: 1.0, z: 1.0),
+                    intensity: 1.0,
+                ),
+            ),
+        ],
+        cameras: [(name: "main", look_at: Some((x: 0.0, y: 0.0, z: 0.0)))],
+    )"#;
+
+    fn known() -> SceneNames {
+        SceneNames {
+            prefabs: ["cube", "quad"]
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            ..SceneNames::default()
+        }
+    }
+
+    fn assert_matrix_eq(a: Matrix4, b: Matrix4) {
+        for row in 0..4 {
+            for (x, y) in a.get_row(row).iter().zip(b.get_row(row).iter()) {
+                assert!((x - y).abs() < 0.001, "{:?} != {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn format_from_extension() {
+        assert_eq!(
+            SceneFormat::from_path("a/level.ron").unwrap(),
+            SceneFormat::Ron
+        );
+        assert_eq!(
+            SceneFormat::from_path("level.json").unwrap(),
+            SceneFormat::Json
+        );
+        assert!(SceneFormat::from_path("level.txt").is_err());
+    }
+
+    #[test]
+    fn ron_scene_parses_with_defaults() {
+        let scene = Scene::from_str(SCENE, SceneFormat::Ron).unwrap();
+
+        assert_eq!(scene.instances.len(), 2);
+        assert_eq!(scene.instances[0].color, [1.0, 1.0, 1.0, 1.0]);
+        assert_eq!(scene.materials[1].blend_mode, BlendMode::Opaque);
+        assert_eq!(scene.cameras[0].z_near, Z_NEAR);
+        assert_eq!(scene.cameras[0].viewport, Viewport::full());
+        assert!(scene.cameras[0].active);
+        assert!(scene.validate(&known()).is_ok());
+    }
+
+    #[test]
+    fn scene_round_trips_through_both_formats() {
+        let scene = Scene::from_str(SCENE, SceneFormat::Ron).unwrap();
+
+        for format in [SceneFormat::Ron, SceneFormat::Json].iter() {
+            let content = scene.to_string(*format).unwrap();
+            assert_eq!(Scene::from_str(&content, *format).unwrap(), scene);
+        }
+    }
+
+    #[test]
+    fn parse_errors_have_a_position() {
+        let error = Scene::from_str("{\"instances\": [{\"name\": 3}]}", SceneFormat::Json)
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.contains("line 1"), "{}", error);
+    }
+
+    #[test]
+    fn parents_survive_a_save_and_load() {
+        let scene = Scene::from_str(SCENE, SceneFormat::Ron).unwrap();
+        let world = scene.world_transforms().unwrap();
+        let parents: HashMap<String, String> = scene
+            .instances
+            .iter()
+            .filter_map(|instance| Some((instance.name.clone(), instance.parent.clone()?)))
+            .collect();
+
+        let locals = local_transforms(&world, &parents);
+        let mut saved = scene.clone();
+        for instance in &mut saved.instances {
+            let (transform, parent) = locals[&instance.name].clone();
+            instance.transform = transform;
+            instance.parent = parent;
+        }
+
+        let content = saved.to_string(SceneFormat::Ron).unwrap();
+        let loaded = Scene::from_str(&content, SceneFormat::Ron).unwrap();
+        let top = &loaded.instances[1];
+        assert_eq!(top.parent.as_deref(), Some("base"));
+        assert_matrix_eq(
+            top.transform.to_matrix(),
+            MatrixOperation::translation(Vector3::new(0.0, 2.0, 0.0)),
+        );
+
+        let loaded_world = loaded.world_transforms().unwrap();
+        for (name, transform) in &world {
+            assert_matrix_eq(loaded_world[name], *transform);
+        }
+    }
+
+    #[test]
+    fn missing_parents_keep_the_world_transform() {
+        let mut world = HashMap::new();
+        world.insert(
+            String::from("child"),
+            MatrixOperation::translation(Vector3::new(1.0, 2.0, 3.0)),
+        );
+        let mut parents = HashMap::new();
+        parents.insert(String::from("child"), String::from("removed"));
+
+        let (transform, parent) = local_transforms(&world, &parents)["child"].clone();
+        assert_eq!(parent, None);
+        assert_eq!(transform.translation, Vector3::new(1.0, 2.0, 3.0));
+    }
+
+    #[test]
+    fn children_follow_their_parents() {
+        let scene = Scene::from_str(SCENE, SceneFormat::Ron).unwrap();
+        let transforms = scene.world_transforms().unwrap();
+
+        let top = transforms["top"].get_position();
+        assert!((top.y - 3.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn bad_references_name_the_entry() {
+        let mut scene = Scene::from_str(SCENE, SceneFormat::Ron).unwrap();
+        scene.instances[1].prefab = String::from("sphere");
+        assert_eq!(
+            scene.validate(&known()).unwrap_err().to_string(),
+            "instances \"top\": unknown prefab \"sphere\""
+        );
+
+        let mut scene = Scene::from_str(SCENE, SceneFormat::Ron).unwrap();
+        scene.materials[1].texture = Some(String::from("mirror"));
+        assert_eq!(
+            scene.validate(&known()).unwrap_err().to_string(),
+            "materials \"screen\": unknown render target \"mirror\""
+        );
+
+        let mut scene = Scene::from_str(SCENE, SceneFormat::Ron).unwrap();
+        scene.instances.push(scene.instances[0].clone());
+        assert_eq!(
+            scene.validate(&known()).unwrap_err().to_string(),
+            "instances \"base\": is declared twice"
+        );
+    }
+
+    #[test]
+    fn parent_loops_are_errors() {
+        let mut scene = Scene::from_str(SCENE, SceneFormat::Ron).unwrap();
+        scene.instances[0].parent = Some(String::from("top"));
+
+        assert!(scene.world_transforms().is_err());
+    }
+
+    #[test]
+    fn known_names_satisfy_references() {
+        let mut scene = Scene::default();
+        scene.instances.push(InstanceDescription {
+            name: String::from("screen"),
+            prefab: String::from("quad"),
+            material: Some(String::from("monitor")),
+            color: white(),
+            transform: Transform::default(),
+            parent: None,
+        });
+
+        assert!(scene.validate(&known()).is_err());
+
+        let mut names = known();
+        names.materials.insert(String::from("monitor"));
+        assert!(scene.validate(&names).is_ok());
+    }
+
+    #[test]
+    fn transform_matrix_round_trip() {
+        let transform = Transform {
+            translation: Vector3::new(1.0, -2.0, 3.0),
+            rotation: Vector3::new(30.0, -120.0, 15.0),
+            scale: Vector3::new(2.0, 0.5, 1.5),
+        };
+
+        let decomposed = Transform::from_matrix(transform.to_matrix());
+        assert_matrix_eq(decomposed.to_matrix(), transform.to_matrix());
+        assert!((decomposed.rotation.y + 120.0).abs() < 0.01);
+        assert!((decomposed.scale.y - 0.5).abs() < 0.001);
+    }
+
+    #[test]
+    fn transform_straight_down_keeps_the_matrix() {
+        let transform = Transform {
+            rotation: Vector3::new(90.0, 40.0, 0.0),
+            ..Transform::default()
+        };
+
+        let decomposed = Transform::from_matrix(transform.to_matrix());
+        assert_matrix_eq(decomposed.to_matrix(), transform.to_matrix());
+    }
+}
@@ -0,0 +1,17 @@
+#version 330
+
+smooth in vec2 texCoords;
+
+out vec4 outputColor;
+
+uniform sampler2D sceneTexture;
+uniform sampler2D bloomTexture;
+uniform float intensity;
+
+void main()
+{
+    vec4 scene = texture(sceneTexture, texCoords);
+    vec3 bloom = texture(bloomTexture, texCoords).rgb;
+
+    outputColor = vec4(scene.rgb + bloom * intensity, scene.a);
+}
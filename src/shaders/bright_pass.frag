@@ -0,0 +1,24 @@
+#version 330
+
+smooth in vec2 texCoords;
+
+out vec4 outputColor;
+
+uniform sampler2D sceneTexture;
+uniform float threshold;
+// how wide the transition under the threshold is, avoids hard edges on the bloom
+uniform float softKnee;
+
+// reference: https://catlikecoding.com/unity/tutorials/advanced-rendering/bloom/
+void main()
+{
+    vec3 color = texture(sceneTexture, texCoords).rgb;
+    float brightness = max(color.r, max(color.g, color.b));
+
+    float knee = threshold * softKnee;
+    float soft = clamp(brightness - threshold + knee, 0.0, 2.0 * knee);
+    soft = soft * soft / (4.0 * knee + 0.00001);
+    float contribution = max(soft, brightness - threshold) / max(brightness, 0.00001);
+
+    outputColor = vec4(color * contribution, 1.0);
+}
@@ -0,0 +1,28 @@
+#version 330
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec4 color;
+layout(location = 2) in vec2 tex_coords;
+
+// per instance attributes, one entry for each instance on the batch
+in mat4 model_matrix;
+in vec4 instance_color;
+
+smooth out vec4 theColor;
+smooth out vec2 texCoords;
+smooth out float fogDistance;
+smooth out float fogHeight;
+
+uniform mat4 cameraToClipMatrix;
+uniform mat4 worldToCameraMatrix;
+
+void main()
+{
+    vec4 temp = model_matrix * vec4(position, 1.0);
+    fogHeight = temp.y;
+    temp = worldToCameraMatrix * temp;
+    fogDistance = length(temp.xyz);
+    gl_Position = cameraToClipMatrix * temp;
+    texCoords = tex_coords;
+    theColor = color * instance_color;
+}
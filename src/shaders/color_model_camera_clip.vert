@@ -2,8 +2,12 @@
 
 layout(location = 0) in vec3 position;
 layout(location = 1) in vec4 color;
+layout(location = 2) in vec2 tex_coords;
 
 smooth out vec4 theColor;
+smooth out vec2 texCoords;
+smooth out float fogDistance;
+smooth out float fogHeight;
 
 uniform mat4 cameraToClipMatrix;
 uniform mat4 modelToCameraMatrix;
@@ -11,6 +15,10 @@ uniform mat4 modelToCameraMatrix;
 void main()
 {
     vec4 cameraPos = modelToCameraMatrix * vec4(position, 1.0);
+    // there is no world space here, so height fog is measured from the camera
+    fogHeight = cameraPos.y;
+    fogDistance = length(cameraPos.xyz);
     gl_Position = cameraToClipMatrix * cameraPos;
+    texCoords = tex_coords;
     theColor = color;
 }
@@ -2,8 +2,12 @@
 
 layout(location = 0) in vec3 position;
 layout(location = 1) in vec4 color;
+layout(location = 2) in vec2 tex_coords;
 
 smooth out vec4 theColor;
+smooth out vec2 texCoords;
+smooth out float fogDistance;
+smooth out float fogHeight;
 
 uniform mat4 cameraToClipMatrix;
 uniform mat4 worldToCameraMatrix;
@@ -12,7 +16,10 @@ uniform mat4 modelToWorldMatrix;
 void main()
 {
     vec4 temp = modelToWorldMatrix * vec4(position, 1.0);
+    fogHeight = temp.y;
     temp = worldToCameraMatrix * temp;
+    fogDistance = length(temp.xyz);
     gl_Position = cameraToClipMatrix * temp;
+    texCoords = tex_coords;
     theColor = color;
 }
@@ -0,0 +1,10 @@
+#version 330
+
+flat in vec4 pickColor;
+
+out vec4 outputColor;
+
+void main()
+{
+    outputColor = pickColor;
+}
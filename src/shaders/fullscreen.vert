@@ -0,0 +1,12 @@
+#version 330
+
+// corners of the screen in clip space, drawn as a triangle strip
+layout(location = 0) in vec2 position;
+
+smooth out vec2 texCoords;
+
+void main()
+{
+    texCoords = position * 0.5 + 0.5;
+    gl_Position = vec4(position, 0.0, 1.0);
+}
@@ -0,0 +1,50 @@
+#version 330
+
+smooth in vec2 texCoords;
+
+out vec4 outputColor;
+
+uniform sampler2D sceneTexture;
+uniform vec2 texelSize;
+
+// expects gamma corrected colors, edges are found on perceived luma.
+// reference: https://github.com/mattdesl/glsl-fxaa
+const float REDUCE_MIN = 1.0 / 128.0;
+const float REDUCE_MUL = 1.0 / 8.0;
+const float SPAN_MAX = 8.0;
+const vec3 LUMA = vec3(0.299, 0.587, 0.114);
+
+void main()
+{
+    vec4 colorM = texture(sceneTexture, texCoords);
+    float lumaNW = dot(texture(sceneTexture, texCoords + vec2(-1.0, -1.0) * texelSize).rgb, LUMA);
+    float lumaNE = dot(texture(sceneTexture, texCoords + vec2(1.0, -1.0) * texelSize).rgb, LUMA);
+    float lumaSW = dot(texture(sceneTexture, texCoords + vec2(-1.0, 1.0) * texelSize).rgb, LUMA);
+    float lumaSE = dot(texture(sceneTexture, texCoords + vec2(1.0, 1.0) * texelSize).rgb, LUMA);
+    float lumaM = dot(colorM.rgb, LUMA);
+
+    float lumaMin = min(lumaM, min(min(lumaNW, lumaNE), min(lumaSW, lumaSE)));
+    float lumaMax = max(lumaM, max(max(lumaNW, lumaNE), max(lumaSW, lumaSE)));
+
+    vec2 direction = vec2(
+        -((lumaNW + lumaNE) - (lumaSW + lumaSE)),
+        (lumaNW + lumaSW) - (lumaNE + lumaSE)
+    );
+
+    float directionReduce = max((lumaNW + lumaNE + lumaSW + lumaSE) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    float inverseDirectionMin = 1.0 / (min(abs(direction.x), abs(direction.y)) + directionReduce);
+    direction = clamp(direction * inverseDirectionMin, vec2(-SPAN_MAX), vec2(SPAN_MAX)) * texelSize;
+
+    vec3 colorA = 0.5 * (
+        texture(sceneTexture, texCoords + direction * (1.0 / 3.0 - 0.5)).rgb +
+        texture(sceneTexture, texCoords + direction * (2.0 / 3.0 - 0.5)).rgb
+    );
+    vec3 colorB = colorA * 0.5 + 0.25 * (
+        texture(sceneTexture, texCoords + direction * -0.5).rgb +
+        texture(sceneTexture, texCoords + direction * 0.5).rgb
+    );
+
+    float lumaB = dot(colorB, LUMA);
+    vec3 color = (lumaB < lumaMin || lumaB > lumaMax) ? colorA : colorB;
+    outputColor = vec4(color, colorM.a);
+}
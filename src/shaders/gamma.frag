@@ -0,0 +1,14 @@
+#version 330
+
+smooth in vec2 texCoords;
+
+out vec4 outputColor;
+
+uniform sampler2D sceneTexture;
+uniform float gamma;
+
+void main()
+{
+    vec4 color = texture(sceneTexture, texCoords);
+    outputColor = vec4(pow(max(color.rgb, vec3(0.0)), vec3(1.0 / gamma)), color.a);
+}
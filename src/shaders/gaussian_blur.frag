@@ -0,0 +1,28 @@
+#version 330
+
+smooth in vec2 texCoords;
+
+out vec4 outputColor;
+
+uniform sampler2D sceneTexture;
+uniform vec2 texelSize;
+// (1, 0) blurs horizontally, (0, 1) vertically
+uniform vec2 direction;
+
+// 9 taps gaussian done in 5 samples, using linear filtering between texels.
+// reference: https://www.rastergrid.com/blog/2010/09/efficient-gaussian-blur-with-linear-sampling/
+const float offsets[3] = float[](0.0, 1.3846153846, 3.2307692308);
+const float weights[3] = float[](0.2270270270, 0.3162162162, 0.0702702703);
+
+void main()
+{
+    vec3 color = texture(sceneTexture, texCoords).rgb * weights[0];
+
+    for (int i = 1; i < 3; i++) {
+        vec2 offset = direction * texelSize * offsets[i];
+        color += texture(sceneTexture, texCoords + offset).rgb * weights[i];
+        color += texture(sceneTexture, texCoords - offset).rgb * weights[i];
+    }
+
+    outputColor = vec4(color, 1.0);
+}
@@ -0,0 +1,18 @@
+#version 330
+
+layout(location = 0) in vec3 position;
+
+// on the picking pass instance_color carries the encoded instance id, not a color
+in mat4 model_matrix;
+in vec4 instance_color;
+
+flat out vec4 pickColor;
+
+uniform mat4 cameraToClipMatrix;
+uniform mat4 worldToCameraMatrix;
+
+void main()
+{
+    gl_Position = cameraToClipMatrix * worldToCameraMatrix * model_matrix * vec4(position, 1.0);
+    pickColor = instance_color;
+}
@@ -11,6 +11,19 @@ impl VertexShader {
     pub fn color_world_model_camera_clip() -> String {
         read_to_string("src/shaders/color_world_model_camera_clip.vert").unwrap()
     }
+
+    pub fn color_instanced() -> String {
+        read_to_string("src/shaders/color_instanced.vert").unwrap()
+    }
+
+    pub fn id_instanced() -> String {
+        read_to_string("src/shaders/id_instanced.vert").unwrap()
+    }
+
+    /// for post processing passes, covers the screen and outputs texture coordinates
+    pub fn fullscreen() -> String {
+        read_to_string("src/shaders/fullscreen.vert").unwrap()
+    }
 }
 
 pub struct FragmentShader {}
@@ -19,4 +32,40 @@ impl FragmentShader {
     pub fn smooth_color() -> String {
         read_to_string("src/shaders/smooth_color.frag").unwrap()
     }
+
+    pub fn flat_id() -> String {
+        read_to_string("src/shaders/flat_id.frag").unwrap()
+    }
+
+    pub fn post_copy() -> String {
+        read_to_string("src/shaders/post_copy.frag").unwrap()
+    }
+
+    pub fn bright_pass() -> String {
+        read_to_string("src/shaders/bright_pass.frag").unwrap()
+    }
+
+    pub fn gaussian_blur() -> String {
+        read_to_string("src/shaders/gaussian_blur.frag").unwrap()
+    }
+
+    pub fn bloom_composite() -> String {
+        read_to_string("src/shaders/bloom_composite.frag").unwrap()
+    }
+
+    pub fn tone_mapping() -> String {
+        read_to_string("src/shaders/tone_mapping.frag").unwrap()
+    }
+
+    pub fn gamma() -> String {
+        read_to_string("src/shaders/gamma.frag").unwrap()
+    }
+
+    pub fn fxaa() -> String {
+        read_to_string("src/shaders/fxaa.frag").unwrap()
+    }
+
+    pub fn vignette() -> String {
+        read_to_string("src/shaders/vignette.frag").unwrap()
+    }
 }
@@ -0,0 +1,12 @@
+#version 330
+
+smooth in vec2 texCoords;
+
+out vec4 outputColor;
+
+uniform sampler2D sceneTexture;
+
+void main()
+{
+    outputColor = texture(sceneTexture, texCoords);
+}
@@ -1,10 +1,48 @@
 #version 330
 
 smooth in vec4 theColor;
+smooth in vec2 texCoords;
+smooth in float fogDistance;
+smooth in float fogHeight;
 
 out vec4 outputColor;
 
+uniform vec4 materialColor;
+// plain white when the material has no texture
+uniform sampler2D materialTexture;
+
+// 0: off, 1: linear, 2: exponential, 3: exponential squared
+uniform int fogMode;
+uniform vec3 fogColor;
+uniform float fogStart;
+uniform float fogEnd;
+uniform float fogDensity;
+uniform float fogHeightBase;
+uniform float fogHeightFalloff;
+
+float fogVisibility()
+{
+    float visibility = 1.0;
+
+    if (fogMode == 1) {
+        visibility = fogEnd > fogStart
+            ? (fogEnd - fogDistance) / (fogEnd - fogStart)
+            : step(fogDistance, fogStart);
+    } else if (fogMode == 2) {
+        visibility = exp(-fogDensity * fogDistance);
+    } else if (fogMode == 3) {
+        float amount = fogDensity * fogDistance;
+        visibility = exp(-amount * amount);
+    }
+
+    visibility = clamp(visibility, 0.0, 1.0);
+
+    float heightFactor = exp(-fogHeightFalloff * max(fogHeight - fogHeightBase, 0.0));
+    return 1.0 - (1.0 - visibility) * heightFactor;
+}
+
 void main()
 {
-    outputColor = theColor;
-}
\ No newline at end of file
+    vec4 color = theColor * materialColor * texture(materialTexture, texCoords);
+    outputColor = vec4(mix(fogColor, color.rgb, fogVisibility()), color.a);
+}
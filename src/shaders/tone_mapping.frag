@@ -0,0 +1,21 @@
+#version 330
+
+smooth in vec2 texCoords;
+
+out vec4 outputColor;
+
+uniform sampler2D sceneTexture;
+uniform float exposure;
+
+// fitted ACES filmic curve.
+// reference: https://knarkowicz.wordpress.com/2016/01/06/aces-filmic-tone-mapping-curve/
+vec3 aces(vec3 color)
+{
+    return clamp((color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14), 0.0, 1.0);
+}
+
+void main()
+{
+    vec4 color = texture(sceneTexture, texCoords);
+    outputColor = vec4(aces(color.rgb * exposure), color.a);
+}
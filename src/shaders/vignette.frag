@@ -0,0 +1,21 @@
+#version 330
+
+smooth in vec2 texCoords;
+
+out vec4 outputColor;
+
+uniform sampler2D sceneTexture;
+// 0.0 leaves the corners untouched, 1.0 makes them black
+uniform float intensity;
+// distance from the center where the darkening starts, 0.5 is the screen border
+uniform float radius;
+uniform float softness;
+
+void main()
+{
+    vec4 color = texture(sceneTexture, texCoords);
+    float distanceToCenter = distance(texCoords, vec2(0.5));
+    float vignette = smoothstep(radius + softness, radius, distanceToCenter);
+
+    outputColor = vec4(color.rgb * mix(1.0, vignette, intensity), color.a);
+}
@@ -0,0 +1,640 @@
+use crate::math::{clamp, Vector3};
+use crate::mesh::Mesh;
+use crate::primitives::Vertex;
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+// Generators for the built in shapes. Every shape is centered on the origin with y up,
+// winds counter clockwise seen from outside and has v = 0 on the bottom row of the texture.
+// Counts too small to close the shape are raised to the smallest that does.
+
+/// around y, or around the tube of a torus
+const MIN_SEGMENTS: u32 = 3;
+/// from pole to pole
+const MIN_RINGS: u32 = 2;
+/// bands along a straight side, rows of a plane and rings of a capsule cap
+const MIN_STACKS: u32 = 1;
+
+fn vertex(position: Vector3, normal: Vector3, tex_coords: [f32; 2]) -> Vertex {
+    Vertex {
+        position: position.to_array(),
+        color: [1.0, 1.0, 1.0, 1.0],
+        tex_coords,
+        normal: normal.to_array(),
+    }
+}
+
+/// a point on the outline spun around y: distance from the axis, height,
+/// the outward normal in the same two axes and the texture v
+#[derive(Debug, Clone, Copy)]
+struct ProfilePoint {
+    radius: f32,
+    height: f32,
+    normal: [f32; 2],
+    v: f32,
+}
+
+impl ProfilePoint {
+    fn new(radius: f32, height: f32, normal: [f32; 2], v: f32) -> Self {
+        ProfilePoint {
+            radius,
+            height,
+            normal,
+            v,
+        }
+    }
+}
+
+/// Spins a bottom to top outline around y into a surface of revolution. Each point
+/// becomes a ring of `segments + 1` vertices, the first and last share a position so
+/// u can run from 0 to 1. Points with a radius of exactly 0.0 are poles.
+///
+/// reference: https://en.wikipedia.org/wiki/Solid_of_revolution
+fn lathe(profile: &[ProfilePoint], segments: u32) -> Mesh {
+    let columns = segments + 1;
+    let mut mesh = Mesh::default();
+
+    for point in profile {
+        for column in 0..columns {
+            let u = column as f32 / segments as f32;
+            // the last column lands exactly on the first, sin(2 PI) is not quite zero
+            let angle = 2.0 * PI * (column % segments) as f32 / segments as f32;
+            let (sin, cos) = angle.sin_cos();
+            mesh.vertices.push(vertex(
+                Vector3::new(point.radius * cos, point.height, -point.radius * sin),
+                Vector3::new(
+                    point.normal[0] * cos,
+                    point.normal[1],
+                    -point.normal[0] * sin,
+                ),
+                [u, point.v],
+            ));
+        }
+    }
+
+    for (ring, pair) in profile.windows(2).enumerate() {
+        let bottom = ring as u32 * columns;
+        let top = bottom + columns;
+        for column in 0..segments {
+            // the triangle along a pole would have no area
+            if pair[0].radius != 0.0 {
+                mesh.indices
+                    .extend(&[bottom + column, bottom + column + 1, top + column + 1]);
+            }
+            if pair[1].radius != 0.0 {
+                mesh.indices
+                    .extend(&[bottom + column, top + column + 1, top + column]);
+            }
+        }
+    }
+    mesh
+}
+
+/// flat round cap at `height`, facing up or down
+fn disk(radius: f32, height: f32, segments: u32, up: bool) -> Mesh {
+    let normal = Vector3::new(0.0, if up { 1.0 } else { -1.0 }, 0.0);
+    let mut mesh = Mesh::default();
+    mesh.vertices
+        .push(vertex(Vector3::new(0.0, height, 0.0), normal, [0.5, 0.5]));
+
+    for column in 0..=segments {
+        let (sin, cos) = (2.0 * PI * (column % segments) as f32 / segments as f32).sin_cos();
+        mesh.vertices.push(vertex(
+            Vector3::new(radius * cos, height, -radius * sin),
+            normal,
+            [0.5 + 0.5 * cos, 0.5 + 0.5 * sin],
+        ));
+    }
+
+    for column in 1..=segments {
+        if up {
+            mesh.indices.extend(&[0, column, column + 1]);
+        } else {
+            mesh.indices.extend(&[0, column + 1, column]);
+        }
+    }
+    mesh
+}
+
+/// `rings` bands from pole to pole, each cut in `segments` around y
+pub fn sphere(radius: f32, segments: u32, rings: u32) -> Mesh {
+    let rings = rings.max(MIN_RINGS);
+    let profile: Vec<ProfilePoint> = (0..=rings)
+        .map(|ring| {
+            let v = ring as f32 / rings as f32;
+            let (sin, cos) = (PI * v).sin_cos();
+            // sin(PI) is not quite zero
+            let sin = if ring == 0 || ring == rings { 0.0 } else { sin };
+            ProfilePoint::new(radius * sin, -radius * cos, [sin, -cos], v)
+        })
+        .collect();
+    lathe(&profile, segments.max(MIN_SEGMENTS))
+}
+
+/// Icosahedron with each triangle split in four `subdivisions` times, so the triangles
+/// are closer in size than on a uv sphere. The texture wraps like on `sphere`: triangles
+/// crossing the seam get copies of their vertices with u past 1, and triangles touching
+/// a pole get a copy of it with u in the middle of their other two vertices.
+///
+/// reference: http://blog.andreaskahler.com/2009/06/creating-icosphere-mesh-in-code.html
+pub fn icosphere(radius: f32, subdivisions: u32) -> Mesh {
+    let t = (1.0 + 5.0_f32.sqrt()) / 2.0;
+    let mut points: Vec<Vector3> = vec![
+        Vector3::new(-1.0, t, 0.0),
+        Vector3::new(1.0, t, 0.0),
+        Vector3::new(-1.0, -t, 0.0),
+        Vector3::new(1.0, -t, 0.0),
+        Vector3::new(0.0, -1.0, t),
+        Vector3::new(0.0, 1.0, t),
+        Vector3::new(0.0, -1.0, -t),
+        Vector3::new(0.0, 1.0, -t),
+        Vector3::new(t, 0.0, -1.0),
+        Vector3::new(t, 0.0, 1.0),
+        Vector3::new(-t, 0.0, -1.0),
+        Vector3::new(-t, 0.0, 1.0),
+    ]
+    .into_iter()
+    .map(Vector3::normalized)
+    .collect();
+
+    #[rustfmt::skip]
+    let mut triangles: Vec<[u32; 3]> = vec![
+        [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+        [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+        [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+        [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions {
+        // edges are shared by two triangles, both must get the same middle vertex
+        let mut middles: HashMap<(u32, u32), u32> = HashMap::new();
+        let mut middle = |a: u32, b: u32| -> u32 {
+            let key = (a.min(b), a.max(b));
+            *middles.entry(key).or_insert_with(|| {
+                points.push(((points[a as usize] + points[b as usize]) * 0.5).normalized());
+                points.len() as u32 - 1
+            })
+        };
+
+        triangles = triangles
+            .iter()
+            .flat_map(|&[a, b, c]| {
+                let ab = middle(a, b);
+                let bc = middle(b, c);
+                let ca = middle(c, a);
+                vec![[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            })
+            .collect();
+    }
+
+    let mut vertices: Vec<Vertex> = points
+        .iter()
+        .map(|&normal| {
+            let u = (-normal.z).atan2(normal.x) / (2.0 * PI);
+            let u = if u < 0.0 { u + 1.0 } else { u };
+            let v = clamp(-normal.y, -1.0, 1.0).acos() / PI;
+            vertex(normal * radius, normal, [u, v])
+        })
+        .collect();
+    // copies past the seam are never poles
+    let is_pole = |index: u32| match points.get(index as usize) {
+        Some(point) => point.x.abs() < 1e-6 && point.z.abs() < 1e-6,
+        None => false,
+    };
+
+    // the copy past the seam is shared by every triangle crossing it there
+    let mut wrapped: HashMap<u32, u32> = HashMap::new();
+    let mut indices = Vec::with_capacity(triangles.len() * 3);
+    for triangle in &triangles {
+        let mut corners = *triangle;
+        let u = |vertices: &[Vertex], index: u32| vertices[index as usize].tex_coords[0];
+
+        let sides: Vec<f32> = corners
+            .iter()
+            .filter(|&&index| !is_pole(index))
+            .map(|&index| u(&vertices, index))
+            .collect();
+        let lowest = sides.iter().cloned().fold(f32::MAX, f32::min);
+        let highest = sides.iter().cloned().fold(f32::MIN, f32::max);
+        if highest - lowest > 0.5 {
+            for corner in corners.iter_mut() {
+                if !is_pole(*corner) && u(&vertices, *corner) < 0.5 {
+                    *corner = *wrapped.entry(*corner).or_insert_with(|| {
+                        let mut copy = vertices[*corner as usize];
+                        copy.tex_coords[0] += 1.0;
+                        vertices.push(copy);
+                        vertices.len() as u32 - 1
+                    });
+                }
+            }
+        }
+
+        for i in 0..3 {
+            if is_pole(corners[i]) {
+                let mut copy = vertices[corners[i] as usize];
+                copy.tex_coords[0] =
+                    (u(&vertices, corners[(i + 1) % 3]) + u(&vertices, corners[(i + 2) % 3])) / 2.0;
+                vertices.push(copy);
+                corners[i] = vertices.len() as u32 - 1;
+            }
+        }
+        indices.extend(&corners);
+    }
+    Mesh::new(vertices, indices)
+}
+
+/// closed tube standing on y, `stacks` bands high
+pub fn cylinder(radius: f32, height: f32, segments: u32, stacks: u32) -> Mesh {
+    let (segments, stacks) = (segments.max(MIN_SEGMENTS), stacks.max(MIN_STACKS));
+    let profile: Vec<ProfilePoint> = (0..=stacks)
+        .map(|stack| {
+            let v = stack as f32 / stacks as f32;
+            ProfilePoint::new(radius, height * (v - 0.5), [1.0, 0.0], v)
+        })
+        .collect();
+
+    let mut mesh = lathe(&profile, segments);
+    mesh.append(&disk(radius, -height / 2.0, segments, false));
+    mesh.append(&disk(radius, height / 2.0, segments, true));
+    mesh
+}
+
+/// base on the bottom, tip on top
+pub fn cone(radius: f32, height: f32, segments: u32, stacks: u32) -> Mesh {
+    let (segments, stacks) = (segments.max(MIN_SEGMENTS), stacks.max(MIN_STACKS));
+    let slant = Vector3::new(height, radius, 0.0).normalized();
+    let profile: Vec<ProfilePoint> = (0..=stacks)
+        .map(|stack| {
+            let v = stack as f32 / stacks as f32;
+            let ring_radius = if stack == stacks {
+                0.0
+            } else {
+                radius * (1.0 - v)
+            };
+            ProfilePoint::new(ring_radius, height * (v - 0.5), [slant.x, slant.y], v)
+        })
+        .collect();
+
+    let mut mesh = lathe(&profile, segments);
+    mesh.append(&disk(radius, -height / 2.0, segments, false));
+    mesh
+}
+
+/// Ring lying on the xz plane. `major_radius` goes to the middle of the tube,
+/// `sides` cut the tube around its own center
+pub fn torus(major_radius: f32, minor_radius: f32, segments: u32, sides: u32) -> Mesh {
+    let sides = sides.max(MIN_SEGMENTS);
+    let profile: Vec<ProfilePoint> = (0..=sides)
+        .map(|side| {
+            let v = side as f32 / sides as f32;
+            let (sin, cos) = (2.0 * PI * (side % sides) as f32 / sides as f32).sin_cos();
+            ProfilePoint::new(
+                major_radius + minor_radius * cos,
+                minor_radius * sin,
+                [cos, sin],
+                v,
+            )
+        })
+        .collect();
+    lathe(&profile, segments.max(MIN_SEGMENTS))
+}
+
+/// Flat grid on the xz plane facing +y, cut in `x_segments` by `z_segments` squares.
+/// Seen from above the texture is upright with its top towards -z
+pub fn plane(width: f32, depth: f32, x_segments: u32, z_segments: u32) -> Mesh {
+    let (x_segments, z_segments) = (x_segments.max(MIN_STACKS), z_segments.max(MIN_STACKS));
+    let columns = x_segments + 1;
+    let normal = Vector3::new(0.0, 1.0, 0.0);
+    let mut mesh = Mesh::default();
+
+    for row in 0..=z_segments {
+        let v = row as f32 / z_segments as f32;
+        for column in 0..columns {
+            let u = column as f32 / x_segments as f32;
+            mesh.vertices.push(vertex(
+                Vector3::new(width * (u - 0.5), 0.0, depth * (0.5 - v)),
+                normal,
+                [u, v],
+            ));
+        }
+    }
+
+    for row in 0..z_segments {
+        for column in 0..x_segments {
+            let near = row * columns + column;
+            let far = near + columns;
+            mesh.indices
+                .extend(&[near, near + 1, far + 1, near, far + 1, far]);
+        }
+    }
+    mesh
+}
+
+/// Cylinder with half spheres for caps, `height` goes from tip to tip like the
+/// character capsule. `rings` bands make each half sphere
+pub fn capsule(radius: f32, height: f32, segments: u32, rings: u32) -> Mesh {
+    let rings = rings.max(MIN_STACKS);
+    let straight = (height - 2.0 * radius).max(0.0);
+    // v follows the outline length so the texture is not stretched on the caps
+    let length = straight + PI * radius;
+
+    // the equator ring is doubled to stretch the straight part between the caps
+    let mut rings_on_caps: Vec<(u32, f32)> = (0..=rings).map(|ring| (ring, 0.0)).collect();
+    let first_top = if straight > 0.0 { rings } else { rings + 1 };
+    rings_on_caps.extend((first_top..=2 * rings).map(|ring| (ring, straight)));
+
+    let profile: Vec<ProfilePoint> = rings_on_caps
+        .into_iter()
+        .map(|(ring, offset)| {
+            let angle = PI * ring as f32 / (2 * rings) as f32;
+            let (sin, cos) = angle.sin_cos();
+            let sin = if ring == 0 || ring == 2 * rings {
+                0.0
+            } else {
+                sin
+            };
+            ProfilePoint::new(
+                radius * sin,
+                offset - straight / 2.0 - radius * cos,
+                [sin, -cos],
+                (radius * angle + offset) / length,
+            )
+        })
+        .collect();
+    lathe(&profile, segments.max(MIN_SEGMENTS))
+}
+
+/// Pointing up from the origin to `length`, like the y axis of a gizmo.
+/// The head is `head_length` long, the shaft makes up the rest
+pub fn arrow(
+    length: f32,
+    shaft_radius: f32,
+    head_radius: f32,
+    head_length: f32,
+    segments: u32,
+) -> Mesh {
+    let shaft_length = (length - head_length).max(0.0);
+
+    let mut mesh = cylinder(shaft_radius, shaft_length, segments, 1);
+    for vertex in &mut mesh.vertices {
+        vertex.position[1] += shaft_length / 2.0;
+    }
+
+    let mut head = cone(head_radius, head_length, segments, 1);
+    for vertex in &mut head.vertices {
+        vertex.position[1] += shaft_length + head_length / 2.0;
+    }
+    mesh.append(&head);
+    mesh
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(mesh: &Mesh, index: u32) -> Vector3 {
+        let [x, y, z] = mesh.vertices[index as usize].position;
+        Vector3::new(x, y, z)
+    }
+
+    fn normal(mesh: &Mesh, index: u32) -> Vector3 {
+        let [x, y, z] = mesh.vertices[index as usize].normal;
+        Vector3::new(x, y, z)
+    }
+
+    /// divergence theorem over the triangles, only positive when they face out
+    fn volume(mesh: &Mesh) -> f32 {
+        mesh.triangles()
+            .map(|[a, b, c]| {
+                position(mesh, a).dot(position(mesh, b).cross(position(mesh, c))) / 6.0
+            })
+            .sum()
+    }
+
+    fn check_shape(mesh: &Mesh) {
+        assert_eq!(mesh.indices.len() % 3, 0);
+        for [a, b, c] in mesh.triangles() {
+            for &index in &[a, b, c] {
+                assert!((index as usize) < mesh.vertices.len());
+            }
+
+            let (pa, pb, pc) = (position(mesh, a), position(mesh, b), position(mesh, c));
+            let face = (pb - pa).cross(pc - pa);
+            assert!(face.length() > 0.0, "triangle {:?} has no area", [a, b, c]);
+            for &index in &[a, b, c] {
+                assert!(
+                    face.dot(normal(mesh, index)) > 0.0,
+                    "triangle {:?} winds against its normals",
+                    [a, b, c]
+                );
+            }
+        }
+
+        for vertex in &mesh.vertices {
+            assert!(
+                (Vector3::new(vertex.normal[0], vertex.normal[1], vertex.normal[2]).length() - 1.0)
+                    .abs()
+                    < 1e-4
+            );
+            // u goes past 1 where the icosphere wraps around the seam
+            let [u, v] = vertex.tex_coords;
+            assert!((0.0..2.0).contains(&u));
+            assert!((0.0..=1.0).contains(&v));
+        }
+    }
+
+    /// vertices copied for the texture share a position with the original
+    fn distinct_positions(mesh: &Mesh) -> usize {
+        let mut positions: Vec<[u32; 3]> = mesh
+            .vertices
+            .iter()
+            .map(|vertex| {
+                let [x, y, z] = vertex.position;
+                [x.to_bits(), y.to_bits(), z.to_bits()]
+            })
+            .collect();
+        positions.sort_unstable();
+        positions.dedup();
+        positions.len()
+    }
+
+    fn assert_close(actual: f32, expected: f32, tolerance: f32) {
+        assert!(
+            (actual - expected).abs() <= tolerance * expected.abs(),
+            "{} is not close to {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn sphere_points_are_on_the_radius() {
+        let mesh = sphere(2.0, 32, 16);
+        check_shape(&mesh);
+        assert_eq!(mesh.vertices.len(), 33 * 17);
+        for index in 0..mesh.vertices.len() as u32 {
+            assert_close(position(&mesh, index).length(), 2.0, 1e-5);
+        }
+        assert_close(volume(&mesh), 4.0 / 3.0 * PI * 8.0, 0.02);
+    }
+
+    #[test]
+    fn icosphere_subdivides_every_triangle_in_four() {
+        let base = icosphere(1.0, 0);
+        check_shape(&base);
+        assert_eq!(distinct_positions(&base), 12);
+        assert_eq!(base.indices.len(), 20 * 3);
+
+        let mesh = icosphere(1.0, 2);
+        check_shape(&mesh);
+        assert_eq!(mesh.indices.len(), 20 * 16 * 3);
+        // shared edges get one middle vertex, V - E + F = 2
+        assert_eq!(distinct_positions(&mesh), 162);
+        assert_close(volume(&mesh), 4.0 / 3.0 * PI, 0.05);
+    }
+
+    #[test]
+    fn icosphere_triangles_do_not_squeeze_the_texture() {
+        for subdivisions in 0..3 {
+            let mesh = icosphere(1.0, subdivisions);
+            for [a, b, c] in mesh.triangles() {
+                let u: Vec<f32> = [a, b, c]
+                    .iter()
+                    .map(|&index| mesh.vertices[index as usize].tex_coords[0])
+                    .collect();
+                let lowest = u.iter().cloned().fold(f32::MAX, f32::min);
+                let highest = u.iter().cloned().fold(f32::MIN, f32::max);
+                assert!(
+                    highest - lowest <= 0.5,
+                    "triangle {:?} spans u {:?}",
+                    [a, b, c],
+                    u
+                );
+            }
+        }
+
+        // the pole is copied for each triangle around it, in the middle of the other two
+        let mesh = icosphere(1.0, 1);
+        let mut around_the_pole = 0;
+        for [a, b, c] in mesh.triangles() {
+            let corners = [a, b, c];
+            for i in 0..3 {
+                if position(&mesh, corners[i]).y == 1.0 {
+                    let u = |index: u32| mesh.vertices[index as usize].tex_coords[0];
+                    let middle = (u(corners[(i + 1) % 3]) + u(corners[(i + 2) % 3])) / 2.0;
+                    assert_eq!(u(corners[i]), middle);
+                    around_the_pole += 1;
+                }
+            }
+        }
+        assert_eq!(around_the_pole, 6);
+    }
+
+    #[test]
+    fn zero_counts_are_raised_to_a_closed_shape() {
+        let meshes = [
+            sphere(1.0, 0, 0),
+            sphere(1.0, 1, 1),
+            cylinder(1.0, 2.0, 0, 0),
+            cone(1.0, 2.0, 0, 0),
+            torus(2.0, 0.5, 0, 0),
+            capsule(0.5, 2.0, 0, 0),
+            arrow(1.0, 0.05, 0.1, 0.25, 0),
+        ];
+        for mesh in meshes.iter() {
+            check_shape(mesh);
+            assert!(volume(mesh) > 0.0);
+        }
+
+        assert_eq!(sphere(1.0, 0, 0).indices, sphere(1.0, 3, 2).indices);
+        assert_eq!(torus(2.0, 0.5, 1, 2).indices, torus(2.0, 0.5, 3, 3).indices);
+        assert_eq!(plane(1.0, 1.0, 0, 0).indices, vec![0, 1, 3, 0, 3, 2]);
+    }
+
+    #[test]
+    fn closed_shapes_have_their_volume() {
+        let mesh = cylinder(0.5, 2.0, 48, 3);
+        check_shape(&mesh);
+        assert_close(volume(&mesh), PI * 0.25 * 2.0, 0.01);
+
+        let mesh = cone(1.0, 3.0, 48, 2);
+        check_shape(&mesh);
+        assert_close(volume(&mesh), PI * 3.0 / 3.0, 0.01);
+
+        let mesh = torus(2.0, 0.5, 64, 32);
+        check_shape(&mesh);
+        assert_close(volume(&mesh), 2.0 * PI * PI * 2.0 * 0.25, 0.01);
+
+        let mesh = capsule(0.5, 3.0, 48, 12);
+        check_shape(&mesh);
+        assert_close(
+            volume(&mesh),
+            PI * 0.25 * 2.0 + 4.0 / 3.0 * PI * 0.125,
+            0.01,
+        );
+
+        let mesh = arrow(1.0, 0.05, 0.1, 0.25, 16);
+        check_shape(&mesh);
+        assert!(volume(&mesh) > 0.0);
+    }
+
+    #[test]
+    fn cylinder_caps_face_up_and_down() {
+        let mesh = cylinder(1.0, 2.0, 8, 1);
+        for &(normal, height) in &[(1.0, 1.0), (-1.0, -1.0)] {
+            let cap: Vec<&Vertex> = mesh
+                .vertices
+                .iter()
+                .filter(|vertex| vertex.normal == [0.0, normal, 0.0])
+                .collect();
+            assert_eq!(cap.len(), 1 + 9);
+            assert!(cap.iter().all(|vertex| vertex.position[1] == height));
+        }
+    }
+
+    #[test]
+    fn capsule_spans_its_height() {
+        let mesh = capsule(0.5, 2.0, 16, 8);
+        let heights: Vec<f32> = mesh
+            .vertices
+            .iter()
+            .map(|vertex| vertex.position[1])
+            .collect();
+        let lowest = heights.iter().cloned().fold(f32::MAX, f32::min);
+        let highest = heights.iter().cloned().fold(f32::MIN, f32::max);
+        assert_close(lowest, -1.0, 1e-5);
+        assert_close(highest, 1.0, 1e-5);
+
+        // a capsule too short for its caps is a sphere
+        let mesh = capsule(0.5, 0.5, 48, 12);
+        check_shape(&mesh);
+        assert_close(volume(&mesh), 4.0 / 3.0 * PI * 0.125, 0.02);
+    }
+
+    #[test]
+    fn plane_is_a_grid_facing_up() {
+        let mesh = plane(4.0, 2.0, 4, 2);
+        check_shape(&mesh);
+        assert_eq!(mesh.vertices.len(), 5 * 3);
+        assert_eq!(mesh.indices.len(), 4 * 2 * 6);
+
+        let first = &mesh.vertices[0];
+        assert_eq!(first.position, [-2.0, 0.0, 1.0]);
+        assert_eq!(first.tex_coords, [0.0, 0.0]);
+        let last = mesh.vertices.last().unwrap();
+        assert_eq!(last.position, [2.0, 0.0, -1.0]);
+        assert_eq!(last.tex_coords, [1.0, 1.0]);
+    }
+
+    #[test]
+    fn arrow_points_up_from_the_origin() {
+        let mesh = arrow(2.0, 0.1, 0.2, 0.5, 12);
+        let heights: Vec<f32> = mesh
+            .vertices
+            .iter()
+            .map(|vertex| vertex.position[1])
+            .collect();
+        assert_eq!(heights.iter().cloned().fold(f32::MAX, f32::min), 0.0);
+        assert_close(heights.iter().cloned().fold(f32::MIN, f32::max), 2.0, 1e-6);
+    }
+}
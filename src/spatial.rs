@@ -0,0 +1,489 @@
+use crate::math::{Aabb, Frustum, Ray, Sphere};
+use std::cmp::Ordering;
+
+pub type ProxyId = usize;
+
+const NULL_NODE: usize = usize::MAX;
+
+struct Node<T> {
+    /// tree bounds: the enlarged box for leaves, the union of children for branches
+    aabb: Aabb,
+    /// exact bounds of the item, only meaningful on leaves
+    bounds: Aabb,
+    parent: usize,
+    left: usize,
+    right: usize,
+    item: Option<T>,
+}
+
+impl<T> Node<T> {
+    fn is_leaf(&self) -> bool {
+        self.left == NULL_NODE
+    }
+}
+
+/// Dynamic bounding volume hierarchy, the same idea as Box2D's b2DynamicTree.
+/// Leaves keep a box enlarged by `margin`, so small movements only refresh the exact bounds
+/// and the tree is restructured only when an item leaves its enlarged box.
+/// reference: https://box2d.org/files/ErinCatto_DynamicBVH_GDC2019.pdf
+pub struct SpatialIndex<T> {
+    nodes: Vec<Node<T>>,
+    free: Vec<usize>,
+    root: usize,
+    margin: f32,
+    len: usize,
+}
+
+#[allow(dead_code)]
+impl<T> SpatialIndex<T> {
+    pub fn new(margin: f32) -> Self {
+        SpatialIndex {
+            nodes: Vec::new(),
+            free: Vec::new(),
+            root: NULL_NODE,
+            margin,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn insert(&mut self, bounds: Aabb, item: T) -> ProxyId {
+        let leaf = self.allocate(bounds.expanded(self.margin));
+        self.nodes[leaf].bounds = bounds;
+        self.nodes[leaf].item = Some(item);
+        self.insert_leaf(leaf);
+        self.len += 1;
+
+        leaf
+    }
+
+    pub fn remove(&mut self, proxy: ProxyId) -> Option<T> {
+        if proxy >= self.nodes.len() || !self.nodes[proxy].is_leaf() {
+            return None;
+        }
+
+        let item = self.nodes[proxy].item.take()?;
+        self.remove_leaf(proxy);
+        self.release(proxy);
+        self.len -= 1;
+
+        Some(item)
+    }
+
+    /// returns true when the item left its enlarged box and had to be moved on the tree
+    pub fn update(&mut self, proxy: ProxyId, bounds: Aabb) -> bool {
+        self.nodes[proxy].bounds = bounds;
+
+        if self.nodes[proxy].aabb.contains(&bounds) {
+            return false;
+        }
+
+        self.remove_leaf(proxy);
+        self.nodes[proxy].aabb = bounds.expanded(self.margin);
+        self.insert_leaf(proxy);
+
+        true
+    }
+
+    pub fn get(&self, proxy: ProxyId) -> Option<&T> {
+        self.nodes.get(proxy).and_then(|node| node.item.as_ref())
+    }
+
+    pub fn get_bounds(&self, proxy: ProxyId) -> Option<Aabb> {
+        self.get(proxy).map(|_| self.nodes[proxy].bounds)
+    }
+
+    /// every item whose exact bounds pass `overlaps`.
+    /// `overlaps` must also accept any box containing a passing box, as it prunes whole branches
+    pub fn query<F>(&self, overlaps: F) -> Vec<&T>
+    where
+        F: Fn(&Aabb) -> bool,
+    {
+        let mut result = Vec::new();
+        let mut stack = vec![self.root];
+
+        while let Some(index) = stack.pop() {
+            if index == NULL_NODE || !overlaps(&self.nodes[index].aabb) {
+                continue;
+            }
+
+            let node = &self.nodes[index];
+            if node.is_leaf() {
+                if overlaps(&node.bounds) {
+                    result.extend(node.item.as_ref());
+                }
+            } else {
+                stack.push(node.left);
+                stack.push(node.right);
+            }
+        }
+
+        result
+    }
+
+    pub fn query_aabb(&self, aabb: &Aabb) -> Vec<&T> {
+        self.query(|bounds| bounds.intersects_aabb(aabb))
+    }
+
+    pub fn query_sphere(&self, sphere: &Sphere) -> Vec<&T> {
+        self.query(|bounds| sphere.intersects_aabb(bounds))
+    }
+
+    pub fn query_frustum(&self, frustum: &Frustum) -> Vec<&T> {
+        self.query(|bounds| frustum.intersects_aabb(bounds))
+    }
+
+    /// items hit by the ray up to `max_distance`, closest first, with the distance to their bounds
+    pub fn query_ray(&self, ray: &Ray, max_distance: f32) -> Vec<(&T, f32)> {
+        let mut result = Vec::new();
+        let mut stack = vec![self.root];
+
+        while let Some(index) = stack.pop() {
+            if index == NULL_NODE {
+                continue;
+            }
+
+            let node = &self.nodes[index];
+            match ray.intersect_aabb(&node.aabb) {
+                Some(distance) if distance <= max_distance => (),
+                _ => continue,
+            };
+
+            if node.is_leaf() {
+                if let (Some(distance), Some(item)) =
+                    (ray.intersect_aabb(&node.bounds), node.item.as_ref())
+                {
+                    if distance <= max_distance {
+                        result.push((item, distance));
+                    }
+                }
+            } else {
+                stack.push(node.left);
+                stack.push(node.right);
+            }
+        }
+
+        result.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
+        result
+    }
+
+    fn allocate(&mut self, aabb: Aabb) -> usize {
+        let node = Node {
+            aabb,
+            bounds: aabb,
+            parent: NULL_NODE,
+            left: NULL_NODE,
+            right: NULL_NODE,
+            item: None,
+        };
+
+        match self.free.pop() {
+            Some(index) => {
+                self.nodes[index] = node;
+                index
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    fn release(&mut self, index: usize) {
+        self.nodes[index].item = None;
+        self.nodes[index].parent = NULL_NODE;
+        self.nodes[index].left = NULL_NODE;
+        self.nodes[index].right = NULL_NODE;
+        self.free.push(index);
+    }
+
+    /// walks down choosing the child that grows the least in surface area
+    fn find_sibling(&self, aabb: &Aabb) -> usize {
+        let mut index = self.root;
+
+        while !self.nodes[index].is_leaf() {
+            let node = &self.nodes[index];
+            let area = node.aabb.surface_area();
+            let combined_area = node.aabb.union(aabb).surface_area();
+
+            // cost of making a new parent for this node and the leaf
+            let cost = 2.0 * combined_area;
+            // cost of pushing the leaf further down the tree
+            let inheritance_cost = 2.0 * (combined_area - area);
+
+            let child_cost = |child: usize| {
+                let child = &self.nodes[child];
+                let union_area = child.aabb.union(aabb).surface_area();
+                if child.is_leaf() {
+                    union_area + inheritance_cost
+                } else {
+                    union_area - child.aabb.surface_area() + inheritance_cost
+                }
+            };
+
+            let cost_left = child_cost(node.left);
+            let cost_right = child_cost(node.right);
+
+            if cost < cost_left && cost < cost_right {
+                break;
+            }
+
+            index = if cost_left < cost_right {
+                node.left
+            } else {
+                node.right
+            };
+        }
+
+        index
+    }
+
+    fn insert_leaf(&mut self, leaf: usize) {
+        if self.root == NULL_NODE {
+            self.root = leaf;
+            self.nodes[leaf].parent = NULL_NODE;
+            return;
+        }
+
+        let leaf_aabb = self.nodes[leaf].aabb;
+        let sibling = self.find_sibling(&leaf_aabb);
+        let old_parent = self.nodes[sibling].parent;
+
+        let new_parent = self.allocate(self.nodes[sibling].aabb.union(&leaf_aabb));
+        self.nodes[new_parent].parent = old_parent;
+        self.nodes[new_parent].left = sibling;
+        self.nodes[new_parent].right = leaf;
+        self.nodes[sibling].parent = new_parent;
+        self.nodes[leaf].parent = new_parent;
+
+        if old_parent == NULL_NODE {
+            self.root = new_parent;
+        } else if self.nodes[old_parent].left == sibling {
+            self.nodes[old_parent].left = new_parent;
+        } else {
+            self.nodes[old_parent].right = new_parent;
+        }
+
+        self.refit(old_parent);
+    }
+
+    fn remove_leaf(&mut self, leaf: usize) {
+        if leaf == self.root {
+            self.root = NULL_NODE;
+            return;
+        }
+
+        let parent = self.nodes[leaf].parent;
+        let grand_parent = self.nodes[parent].parent;
+        let sibling = if self.nodes[parent].left == leaf {
+            self.nodes[parent].right
+        } else {
+            self.nodes[parent].left
+        };
+
+        self.nodes[sibling].parent = grand_parent;
+        if grand_parent == NULL_NODE {
+            self.root = sibling;
+        } else if self.nodes[grand_parent].left == parent {
+            self.nodes[grand_parent].left = sibling;
+        } else {
+            self.nodes[grand_parent].right = sibling;
+        }
+
+        self.nodes[leaf].parent = NULL_NODE;
+        self.release(parent);
+        self.refit(grand_parent);
+    }
+
+    fn refit(&mut self, mut index: usize) {
+        while index != NULL_NODE {
+            let left = self.nodes[index].left;
+            let right = self.nodes[index].right;
+            self.nodes[index].aabb = self.nodes[left].aabb.union(&self.nodes[right].aabb);
+            index = self.nodes[index].parent;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Vector3;
+    use crate::matrices::MatrixOperation;
+
+    fn unit_box(x: f32, y: f32, z: f32) -> Aabb {
+        Aabb::new(
+            Vector3::new(x - 0.5, y - 0.5, z - 0.5),
+            Vector3::new(x + 0.5, y + 0.5, z + 0.5),
+        )
+    }
+
+    fn grid() -> (SpatialIndex<usize>, Vec<Aabb>, Vec<ProxyId>) {
+        let mut index = SpatialIndex::new(0.1);
+        let mut boxes = Vec::new();
+        let mut proxies = Vec::new();
+
+        for i in 0..100 {
+            let aabb = unit_box((i % 10) as f32 * 3.0, 0.0, (i / 10) as f32 * 3.0);
+            proxies.push(index.insert(aabb, i));
+            boxes.push(aabb);
+        }
+
+        (index, boxes, proxies)
+    }
+
+    fn sorted(items: Vec<&usize>) -> Vec<usize> {
+        let mut items: Vec<usize> = items.into_iter().copied().collect();
+        items.sort();
+        items
+    }
+
+    fn check_tree(index: &SpatialIndex<usize>) {
+        let mut leaves = 0;
+        let mut stack = vec![index.root];
+
+        while let Some(node) = stack.pop() {
+            if node == NULL_NODE {
+                continue;
+            }
+
+            let current = &index.nodes[node];
+            if current.is_leaf() {
+                assert!(current.aabb.contains(&current.bounds));
+                leaves += 1;
+            } else {
+                for child in [current.left, current.right].iter() {
+                    assert_eq!(index.nodes[*child].parent, node);
+                    assert!(current.aabb.contains(&index.nodes[*child].aabb));
+                    stack.push(*child);
+                }
+            }
+        }
+
+        assert_eq!(leaves, index.len());
+    }
+
+    #[test]
+    fn spatial_index_insert_keeps_tree_valid() {
+        let (index, _, _) = grid();
+
+        assert_eq!(index.len(), 100);
+        check_tree(&index);
+    }
+
+    #[test]
+    fn spatial_index_query_aabb_matches_linear_scan() {
+        let (index, boxes, _) = grid();
+        let area = Aabb::new(Vector3::new(2.0, -1.0, 2.0), Vector3::new(10.0, 1.0, 7.0));
+
+        let expected: Vec<usize> = (0..100)
+            .filter(|i| boxes[*i].intersects_aabb(&area))
+            .collect();
+
+        assert_eq!(sorted(index.query_aabb(&area)), expected);
+        assert_eq!(expected.len(), 6);
+    }
+
+    #[test]
+    fn spatial_index_query_sphere() {
+        let (index, _, _) = grid();
+
+        let found = sorted(index.query_sphere(&Sphere::new(Vector3::new(3.0, 0.0, 3.0), 1.0)));
+
+        assert_eq!(found, vec![11]);
+    }
+
+    #[test]
+    fn spatial_index_query_frustum() {
+        let (index, boxes, _) = grid();
+        let view_projection = MatrixOperation::perspective(1.0, 45.0, 1.0, 100.0)
+            * MatrixOperation::camera_matrix(
+                Vector3::new(0.0, 2.0, -10.0),
+                Vector3::new(0.0, 0.0, 0.0),
+                Vector3::new(0.0, 1.0, 0.0),
+            );
+        let frustum = Frustum::from_matrix(view_projection);
+
+        let expected: Vec<usize> = (0..100)
+            .filter(|i| frustum.intersects_aabb(&boxes[*i]))
+            .collect();
+
+        assert_eq!(sorted(index.query_frustum(&frustum)), expected);
+        assert!(expected.len() < 100);
+    }
+
+    #[test]
+    fn spatial_index_query_ray_sorted_by_distance() {
+        let (index, _, _) = grid();
+        let ray = Ray::new(Vector3::new(-5.0, 0.0, 3.0), Vector3::new(1.0, 0.0, 0.0));
+
+        let hits = index.query_ray(&ray, 10.0);
+
+        let items: Vec<usize> = hits.iter().map(|(item, _)| **item).collect();
+        assert_eq!(items, vec![10, 11]);
+        assert_eq!(hits[0].1, 4.5);
+    }
+
+    #[test]
+    fn spatial_index_small_update_keeps_node() {
+        let (mut index, _, proxies) = grid();
+
+        let moved = index.update(proxies[5], unit_box(15.05, 0.0, 0.0));
+
+        assert!(!moved);
+        assert_eq!(index.get(proxies[5]), Some(&5));
+        assert_eq!(
+            index.get_bounds(proxies[5]),
+            Some(unit_box(15.05, 0.0, 0.0))
+        );
+        check_tree(&index);
+    }
+
+    #[test]
+    fn spatial_index_large_update_moves_node() {
+        let (mut index, _, _) = grid();
+        let proxy = index.insert(unit_box(100.0, 0.0, 0.0), 100);
+
+        let moved = index.update(proxy, unit_box(-50.0, 0.0, -50.0));
+
+        assert!(moved);
+        check_tree(&index);
+        assert_eq!(
+            sorted(index.query_sphere(&Sphere::new(Vector3::new(-50.0, 0.0, -50.0), 0.1))),
+            vec![100]
+        );
+        assert!(index
+            .query_sphere(&Sphere::new(Vector3::new(100.0, 0.0, 0.0), 0.1))
+            .is_empty());
+    }
+
+    #[test]
+    fn spatial_index_remove() {
+        let mut index = SpatialIndex::new(0.1);
+        let proxies: Vec<ProxyId> = (0..10)
+            .map(|i| index.insert(unit_box(i as f32 * 2.0, 0.0, 0.0), i))
+            .collect();
+
+        assert_eq!(index.remove(proxies[3]), Some(3));
+        assert_eq!(index.remove(proxies[3]), None);
+
+        assert_eq!(index.len(), 9);
+        check_tree(&index);
+        assert!(index
+            .query_sphere(&Sphere::new(Vector3::new(6.0, 0.0, 0.0), 0.1))
+            .is_empty());
+
+        for proxy in proxies {
+            index.remove(proxy);
+        }
+        assert!(index.is_empty());
+        assert!(index.query_aabb(&unit_box(0.0, 0.0, 0.0)).is_empty());
+    }
+}